@@ -0,0 +1,571 @@
+// benchmarks for each stage of the renderer and for whole frames, run with
+//
+//     cargo bench --bench pipeline [-- OPTIONS] [FILTER...]
+//
+// only the benchmarks whose names contain one of the filters are run. each
+// one is repeated for about --time seconds and the median time per run is
+// reported. to catch regressions, save the numbers from a known good build
+// with --save-baseline and compare a later run against them with --baseline,
+// which marks anything more than --threshold percent slower and exits with
+// an error if there was any
+//
+// options:
+//   --time <SECONDS>            how long to spend on each benchmark [default: 1]
+//   --save-baseline <FILE>      write the results to FILE
+//   --baseline <FILE>           compare the results against FILE
+//   --threshold <PERCENT>       slowdown that counts as a regression [default: 10]
+
+use std::{
+    env,
+    fs,
+    hint::black_box,
+    process,
+    time::{Duration, Instant},
+};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rust_3d_rendering::{
+    antialias::{AntiAliasing, anti_aliasing_name},
+    camera::{camera_projection_matrix, camera_view_matrix},
+    display::{Display, RenderMode, render_mode_name},
+    line::{HiddenLines, LineEdges, LineStyle, line_style_default},
+    light::Light,
+    material::BlendMode,
+    matrix::{
+        mat3_from_mat4, mat4_mul_mat4, mat4_mul_vec4, mat4_normal_matrix, mat4_transform_points,
+        mat4_transform_points_scalar,
+    },
+    mesh::{Mesh, face_vertex_indices, get_cube_mesh, get_sphere_mesh},
+    pipeline::{
+        AssemblySettings, assemble_scene_triangles, assemble_triangles, process_vertices, project_to_screen,
+        sort_triangles_by_depth, vertex_cache_new,
+    },
+    quaternion::{EulerOrder, quat_from_euler, quat_mul},
+    raster::{render_thread_count_default, render_triangles},
+    scene::{Scene, scene_add_mesh, scene_add_node, scene_add_texture, scene_new, scene_node_new, scene_update_world_matrices},
+    texture::{MeshTexture, Tex2},
+    triangle::{Triangle, draw_filled_triangle, draw_textured_triangle},
+    vector::{Vec3, Vec4},
+};
+use sdl2::pixels::Color;
+
+const RESOLUTIONS: [(usize, usize); 3] = [(320, 240), (800, 600), (1920, 1080)];
+
+// the sphere has about 40 thousand faces, roughly a detailed game model
+const SPHERE_RINGS: usize = 100;
+const SPHERE_SEGMENTS: usize = 200;
+
+// each timed sample runs the benchmark enough times to take at least this
+// long, so the timer's own overhead doesn't show up in fast benchmarks
+const MIN_SAMPLE_TIME: Duration = Duration::from_millis(2);
+
+struct BenchOptions {
+    filters: Vec<String>,
+    time: Duration,
+    save_baseline: Option<String>,
+    baseline: Option<String>,
+    threshold: f64,
+}
+
+struct BenchResult {
+    name: String,
+    median_ns: f64,
+    min_ns: f64,
+}
+
+struct Bencher {
+    options: BenchOptions,
+    results: Vec<BenchResult>,
+}
+
+impl Bencher {
+    // runs `f` over and over and records how long one run takes. `f` should
+    // hand anything it computes to black_box so the work isn't optimized away
+    fn bench<F: FnMut()>(&mut self, name: &str, mut f: F) {
+        if !self.options.filters.is_empty() && !self.options.filters.iter().any(|filter| name.contains(filter.as_str())) {
+            return;
+        }
+
+        // warm up the caches and find how many runs fill one sample
+        let mut runs_per_sample: u32 = 1;
+        loop {
+            let start = Instant::now();
+            for _ in 0..runs_per_sample {
+                f();
+            }
+            if start.elapsed() >= MIN_SAMPLE_TIME || runs_per_sample >= 1 << 20 {
+                break;
+            }
+            runs_per_sample *= 2;
+        }
+
+        let mut samples = Vec::new();
+        let bench_start = Instant::now();
+        while samples.len() < 5 || bench_start.elapsed() < self.options.time {
+            let start = Instant::now();
+            for _ in 0..runs_per_sample {
+                f();
+            }
+            samples.push(start.elapsed().as_nanos() as f64 / runs_per_sample as f64);
+        }
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let result = BenchResult {
+            name: name.to_string(),
+            median_ns: samples[samples.len() / 2],
+            min_ns: samples[0],
+        };
+        println!(
+            "{:<52} median {:>12}   min {:>12}   ({} samples of {})",
+            result.name, format_time(result.median_ns), format_time(result.min_ns), samples.len(), runs_per_sample);
+        self.results.push(result);
+    }
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(2);
+        }
+    };
+    let mut bencher = Bencher { options, results: Vec::new() };
+
+    bench_vertex_stage(&mut bencher);
+    bench_transform_points(&mut bencher);
+    bench_uncached_vertex_stage(&mut bencher);
+    bench_primitive_assembly(&mut bencher);
+    bench_sort(&mut bencher);
+    bench_clear(&mut bencher);
+    bench_rasterize(&mut bencher);
+    bench_render_triangles(&mut bencher);
+    bench_anti_aliasing(&mut bencher);
+    bench_line_styles(&mut bencher);
+    bench_frame(&mut bencher);
+
+    if let Some(path) = &bencher.options.save_baseline {
+        let mut out = String::new();
+        for result in &bencher.results {
+            out.push_str(&format!("{},{}\n", result.name, result.median_ns));
+        }
+        if let Err(e) = fs::write(path, out) {
+            eprintln!("Failed to write baseline '{}': {}", path, e);
+            process::exit(1);
+        }
+        println!("\nSaved the results to {}", path);
+    }
+
+    if let Some(path) = &bencher.options.baseline {
+        if !compare_to_baseline(&bencher, path) {
+            process::exit(1);
+        }
+    }
+}
+
+fn parse_args() -> Result<BenchOptions, String> {
+    let mut options = BenchOptions {
+        filters: Vec::new(),
+        time: Duration::from_secs(1),
+        save_baseline: None,
+        baseline: None,
+        threshold: 10.0,
+    };
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        i += 1;
+        let mut value = || {
+            i += 1;
+            args.get(i - 1).cloned().ok_or(format!("{} needs a value", arg))
+        };
+        match arg {
+            // cargo bench passes this to every bench binary
+            "--bench" => {},
+            "--time" => {
+                let seconds: f64 = value()?.parse().map_err(|_| "--time should be a number of seconds".to_string())?;
+                options.time = Duration::from_secs_f64(seconds.max(0.0));
+            },
+            "--save-baseline" => options.save_baseline = Some(value()?),
+            "--baseline" => options.baseline = Some(value()?),
+            "--threshold" => {
+                options.threshold = value()?.parse().map_err(|_| "--threshold should be a percentage".to_string())?;
+            },
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+            _ => options.filters.push(arg.to_string()),
+        }
+    }
+    Ok(options)
+}
+
+// prints how each result changed since the baseline and returns false if
+// anything got slower by more than the threshold
+fn compare_to_baseline(bencher: &Bencher, path: &str) -> bool {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Failed to read baseline '{}': {}", path, e);
+            return false;
+        }
+    };
+
+    println!("\nCompared to {}:", path);
+    let mut regressions = 0;
+    for line in text.lines() {
+        let (name, median) = match line.split_once(',') {
+            Some((name, median)) => (name, median.parse::<f64>().unwrap_or(0.0)),
+            None => continue,
+        };
+        let result = match bencher.results.iter().find(|result| result.name == name) {
+            Some(result) => result,
+            None => continue,
+        };
+        let change = (result.median_ns / median - 1.0) * 100.0;
+        let regressed = change > bencher.options.threshold;
+        if regressed {
+            regressions += 1;
+        }
+        println!(
+            "{:<52} {:>12} -> {:>12}   {:+6.1}%{}",
+            name, format_time(median), format_time(result.median_ns), change,
+            if regressed { "   REGRESSED" } else { "" });
+    }
+
+    if regressions > 0 {
+        println!("\n{} benchmarks are more than {}% slower", regressions, bencher.options.threshold);
+    }
+    regressions == 0
+}
+
+fn format_time(ns: f64) -> String {
+    if ns >= 1e6 {
+        format!("{:.3} ms", ns / 1e6)
+    } else if ns >= 1e3 {
+        format!("{:.3} us", ns / 1e3)
+    } else {
+        format!("{:.1} ns", ns)
+    }
+}
+
+// the standard scenes, each a single mesh spun so some faces point away
+fn standard_meshes() -> Vec<(&'static str, Mesh)> {
+    vec![
+        ("cube", get_cube_mesh()),
+        ("sphere", get_sphere_mesh(SPHERE_RINGS, SPHERE_SEGMENTS)),
+    ]
+}
+
+fn checker_texture(size: usize) -> MeshTexture {
+    let mut pixels = Vec::with_capacity(size * size);
+    for y in 0..size {
+        for x in 0..size {
+            let value = if (x / 8 + y / 8) % 2 == 0 { 255 } else { 64 };
+            pixels.push(Color::RGBA(value, value, value, 255));
+        }
+    }
+    MeshTexture { width: size, height: size, pixels }
+}
+
+fn standard_scene(mesh: Mesh) -> Scene {
+    let mut scene = scene_new();
+    let mesh = scene_add_mesh(&mut scene, "mesh", "", mesh);
+    let texture = scene_add_texture(&mut scene, "checker", "", checker_texture(64));
+
+    let mut node = scene_node_new("mesh");
+    node.mesh = Some(mesh);
+    node.material.texture = Some(texture);
+    node.transform.translation.z = 4.0;
+    node.transform.rotation = quat_from_euler(0.4, 0.6, 0.0, EulerOrder::XYZ);
+    scene_add_node(&mut scene, None, node);
+
+    scene.lights.push(Light { direction: Vec3 { x: 0.3, y: -0.5, z: 0.8 } });
+    scene_update_world_matrices(&mut scene);
+    scene
+}
+
+fn assembled_triangles(scene: &Scene, width: usize, height: usize) -> Vec<Triangle> {
+    let mut cache = vertex_cache_new(0);
+    let mut triangles = Vec::new();
+    assemble_scene_triangles(&mut triangles, scene, &mut cache, width, height, true);
+    sort_triangles_by_depth(&mut triangles);
+    triangles
+}
+
+fn bench_vertex_stage(bencher: &mut Bencher) {
+    for (name, mesh) in standard_meshes() {
+        let scene = standard_scene(mesh);
+        let node = &scene.nodes[0];
+        let view_matrix = camera_view_matrix(&scene.camera);
+        let model_view_matrix = mat4_mul_mat4(&view_matrix, &node.world_matrix);
+        let proj_matrix = camera_projection_matrix(&scene.camera, 800, 600);
+        let vertices = &scene.meshes[0].vertices;
+        let mut cache = vertex_cache_new(vertices.len());
+
+        bencher.bench(&format!("vertex_stage/{}", name), || {
+            process_vertices(&mut cache, vertices, &model_view_matrix, &proj_matrix, 800, 600);
+            black_box(&cache);
+        });
+    }
+}
+
+// mat4_transform_points against the plain loop it falls back on without SSE
+fn bench_transform_points(bencher: &mut Bencher) {
+    for (name, mesh) in standard_meshes() {
+        let scene = standard_scene(mesh);
+        let world_matrix = scene.nodes[0].world_matrix;
+        let vertices = &scene.meshes[0].vertices;
+        let mut out = Vec::with_capacity(vertices.len());
+
+        bencher.bench(&format!("transform_points/{}", name), || {
+            mat4_transform_points(&world_matrix, vertices, &mut out);
+            black_box(&out);
+        });
+        bencher.bench(&format!("transform_points_scalar/{}", name), || {
+            mat4_transform_points_scalar(&world_matrix, vertices, &mut out);
+            black_box(&out);
+        });
+    }
+}
+
+// the vertex stage without the cache, transforming and projecting the three
+// corners of every face on their own, for comparing against vertex_stage
+fn bench_uncached_vertex_stage(bencher: &mut Bencher) {
+    for (name, mesh) in standard_meshes() {
+        let scene = standard_scene(mesh);
+        let view_matrix = camera_view_matrix(&scene.camera);
+        let model_view_matrix = mat4_mul_mat4(&view_matrix, &scene.nodes[0].world_matrix);
+        let proj_matrix = camera_projection_matrix(&scene.camera, 800, 600);
+        let mesh = &scene.meshes[0];
+        let mut corners: Vec<[Vec4; 3]> = Vec::with_capacity(mesh.faces.len());
+
+        bencher.bench(&format!("uncached_vertex_stage/{}", name), || {
+            corners.clear();
+            for face in &mesh.faces {
+                corners.push(face_vertex_indices(face).map(|index| {
+                    let vertex = mesh.vertices[index];
+                    let point = Vec4 { x: vertex.x, y: vertex.y, z: vertex.z, w: 1.0 };
+                    project_to_screen(&proj_matrix, &mat4_mul_vec4(&model_view_matrix, &point), 800, 600)
+                }));
+            }
+            black_box(&corners);
+        });
+    }
+}
+
+fn bench_primitive_assembly(bencher: &mut Bencher) {
+    for (name, mesh) in standard_meshes() {
+        let scene = standard_scene(mesh);
+        let node = &scene.nodes[0];
+        let view_matrix = camera_view_matrix(&scene.camera);
+        let model_view_matrix = mat4_mul_mat4(&view_matrix, &node.world_matrix);
+        let normal_matrix = mat4_normal_matrix(&model_view_matrix).unwrap_or_else(|| mat3_from_mat4(&model_view_matrix));
+        let proj_matrix = camera_projection_matrix(&scene.camera, 800, 600);
+        let mesh = &scene.meshes[0];
+        let mesh_edges = &scene.mesh_edges[0];
+        let mut cache = vertex_cache_new(mesh.vertices.len());
+        process_vertices(&mut cache, &mesh.vertices, &model_view_matrix, &proj_matrix, 800, 600);
+        let settings = AssemblySettings {
+            camera_position: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+            lights: &scene.lights,
+            backface_culling_enabled: true,
+        };
+        let mut triangles = Vec::with_capacity(mesh.faces.len());
+
+        bencher.bench(&format!("primitive_assembly/{}", name), || {
+            triangles.clear();
+            assemble_triangles(&mut triangles, mesh, mesh_edges, &node.material, &mut cache, &normal_matrix, &settings);
+            black_box(&triangles);
+        });
+    }
+}
+
+fn bench_sort(bencher: &mut Bencher) {
+    let scene = standard_scene(get_sphere_mesh(SPHERE_RINGS, SPHERE_SEGMENTS));
+    let mut unsorted = assembled_triangles(&scene, 800, 600);
+    // shuffle so every run sorts from the same mixed up order
+    let mut rng = StdRng::seed_from_u64(38);
+    for i in (1..unsorted.len()).rev() {
+        unsorted.swap(i, rng.gen_range(0..=i));
+    }
+    let mut triangles = unsorted.clone();
+
+    bencher.bench("sort_triangles_by_depth/sphere", || {
+        triangles.clone_from(&unsorted);
+        sort_triangles_by_depth(&mut triangles);
+        black_box(&triangles);
+    });
+}
+
+fn bench_clear(bencher: &mut Bencher) {
+    for (width, height) in RESOLUTIONS {
+        let mut display = Display::new_headless(width, height);
+        bencher.bench(&format!("clear_color_buffer/{}x{}", width, height), || {
+            display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+            black_box(&display);
+        });
+        bencher.bench(&format!("clear_depth_buffer/{}x{}", width, height), || {
+            display.clear_depth_buffer();
+            black_box(&display);
+        });
+    }
+}
+
+// random triangles of about the given size in pixels, all inside the screen
+fn random_triangles(rng: &mut StdRng, count: usize, size: f32, width: usize, height: usize) -> Vec<[Vec4; 3]> {
+    (0..count)
+        .map(|_| {
+            let center_x = rng.gen_range(size..(width as f32 - size).max(size + 1.0));
+            let center_y = rng.gen_range(size..(height as f32 - size).max(size + 1.0));
+            let mut corner = || Vec4 {
+                x: (center_x + rng.gen_range(-size..size)).floor(),
+                y: (center_y + rng.gen_range(-size..size)).floor(),
+                z: 0.0,
+                w: rng.gen_range(2.0..20.0),
+            };
+            [corner(), corner(), corner()]
+        })
+        .collect()
+}
+
+// small triangles are about the size of the faces of a detailed model, large
+// ones cover around a quarter of the screen. the depth buffer is cleared each
+// run so every pixel passes the depth test and gets written
+fn bench_rasterize(bencher: &mut Bencher) {
+    let texture = checker_texture(64);
+    let texcoords = [Tex2 { u: 0.0, v: 0.0 }, Tex2 { u: 0.0, v: 1.0 }, Tex2 { u: 1.0, v: 1.0 }];
+    for (width, height) in RESOLUTIONS {
+        let mut rng = StdRng::seed_from_u64(width as u64);
+        let sets = [
+            ("small", random_triangles(&mut rng, 1000, 8.0, width, height)),
+            ("large", random_triangles(&mut rng, 10, height as f32 / 3.0, width, height)),
+        ];
+        let mut display = Display::new_headless(width, height);
+
+        for (size_name, triangles) in &sets {
+            bencher.bench(&format!("draw_filled_triangle/{}x{}/{}", width, height, size_name), || {
+                display.clear_depth_buffer();
+                let mut tile = display.full_frame();
+                for points in triangles {
+                    draw_filled_triangle(&mut tile, points, Color::RGBA(200, 100, 50, 255), BlendMode::Opaque);
+                }
+                black_box(&display);
+            });
+
+            bencher.bench(&format!("draw_textured_triangle/{}x{}/{}", width, height, size_name), || {
+                display.clear_depth_buffer();
+                let mut tile = display.full_frame();
+                for points in triangles {
+                    draw_textured_triangle(&mut tile, points, &texcoords, &texture, 255, BlendMode::Opaque);
+                }
+                black_box(&display);
+            });
+        }
+    }
+}
+
+fn bench_render_triangles(bencher: &mut Bencher) {
+    // the default thread count is one on a single core machine
+    let mut thread_counts = vec![1, render_thread_count_default()];
+    thread_counts.dedup();
+    for (name, mesh) in standard_meshes() {
+        let scene = standard_scene(mesh);
+        for (width, height) in RESOLUTIONS {
+            let triangles = assembled_triangles(&scene, width, height);
+            let mut display = Display::new_headless(width, height);
+            for render_mode in [RenderMode::FilledTriangles, RenderMode::Textured] {
+                for &thread_count in &thread_counts {
+                    let bench_name = format!(
+                        "render_triangles/{}/{}x{}/{}/{}_threads",
+                        name, width, height, render_mode_name(render_mode), thread_count);
+                    bencher.bench(&bench_name, || {
+                        display.clear_depth_buffer();
+                        render_triangles(&mut display, &triangles, render_mode, &scene.textures, thread_count);
+                        black_box(&display);
+                    });
+                }
+            }
+        }
+    }
+}
+
+// rasterizing with each kind of anti-aliasing, including averaging the samples
+// into the colour buffer. only at the middle resolution, supersampling 1080p
+// four times over takes too long to be worth waiting for
+fn bench_anti_aliasing(bencher: &mut Bencher) {
+    let thread_count = render_thread_count_default();
+    let (width, height) = RESOLUTIONS[1];
+    for (name, mesh) in standard_meshes() {
+        let scene = standard_scene(mesh);
+        let triangles = assembled_triangles(&scene, width, height);
+        let mut display = Display::new_headless(width, height);
+        for anti_aliasing in [
+            AntiAliasing::Off, AntiAliasing::Supersample(2), AntiAliasing::Multisample(4), AntiAliasing::Multisample(8)] {
+            display.set_anti_aliasing(anti_aliasing).unwrap();
+            let bench_name = format!(
+                "anti_aliasing/{}/{}x{}/{}", name, width, height, anti_aliasing_name(anti_aliasing));
+            bencher.bench(&bench_name, || {
+                display.clear_depth_buffer();
+                render_triangles(&mut display, &triangles, RenderMode::Textured, &scene.textures, thread_count);
+                black_box(&display);
+            });
+        }
+    }
+}
+
+// the wireframe drawn with each kind of line, the hidden lines one including
+// the pass that fills in the depth buffer for the lines to be tested against
+fn bench_line_styles(bencher: &mut Bencher) {
+    let thread_count = render_thread_count_default();
+    let (width, height) = RESOLUTIONS[1];
+    let line_styles = [
+        ("stepped", line_style_default()),
+        ("smooth", LineStyle { smooth: true, ..line_style_default() }),
+        ("wide", LineStyle { width: 3.0, smooth: true, ..line_style_default() }),
+        ("hidden", LineStyle { hidden: HiddenLines::Hide, ..line_style_default() }),
+        ("dashed", LineStyle { hidden: HiddenLines::Dashed, ..line_style_default() }),
+        ("outline", LineStyle { hidden: HiddenLines::Hide, edges: LineEdges::Outline, ..line_style_default() }),
+    ];
+    for (name, mesh) in standard_meshes() {
+        let scene = standard_scene(mesh);
+        let triangles = assembled_triangles(&scene, width, height);
+        let mut display = Display::new_headless(width, height);
+        for (style_name, line_style) in line_styles {
+            display.set_line_style(line_style);
+            bencher.bench(&format!("lines/{}/{}x{}/{}", name, width, height, style_name), || {
+                display.clear_depth_buffer();
+                render_triangles(&mut display, &triangles, RenderMode::Wireframe, &scene.textures, thread_count);
+                black_box(&display);
+            });
+        }
+    }
+}
+
+// everything the viewer does for a frame apart from showing it: clearing,
+// updating the scene, both pipeline stages, sorting and rasterizing
+fn bench_frame(bencher: &mut Bencher) {
+    let thread_count = render_thread_count_default();
+    for (name, mesh) in standard_meshes() {
+        let mut scene = standard_scene(mesh);
+        let mut cache = vertex_cache_new(scene.meshes[0].vertices.len());
+        let mut triangles = Vec::new();
+        let frame_rotation = quat_from_euler(0.01, 0.01, 0.01, EulerOrder::XYZ);
+
+        for (width, height) in RESOLUTIONS {
+            let mut display = Display::new_headless(width, height);
+            bencher.bench(&format!("frame/{}/{}x{}", name, width, height), || {
+                display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+                display.clear_depth_buffer();
+
+                let transform = &mut scene.nodes[0].transform;
+                transform.rotation = quat_mul(&transform.rotation, &frame_rotation);
+                scene_update_world_matrices(&mut scene);
+                assemble_scene_triangles(&mut triangles, &scene, &mut cache, width, height, true);
+                sort_triangles_by_depth(&mut triangles);
+                render_triangles(&mut display, &triangles, RenderMode::Textured, &scene.textures, thread_count);
+                black_box(&display);
+            });
+        }
+    }
+}
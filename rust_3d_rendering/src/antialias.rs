@@ -0,0 +1,137 @@
+// full screen anti-aliasing. both modes keep several samples for every
+// screen pixel and average them into the colour buffer once the triangles are
+// drawn, they differ in how much work each sample costs:
+//
+//   supersampling    draws the whole frame at n times the width and height,
+//                    so every sample is shaded. works for everything, but
+//                    costs n * n times as much
+//   multisampling    works out which of the sample points of a pixel a
+//                    triangle covers and how deep it is at each of them, but
+//                    shades the pixel only once and copies the colour to the
+//                    covered samples. only the edges of triangles are
+//                    smoothed, not the inside of textures
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AntiAliasing {
+    Off,
+    // the number of samples across and down each pixel
+    Supersample(usize),
+    // the number of samples in each pixel, one of MULTISAMPLE_PATTERNS
+    Multisample(usize),
+}
+
+// the names used in scene files and on the command line
+pub const ANTI_ALIASING_NAMES: [(&str, AntiAliasing); 7] = [
+    ("off", AntiAliasing::Off),
+    ("ssaa2", AntiAliasing::Supersample(2)),
+    ("ssaa3", AntiAliasing::Supersample(3)),
+    ("ssaa4", AntiAliasing::Supersample(4)),
+    ("msaa2", AntiAliasing::Multisample(2)),
+    ("msaa4", AntiAliasing::Multisample(4)),
+    ("msaa8", AntiAliasing::Multisample(8)),
+];
+
+pub fn anti_aliasing_from_name(name: &str) -> Option<AntiAliasing> {
+    ANTI_ALIASING_NAMES
+        .iter()
+        .find(|(aa_name, _)| *aa_name == name)
+        .map(|(_, anti_aliasing)| *anti_aliasing)
+}
+
+pub fn anti_aliasing_name(anti_aliasing: AntiAliasing) -> &'static str {
+    ANTI_ALIASING_NAMES
+        .iter()
+        .find(|(_, aa)| *aa == anti_aliasing)
+        .map(|(aa_name, _)| *aa_name)
+        .unwrap_or("off")
+}
+
+pub const MAX_SAMPLES: usize = 8;
+
+// the most memory the sample colour and depth buffers may take together.
+// supersampling multiplies the pixels by the factor squared, so a big frame
+// can need far more than there is
+pub const MAX_SAMPLE_BUFFER_BYTES: usize = 1 << 31;
+
+// a bgra colour and a depth for each sample
+const SAMPLE_BYTES: usize = 4 + std::mem::size_of::<f32>();
+
+// how many samples anti-aliasing a `width` by `height` frame keeps, or an
+// error when their buffers would be bigger than MAX_SAMPLE_BUFFER_BYTES
+pub fn anti_aliasing_sample_count(anti_aliasing: AntiAliasing, width: usize, height: usize) -> Result<usize, String> {
+    let samples_per_pixel = match anti_aliasing {
+        AntiAliasing::Off => return Ok(0),
+        AntiAliasing::Supersample(factor) => factor * factor,
+        AntiAliasing::Multisample(samples) => samples,
+    };
+    width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(samples_per_pixel))
+        .filter(|samples| samples.checked_mul(SAMPLE_BYTES).is_some_and(|bytes| bytes <= MAX_SAMPLE_BUFFER_BYTES))
+        .ok_or_else(|| format!(
+            "{} at {}x{} needs more than the {} MiB allowed for the samples",
+            anti_aliasing_name(anti_aliasing), width, height, MAX_SAMPLE_BUFFER_BYTES >> 20))
+}
+
+// where the samples sit in a pixel, as offsets from the pixel's own point.
+// these are the usual rotated grid patterns graphics cards use, so no two
+// samples share a row or a column and edges close to horizontal or vertical
+// still get several steps of coverage
+const MULTISAMPLE_PATTERNS: [&[(f32, f32)]; 3] = [
+    &[(0.25, 0.25), (-0.25, -0.25)],
+    &[(-0.125, -0.375), (0.375, -0.125), (-0.375, 0.125), (0.125, 0.375)],
+    &[
+        (0.0625, -0.1875), (-0.0625, 0.1875), (0.3125, 0.0625), (-0.1875, -0.3125),
+        (-0.3125, 0.3125), (-0.4375, -0.0625), (0.1875, 0.4375), (0.4375, -0.4375),
+    ],
+];
+
+// the sample offsets for a pixel with `sample_count` samples, or just the
+// pixel's own point when it isn't multisampled
+pub fn sample_offsets(sample_count: usize) -> &'static [(f32, f32)] {
+    MULTISAMPLE_PATTERNS
+        .iter()
+        .find(|pattern| pattern.len() == sample_count)
+        .copied()
+        .unwrap_or(&[(0.0, 0.0)])
+}
+
+// averages each `factor` by `factor` block of the bgra `samples`, which are
+// `width * factor` pixels across, into one pixel of `pixels`
+pub fn downsample_box(samples: &[u8], pixels: &mut [u8], width: usize, factor: usize) {
+    let sample_width = width * factor;
+    let count = (factor * factor) as u32;
+    for (pixel_index, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (pixel_index % width, pixel_index / width);
+        let mut sums = [0u32; 4];
+        for sample_y in y * factor..(y + 1) * factor {
+            let row = sample_y * sample_width;
+            for sample_x in x * factor..(x + 1) * factor {
+                let index = (row + sample_x) * 4;
+                for (sum, value) in sums.iter_mut().zip(&samples[index..index + 4]) {
+                    *sum += *value as u32;
+                }
+            }
+        }
+        for (channel, sum) in pixel.iter_mut().zip(sums) {
+            *channel = ((sum + count / 2) / count) as u8;
+        }
+    }
+}
+
+// averages the `sample_count` bgra samples stored one after another for each
+// pixel into that pixel of `pixels`
+pub fn downsample_multisampled(samples: &[u8], pixels: &mut [u8], sample_count: usize) {
+    let count = sample_count as u32;
+    for (pixel, pixel_samples) in pixels.chunks_exact_mut(4).zip(samples.chunks_exact(4 * sample_count)) {
+        let mut sums = [0u32; 4];
+        for sample in pixel_samples.chunks_exact(4) {
+            for (sum, value) in sums.iter_mut().zip(sample) {
+                *sum += *value as u32;
+            }
+        }
+        for (channel, sum) in pixel.iter_mut().zip(sums) {
+            *channel = ((sum + count / 2) / count) as u8;
+        }
+    }
+}
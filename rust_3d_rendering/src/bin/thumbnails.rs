@@ -0,0 +1,493 @@
+// renders preview images of .obj files without opening a window. every model
+// is centred and framed from its bounds, then drawn from one three quarter
+// view or from several angles around it like a turntable. each image is
+// saved as soon as it's drawn, and a shrunk copy is kept for a contact sheet
+// of all of them. a manifest.json lists what went where
+
+use std::{
+    env,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use image::{GenericImage, ImageBuffer, Rgba, RgbaImage, imageops::{self, FilterType}};
+use rust_3d_rendering::{
+    display::{Display, RenderSettings, render_mode_from_name, render_mode_name, render_settings_default, RENDER_MODE_NAMES},
+    light::Light,
+    mesh::{Mesh, load_obj_file, mesh_bounds},
+    pipeline::{assemble_scene_triangles, sort_triangles_by_depth, vertex_cache_new},
+    quaternion::{quat_from_axis_angle, quat_rotate_vec3},
+    raster::{render_thread_count_default, render_triangles},
+    scene::{Scene, scene_add_mesh, scene_add_node, scene_new, scene_node_new, scene_update_world_matrices},
+    vector::{Vec3, vec3_distance, vec3_normalize_or_zero},
+};
+use sdl2::pixels::Color;
+
+const USAGE: &str = "\
+Usage: thumbnails [OPTIONS] <MODEL.obj or DIRECTORY>...
+
+Renders preview images of .obj files. Directories are searched for .obj files,
+including in their subdirectories.
+
+Options:
+  --out <DIR>             directory to write the images and manifest.json to [default: thumbnails]
+  --size <WxH>            size of each image [default: 256x256]
+  --angles <N>            1 renders one three quarter view, more than 1 renders a turntable
+                          of N views evenly spaced around the model [default: 1]
+  --mode <MODE>           render mode, one of: wireframe_dot, wireframe, filled,
+                          filled_wireframe, textured, textured_wireframe, depth, normals,
+                          uvs, triangle_index, overdraw [default: filled]
+  --culling <on|off>      backface culling [default: on]
+  --columns <N>           images per row of the contact sheet [default: the number of
+                          angles for a turntable, otherwise enough to make it about square].
+                          images bigger than 128 pixels are shrunk to fit on the sheet
+  -h, --help              show this help";
+
+// how far the camera looks down on the model, and where the first view of
+// the turntable starts from
+const VIEW_PITCH_DEGREES: f32 = 25.0;
+const VIEW_YAW_DEGREES: f32 = 45.0;
+
+// space left around the model so its bounding sphere doesn't touch the edges
+const FRAMING_MARGIN: f32 = 1.1;
+
+const BACKGROUND_COLOR: Color = Color::RGBA(40, 40, 40, 255);
+
+// the longest side of an image on the contact sheet. bigger images are shrunk
+// to fit, so the sheet of a big batch stays a sensible size and only the
+// small copies are kept around until it's made
+const SHEET_CELL_SIZE: u32 = 128;
+
+struct ThumbnailOptions {
+    inputs: Vec<String>,
+    out_dir: String,
+    width: usize,
+    height: usize,
+    angle_count: usize,
+    settings: RenderSettings,
+    columns: Option<usize>,
+    show_help: bool,
+}
+
+// what happened to one model, for the manifest
+struct ModelResult {
+    path: String,
+    vertex_count: usize,
+    face_count: usize,
+    bounds: Option<(Vec3, Vec3)>,
+    images: Vec<String>,
+    error: Option<String>,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {}\n\nRun with --help to see the options.", e);
+            process::exit(2);
+        }
+    };
+    if options.show_help {
+        println!("{}", USAGE);
+        return;
+    }
+
+    if let Err(e) = run(&options) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn run(options: &ThumbnailOptions) -> Result<(), String> {
+    let mut model_paths = Vec::new();
+    for input in &options.inputs {
+        let path = Path::new(input);
+        if path.is_dir() {
+            find_obj_files(path, &mut model_paths)?;
+        } else {
+            model_paths.push(path.to_path_buf());
+        }
+    }
+    if model_paths.is_empty() {
+        return Err("no .obj files found".to_string());
+    }
+
+    let out_dir = Path::new(&options.out_dir);
+    if let Err(e) = fs::create_dir_all(out_dir) {
+        return Err(format!("Failed to create the output directory '{}': {}", options.out_dir, e));
+    }
+
+    let angles = view_angles(options.angle_count);
+    let (cell_width, cell_height) = sheet_cell_size(options.width as u32, options.height as u32);
+    let mut display = Display::new_headless(options.width, options.height);
+    let mut used_names: Vec<String> = Vec::new();
+    let mut results = Vec::new();
+    let mut sheet_images: Vec<RgbaImage> = Vec::new();
+
+    for model_path in &model_paths {
+        let path_string = model_path.to_string_lossy().to_string();
+        println!("{}", path_string);
+
+        let mesh = match load_obj_file(&path_string) {
+            Ok(mesh) => mesh,
+            Err(e) => {
+                eprintln!("  skipped: {}", e);
+                results.push(ModelResult {
+                    path: path_string,
+                    vertex_count: 0,
+                    face_count: 0,
+                    bounds: None,
+                    images: Vec::new(),
+                    error: Some(e),
+                });
+                continue;
+            }
+        };
+
+        let mut result = ModelResult {
+            path: path_string,
+            vertex_count: mesh.vertices.len(),
+            face_count: mesh.faces.len(),
+            bounds: mesh_bounds(&mesh),
+            images: Vec::new(),
+            error: None,
+        };
+        let base_name = unique_name(model_path, &mut used_names);
+        let (mut scene, turntable_node) = framed_scene(mesh, options.width, options.height);
+
+        for (angle_index, &angle) in angles.iter().enumerate() {
+            scene.nodes[turntable_node].transform.rotation =
+                quat_from_axis_angle(&Vec3 { x: 0.0, y: 1.0, z: 0.0 }, angle.to_radians());
+            render_scene(&mut display, &mut scene, &options.settings);
+
+            let file_name = if angles.len() == 1 {
+                format!("{}.png", base_name)
+            } else {
+                format!("{}_{:03}.png", base_name, angle_index)
+            };
+            display.save_color_buffer_png(&out_dir.join(&file_name))?;
+
+            let image: RgbaImage = ImageBuffer::from_raw(
+                options.width as u32, options.height as u32, display.color_buffer_rgba()).unwrap();
+            if (cell_width, cell_height) == image.dimensions() {
+                sheet_images.push(image);
+            } else {
+                sheet_images.push(imageops::resize(&image, cell_width, cell_height, FilterType::Triangle));
+            }
+            result.images.push(file_name);
+        }
+        results.push(result);
+    }
+
+    let columns = match options.columns {
+        Some(columns) => columns,
+        None if angles.len() > 1 => angles.len(),
+        None => (sheet_images.len() as f32).sqrt().ceil().max(1.0) as usize,
+    };
+    if !sheet_images.is_empty() {
+        let sheet = contact_sheet(&sheet_images, columns, cell_width, cell_height);
+        let sheet_path = out_dir.join("contact_sheet.png");
+        if let Err(e) = sheet.save(&sheet_path) {
+            return Err(format!("Failed to save image '{}': {}", sheet_path.display(), e));
+        }
+    }
+
+    let sheet_layout = if sheet_images.is_empty() { None } else { Some((columns, cell_width, cell_height)) };
+    let manifest = manifest_json(options, &angles, sheet_layout, &results);
+    let manifest_path = out_dir.join("manifest.json");
+    if let Err(e) = fs::write(&manifest_path, manifest) {
+        return Err(format!("Failed to write '{}': {}", manifest_path.display(), e));
+    }
+
+    let failed = results.iter().filter(|result| result.error.is_some()).count();
+    println!(
+        "Rendered {} of {} models into {}",
+        results.len() - failed, results.len(), out_dir.display());
+    Ok(())
+}
+
+fn parse_args(args: &[String]) -> Result<ThumbnailOptions, String> {
+    let mut options = ThumbnailOptions {
+        inputs: Vec::new(),
+        out_dir: "thumbnails".to_string(),
+        width: 256,
+        height: 256,
+        angle_count: 1,
+        settings: render_settings_default(),
+        columns: None,
+        show_help: false,
+    };
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        i += 1;
+
+        if !arg.starts_with('-') {
+            options.inputs.push(arg.to_string());
+            continue;
+        }
+        // only here, where an option is expected, so `--out -h` is still a
+        // folder called -h
+        if arg == "-h" || arg == "--help" {
+            options.show_help = true;
+            continue;
+        }
+
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, value.to_string()),
+            None => {
+                if i >= args.len() {
+                    return Err(format!("{} needs a value", arg));
+                }
+                i += 1;
+                (arg, args[i - 1].clone())
+            }
+        };
+
+        match name {
+            "--out" => options.out_dir = value,
+            "--size" => {
+                let parsed = value.split_once('x')
+                    .and_then(|(width, height)| Some((width.parse::<usize>().ok()?, height.parse::<usize>().ok()?)));
+                match parsed {
+                    Some((width, height)) if width > 0 && height > 0 => {
+                        options.width = width;
+                        options.height = height;
+                    },
+                    _ => return Err(format!("--size should look like 256x256, not '{}'", value)),
+                }
+            },
+            "--angles" => {
+                options.angle_count = match value.parse::<usize>() {
+                    Ok(count) if count > 0 => count,
+                    _ => return Err(format!("--angles should be a whole number above 0, not '{}'", value)),
+                };
+            },
+            "--mode" => {
+                options.settings.render_mode = match render_mode_from_name(&value) {
+                    Some(mode) => mode,
+                    None => {
+                        let names: Vec<&str> = RENDER_MODE_NAMES.iter().map(|(name, _)| *name).collect();
+                        return Err(format!("unknown render mode '{}', expected one of: {}", value, names.join(", ")));
+                    }
+                };
+            },
+            "--culling" => {
+                options.settings.backface_culling_enabled = match value.as_str() {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(format!("--culling should be 'on' or 'off', not '{}'", value)),
+                };
+            },
+            "--columns" => {
+                options.columns = match value.parse::<usize>() {
+                    Ok(columns) if columns > 0 => Some(columns),
+                    _ => return Err(format!("--columns should be a whole number above 0, not '{}'", value)),
+                };
+            },
+            _ => return Err(format!("unknown option '{}'", name)),
+        }
+    }
+
+    if options.inputs.is_empty() && !options.show_help {
+        return Err("no models or directories given".to_string());
+    }
+    Ok(options)
+}
+
+// every .obj file under the directory, sorted so the output is always in the
+// same order
+fn find_obj_files(dir: &Path, found: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => return Err(format!("Failed to read directory '{}': {}", dir.display(), e)),
+    };
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            find_obj_files(&path, found)?;
+        } else if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("obj")) {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+// the turntable angles in degrees around the y axis, starting from the three
+// quarter view
+fn view_angles(angle_count: usize) -> Vec<f32> {
+    (0..angle_count)
+        .map(|i| VIEW_YAW_DEGREES + 360.0 * i as f32 / angle_count as f32)
+        .collect()
+}
+
+// the model's file name without the extension, with a number added on if
+// another model already used that name
+fn unique_name(path: &Path, used_names: &mut Vec<String>) -> String {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or("model".to_string());
+    let mut name = stem.clone();
+    let mut count = 2;
+    while used_names.contains(&name) {
+        name = format!("{}_{}", stem, count);
+        count += 1;
+    }
+    used_names.push(name.clone());
+    name
+}
+
+// puts the mesh on a turntable node at the origin, moved so the centre of its
+// bounds sits on the turntable's axis, and backs the camera off along its
+// view direction until the bounding sphere fits on screen. returns the scene
+// and the turntable node, whose rotation turns the model
+fn framed_scene(mesh: Mesh, width: usize, height: usize) -> (Scene, usize) {
+    let (min, max) = mesh_bounds(&mesh).unwrap_or_default();
+    let center = (min + max) * 0.5;
+    let radius = vec3_distance(&min, &max).max(1e-6) * 0.5;
+
+    let mut scene = scene_new();
+    let mesh_index = scene_add_mesh(&mut scene, "model", "", mesh);
+
+    let turntable_node = scene_add_node(&mut scene, None, scene_node_new("turntable"));
+    let mut model = scene_node_new("model");
+    model.mesh = Some(mesh_index);
+    model.transform.translation = -center;
+    scene_add_node(&mut scene, Some(turntable_node), model);
+
+    // the fov is the vertical one, the horizontal one shrinks with the aspect
+    // ratio, so the narrower of the two decides how far back the camera goes
+    let camera = &mut scene.camera;
+    let aspect = height as f32 / width as f32;
+    let half_fov = (camera.fov_angle / 2.0).min(((camera.fov_angle / 2.0).tan() / aspect).atan());
+    let distance = radius / half_fov.sin() * FRAMING_MARGIN;
+
+    camera.rotation = quat_from_axis_angle(&Vec3 { x: 1.0, y: 0.0, z: 0.0 }, VIEW_PITCH_DEGREES.to_radians());
+    camera.position = quat_rotate_vec3(&camera.rotation, &Vec3 { x: 0.0, y: 0.0, z: -distance });
+    camera.znear = (distance - radius * 2.0).max(distance * 0.01);
+    camera.zfar = distance + radius * 2.0;
+
+    // the light comes from over the camera's left shoulder
+    let light_direction = vec3_normalize_or_zero(&Vec3 { x: 0.5, y: -0.6, z: 1.0 });
+    scene.lights.push(Light { direction: quat_rotate_vec3(&scene.camera.rotation, &light_direction) });
+
+    (scene, turntable_node)
+}
+
+fn render_scene(display: &mut Display, scene: &mut Scene, settings: &RenderSettings) {
+    display.clear_color_buffer(BACKGROUND_COLOR);
+    display.clear_depth_buffer();
+
+    scene_update_world_matrices(scene);
+    let vertex_count = scene.meshes.iter().map(|mesh| mesh.vertices.len()).max().unwrap_or(0);
+    let mut cache = vertex_cache_new(vertex_count);
+    let mut triangles = Vec::new();
+    assemble_scene_triangles(
+        &mut triangles,
+        scene,
+        &mut cache,
+        display.width,
+        display.height,
+        settings.backface_culling_enabled);
+    sort_triangles_by_depth(&mut triangles);
+
+    render_triangles(display, &triangles, settings.render_mode, &scene.textures, render_thread_count_default());
+}
+
+// the size of an image on the contact sheet, the same shape as the image
+// with its longest side at most SHEET_CELL_SIZE
+fn sheet_cell_size(width: u32, height: u32) -> (u32, u32) {
+    let longest = width.max(height);
+    if longest <= SHEET_CELL_SIZE {
+        return (width, height);
+    }
+    let scale = |side: u32| ((side as u64 * SHEET_CELL_SIZE as u64 / longest as u64) as u32).max(1);
+    (scale(width), scale(height))
+}
+
+fn contact_sheet(images: &[RgbaImage], columns: usize, cell_width: u32, cell_height: u32) -> RgbaImage {
+    let rows = images.len().div_ceil(columns);
+    let background = Rgba([BACKGROUND_COLOR.r, BACKGROUND_COLOR.g, BACKGROUND_COLOR.b, 255]);
+    let mut sheet = ImageBuffer::from_pixel(columns as u32 * cell_width, rows as u32 * cell_height, background);
+    for (i, image) in images.iter().enumerate() {
+        let x = (i % columns) as u32 * cell_width;
+        let y = (i / columns) as u32 * cell_height;
+        sheet.copy_from(image, x, y).unwrap();
+    }
+    sheet
+}
+
+// `sheet_layout` is the columns and the size of each image on the contact
+// sheet, or None when there isn't one
+fn manifest_json(
+    options: &ThumbnailOptions,
+    angles: &[f32],
+    sheet_layout: Option<(usize, u32, u32)>,
+    results: &[ModelResult]) -> String {
+    let columns = sheet_layout.map_or(1, |(columns, _, _)| columns);
+    let mut out = String::from("{\n");
+    out.push_str(&format!("  \"mode\": {},\n", json_string(render_mode_name(options.settings.render_mode))));
+    out.push_str(&format!("  \"backface_culling\": {},\n", options.settings.backface_culling_enabled));
+    out.push_str(&format!("  \"width\": {},\n", options.width));
+    out.push_str(&format!("  \"height\": {},\n", options.height));
+    let angle_list: Vec<String> = angles.iter().map(|angle| format!("{}", angle)).collect();
+    out.push_str(&format!("  \"angles\": [{}],\n", angle_list.join(", ")));
+    if let Some((columns, cell_width, cell_height)) = sheet_layout {
+        out.push_str("  \"contact_sheet\": \"contact_sheet.png\",\n");
+        out.push_str(&format!("  \"contact_sheet_columns\": {},\n", columns));
+        out.push_str(&format!("  \"contact_sheet_cell_size\": [{}, {}],\n", cell_width, cell_height));
+    }
+    out.push_str("  \"models\": [");
+
+    // the contact sheet cells are handed out in the same order as the images
+    let mut sheet_cell = 0;
+    for (i, result) in results.iter().enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        out.push_str("    {\n");
+        out.push_str(&format!("      \"path\": {}", json_string(&result.path)));
+        match &result.error {
+            Some(error) => {
+                out.push_str(&format!(",\n      \"error\": {}\n", json_string(error)));
+            },
+            None => {
+                out.push_str(&format!(",\n      \"vertices\": {}", result.vertex_count));
+                out.push_str(&format!(",\n      \"faces\": {}", result.face_count));
+                if let Some((min, max)) = result.bounds {
+                    out.push_str(&format!(",\n      \"bounds_min\": [{}, {}, {}]", min.x, min.y, min.z));
+                    out.push_str(&format!(",\n      \"bounds_max\": [{}, {}, {}]", max.x, max.y, max.z));
+                }
+                let images: Vec<String> = result.images.iter().map(|image| json_string(image)).collect();
+                out.push_str(&format!(",\n      \"images\": [{}]", images.join(", ")));
+                let cells: Vec<String> = (sheet_cell..sheet_cell + result.images.len())
+                    .map(|cell| format!("[{}, {}]", cell / columns, cell % columns))
+                    .collect();
+                sheet_cell += result.images.len();
+                out.push_str(&format!(",\n      \"contact_sheet_cells\": [{}]\n", cells.join(", ")));
+            }
+        }
+        out.push_str("    }");
+    }
+    out.push_str("\n  ]\n}\n");
+    out
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
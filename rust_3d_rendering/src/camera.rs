@@ -1,7 +1,74 @@
-use crate::vector::Vec3;
+use std::f32::consts::PI;
 
+use crate::{
+    matrix::{Mat4, mat4_make_perspective, mat4_make_translation, mat4_mul_mat4, mat4_transpose},
+    quaternion::{Quat, quat_identity, quat_slerp, quat_to_mat4},
+    vector::{Vec3, vec3_lerp},
+};
+
+#[derive(Debug, Copy, Clone)]
 pub struct Camera {
     pub position: Vec3,
-    pub rotation: Vec3,
+    pub rotation: Quat,
     pub fov_angle: f32,
-}
\ No newline at end of file
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+// somewhere a scripted camera path passes through, `time` seconds in
+#[derive(Debug, Copy, Clone)]
+pub struct CameraKey {
+    pub time: f32,
+    pub position: Vec3,
+    pub rotation: Quat,
+}
+
+// a camera at the origin looking down +z, which is what the renderer always
+// used before there was a camera
+pub fn camera_default() -> Camera {
+    Camera {
+        position: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        rotation: quat_identity(),
+        fov_angle: PI / 3.0,
+        znear: 0.1,
+        zfar: 100.0,
+    }
+}
+
+// moves world space into camera space, the inverse of the camera's own
+// rotation and translation. the inverse of a rotation is its transpose, so
+// there's no need for a general matrix inverse
+pub fn camera_view_matrix(camera: &Camera) -> Mat4 {
+    let inverse_rotation = mat4_transpose(&quat_to_mat4(&camera.rotation));
+    let inverse_translation = mat4_make_translation(-camera.position.x, -camera.position.y, -camera.position.z);
+    mat4_mul_mat4(&inverse_rotation, &inverse_translation)
+}
+
+pub fn camera_projection_matrix(camera: &Camera, screen_width: usize, screen_height: usize) -> Mat4 {
+    let aspect = screen_height as f32 / screen_width as f32;
+    mat4_make_perspective(camera.fov_angle, aspect, camera.znear, camera.zfar)
+}
+
+// where the camera is `time` seconds along a path of keys sorted by time. it
+// moves in a straight line and turns evenly from each key to the next, and
+// stays at the first or last key before or after the path. None without keys
+pub fn camera_path_sample(keys: &[CameraKey], time: f32) -> Option<(Vec3, Quat)> {
+    let first = keys.first()?;
+    if time <= first.time {
+        return Some((first.position, first.rotation));
+    }
+    for pair in keys.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        if time < b.time {
+            let t = (time - a.time) / (b.time - a.time);
+            return Some((vec3_lerp(&a.position, &b.position, t), quat_slerp(&a.rotation, &b.rotation, t)));
+        }
+    }
+    let last = &keys[keys.len() - 1];
+    Some((last.position, last.rotation))
+}
+
+// the time of the last key, which is how long the path takes
+pub fn camera_path_duration(keys: &[CameraKey]) -> f32 {
+    keys.last().map_or(0.0, |key| key.time)
+}
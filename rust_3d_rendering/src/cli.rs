@@ -0,0 +1,377 @@
+// the viewer's command line options. they're parsed here in the library
+// rather than in main.rs so the tests can reach them
+
+use std::path::Path;
+
+use crate::antialias::{AntiAliasing, anti_aliasing_from_name, anti_aliasing_sample_count, ANTI_ALIASING_NAMES};
+use crate::clock::FrameLimit;
+use crate::display::{
+    RenderMode, TransparencyMode, render_mode_from_name, transparency_mode_from_name, RENDER_MODE_NAMES, SCREEN_HEIGHT,
+    SCREEN_WIDTH, TRANSPARENCY_MODE_NAMES,
+};
+use crate::fragment::{DEFAULT_FRAGMENTS_PER_PIXEL, MAX_FRAGMENTS_PER_PIXEL};
+use crate::line::{
+    HiddenLines, LineEdges, hidden_lines_from_name, line_edges_from_name, HIDDEN_LINES_NAMES, LINE_EDGES_NAMES,
+};
+use crate::video::{video_format_from_path, VIDEO_FORMAT_NAMES};
+
+pub const DEFAULT_FPS: f32 = 30.0;
+
+// the widest and tallest --resolution can be, which keeps the frame's own
+// colour and depth buffers to 512 MiB. the anti-aliasing samples have a budget
+// of their own, MAX_SAMPLE_BUFFER_BYTES, so big frames only get the lighter
+// --aa modes
+pub const MAX_RESOLUTION: usize = 8192;
+
+pub const USAGE: &str = "\
+Usage: rust_3d_rendering [OPTIONS]
+
+Shows a spinning textured cube, or whatever model or scene is given. A scene
+file's camera keys move the camera along a scripted path, and its nodes with
+spin = true turn around like the cube.
+
+Options:
+  --model <PATH>          .obj file to show instead of the cube
+  --texture <PATH>        .png texture for the model
+  --scene <PATH>          scene file to load, can't be used with --model or --texture
+  --resolution <WxH>      size of the window or of the saved frames, up to 8192 on each
+                          side [default: 800x600]
+  --mode <MODE>           render mode to start in, one of: wireframe_dot, wireframe,
+                          filled, filled_wireframe, textured, textured_wireframe,
+                          hidden_line, or one of the debug views: depth, normals, uvs,
+                          triangle_index, overdraw
+                          [default: filled, or whatever the scene file says]
+  --culling <on|off>      backface culling [default: on, or whatever the scene file says]
+  --transparency <MODE>   how see through materials are drawn, one of: sorted (by each
+                          triangle's depth), a_buffer (by each pixel's depth, right where
+                          see through parts cross) [default: sorted, or whatever the
+                          scene file says]
+  --a-buffer-fragments <N>
+                          see through layers the a-buffer has room for per pixel on
+                          average, up to 64, anything past that is blended like sorted
+                          [default: 4]
+  --aa <MODE>             anti-aliasing, one of: off, ssaa2, ssaa3, ssaa4 (draw the frame
+                          2 to 4 times as wide and tall and shrink it), msaa2, msaa4,
+                          msaa8 (only smooth the edges of triangles, much faster)
+                          the samples can take up to 2 GiB, which at 8192x8192 allows
+                          up to ssaa2 or msaa4
+                          [default: off, or whatever the scene file says]
+  --line-width <N>        width in pixels of the lines in the wireframe modes
+                          [default: 1, or whatever the scene file says]
+  --smooth-lines <on|off> anti-aliased wireframe lines [default: off, or whatever the
+                          scene file says]
+  --hidden-lines <MODE>   what happens to wireframe lines behind the faces, one of:
+                          show, dim, hide, dashed [default: show, or whatever the scene
+                          file says]
+  --line-edges <EDGES>    which edges the wireframe modes draw, one of: all, outline
+                          (just the silhouette, creases and open borders) [default: all,
+                          or whatever the scene file says]
+  --fps <N>               frames per second to aim for, and with --headless the
+                          frame rate the saved frames are timed for [default: 30]
+  --frame-limit <LIMIT>   how to pace the frames, one of: fps (sleep to hit --fps),
+                          vsync (wait for the display to refresh), uncapped [default: fps]
+  --hud                   start with the hud showing the frame rate, triangle counts
+                          and render settings
+  --stats-csv <PATH>      write timings and triangle and pixel counts for every frame
+                          to a csv file
+  --headless              render without opening a window and save the frames as pngs
+  --frames <N>            number of frames to render with --headless [default: enough
+                          for the scene's whole camera path, or else 1]
+  --out <DIR>             directory to save the --headless frames in [default: .]
+  --svg                   with --headless, also save each frame as an svg next to its
+                          png, only for the wireframe, filled and textured modes
+  --record <PATH>         record the frames into a video, a .y4m (YUV4MPEG2) or an
+                          uncompressed .avi. in the window it's recorded in real time
+                          until quitting, with --headless the frames go into the video
+                          instead of pngs. the hud is left out of the video
+  --record-fps <N>        frame rate of the recorded video, frames are repeated or
+                          skipped to keep it in time [default: --fps]
+  -h, --help              show this help
+
+Keys: 1 to 7 change the render mode, F1 to F5 switch to the depth,
+normals, uvs, triangle index and overdraw debug views, C and D turn backface
+culling on and off, T switches between sorted and a-buffer transparency, A
+steps through the anti-aliasing modes, L turns smooth lines on and off, E
+steps through showing, dimming, hiding and dashing the hidden lines, O switches
+between drawing all the edges and just the outline, H shows and hides
+the hud, S saves the scene (a scene loaded with --scene is saved beside it
+as <name>_saved.toml), V saves the frame as an svg and Escape quits.";
+
+pub struct CliOptions {
+    pub model_path: Option<String>,
+    pub texture_path: Option<String>,
+    pub scene_path: Option<String>,
+    pub width: usize,
+    pub height: usize,
+    // left as None so the scene file's own settings are used
+    pub render_mode: Option<RenderMode>,
+    pub backface_culling_enabled: Option<bool>,
+    pub transparency: Option<TransparencyMode>,
+    pub a_buffer_fragments: usize,
+    pub anti_aliasing: Option<AntiAliasing>,
+    pub line_width: Option<f32>,
+    pub smooth_lines: Option<bool>,
+    pub hidden_lines: Option<HiddenLines>,
+    pub line_edges: Option<LineEdges>,
+    pub fps: f32,
+    pub frame_limit: FrameLimit,
+    pub hud: bool,
+    pub stats_csv_path: Option<String>,
+    pub headless: bool,
+    // left as None to render the whole camera path, or one frame without one
+    pub frame_count: Option<usize>,
+    pub out_dir: String,
+    pub svg: bool,
+    pub record_path: Option<String>,
+    // left as None to record at --fps
+    pub record_fps: Option<f32>,
+    pub show_help: bool,
+}
+
+pub fn cli_options_default() -> CliOptions {
+    CliOptions {
+        model_path: None,
+        texture_path: None,
+        scene_path: None,
+        width: SCREEN_WIDTH,
+        height: SCREEN_HEIGHT,
+        render_mode: None,
+        backface_culling_enabled: None,
+        transparency: None,
+        a_buffer_fragments: DEFAULT_FRAGMENTS_PER_PIXEL,
+        anti_aliasing: None,
+        line_width: None,
+        smooth_lines: None,
+        hidden_lines: None,
+        line_edges: None,
+        fps: DEFAULT_FPS,
+        frame_limit: FrameLimit::TargetFps(DEFAULT_FPS),
+        hud: false,
+        stats_csv_path: None,
+        headless: false,
+        frame_count: None,
+        out_dir: ".".to_string(),
+        svg: false,
+        record_path: None,
+        record_fps: None,
+        show_help: false,
+    }
+}
+
+// the arguments without the program name. values can be given either as the
+// next argument or after an '=', so `--fps 60` and `--fps=60` are the same
+pub fn parse_args(args: &[String]) -> Result<CliOptions, String> {
+    let mut options = cli_options_default();
+    let mut saw_out = false;
+    let mut frame_limit_name = "fps".to_string();
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        i += 1;
+
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg, None),
+        };
+
+        // flags that don't take a value
+        match name {
+            "-h" | "--help" => {
+                options.show_help = true;
+                continue;
+            },
+            "--headless" | "--hud" | "--svg" => {
+                if inline_value.is_some() {
+                    return Err(format!("{} doesn't take a value", name));
+                }
+                match name {
+                    "--hud" => options.hud = true,
+                    "--svg" => options.svg = true,
+                    _ => options.headless = true,
+                }
+                continue;
+            },
+            _ => {}
+        }
+
+        if !name.starts_with('-') {
+            return Err(format!("unexpected argument '{}'", arg));
+        }
+
+        let value = match inline_value {
+            Some(value) => value,
+            None => {
+                if i >= args.len() {
+                    return Err(format!("{} needs a value", name));
+                }
+                i += 1;
+                args[i - 1].clone()
+            }
+        };
+
+        match name {
+            "--model" => options.model_path = Some(value),
+            "--texture" => options.texture_path = Some(value),
+            "--scene" => options.scene_path = Some(value),
+            "--stats-csv" => options.stats_csv_path = Some(value),
+            "--resolution" => {
+                let (width, height) = parse_resolution(&value)?;
+                options.width = width;
+                options.height = height;
+            },
+            "--mode" => {
+                options.render_mode = match render_mode_from_name(&value) {
+                    Some(mode) => Some(mode),
+                    None => {
+                        let names: Vec<&str> = RENDER_MODE_NAMES.iter().map(|(name, _)| *name).collect();
+                        return Err(format!("unknown render mode '{}', expected one of: {}", value, names.join(", ")));
+                    }
+                };
+            },
+            "--culling" => {
+                options.backface_culling_enabled = match value.as_str() {
+                    "on" => Some(true),
+                    "off" => Some(false),
+                    _ => return Err(format!("--culling should be 'on' or 'off', not '{}'", value)),
+                };
+            },
+            "--transparency" => {
+                options.transparency = match transparency_mode_from_name(&value) {
+                    Some(transparency) => Some(transparency),
+                    None => {
+                        let names: Vec<&str> = TRANSPARENCY_MODE_NAMES.iter().map(|(name, _)| *name).collect();
+                        return Err(format!(
+                            "unknown transparency mode '{}', expected one of: {}", value, names.join(", ")));
+                    }
+                };
+            },
+            "--a-buffer-fragments" => {
+                options.a_buffer_fragments = match value.parse::<usize>() {
+                    Ok(count) if count > 0 && count <= MAX_FRAGMENTS_PER_PIXEL => count,
+                    _ => return Err(format!(
+                        "--a-buffer-fragments should be a whole number from 1 to {}, not '{}'",
+                        MAX_FRAGMENTS_PER_PIXEL, value)),
+                };
+            },
+            "--aa" => {
+                options.anti_aliasing = match anti_aliasing_from_name(&value) {
+                    Some(anti_aliasing) => Some(anti_aliasing),
+                    None => {
+                        let names: Vec<&str> = ANTI_ALIASING_NAMES.iter().map(|(name, _)| *name).collect();
+                        return Err(format!("unknown anti-aliasing '{}', expected one of: {}", value, names.join(", ")));
+                    }
+                };
+            },
+            "--line-width" => {
+                options.line_width = match value.parse::<f32>() {
+                    Ok(width) if width > 0.0 && width.is_finite() => Some(width),
+                    _ => return Err(format!("--line-width should be a number above 0, not '{}'", value)),
+                };
+            },
+            "--smooth-lines" => {
+                options.smooth_lines = match value.as_str() {
+                    "on" => Some(true),
+                    "off" => Some(false),
+                    _ => return Err(format!("--smooth-lines should be 'on' or 'off', not '{}'", value)),
+                };
+            },
+            "--hidden-lines" => {
+                options.hidden_lines = match hidden_lines_from_name(&value) {
+                    Some(hidden) => Some(hidden),
+                    None => {
+                        let names: Vec<&str> = HIDDEN_LINES_NAMES.iter().map(|(name, _)| *name).collect();
+                        return Err(format!("unknown hidden lines '{}', expected one of: {}", value, names.join(", ")));
+                    }
+                };
+            },
+            "--line-edges" => {
+                options.line_edges = match line_edges_from_name(&value) {
+                    Some(edges) => Some(edges),
+                    None => {
+                        let names: Vec<&str> = LINE_EDGES_NAMES.iter().map(|(name, _)| *name).collect();
+                        return Err(format!("unknown line edges '{}', expected one of: {}", value, names.join(", ")));
+                    }
+                };
+            },
+            "--fps" => {
+                options.fps = match value.parse::<f32>() {
+                    Ok(fps) if fps > 0.0 && fps.is_finite() => fps,
+                    _ => return Err(format!("--fps should be a number above 0, not '{}'", value)),
+                };
+            },
+            "--frame-limit" => {
+                if !["fps", "vsync", "uncapped"].contains(&value.as_str()) {
+                    return Err(format!("--frame-limit should be 'fps', 'vsync' or 'uncapped', not '{}'", value));
+                }
+                frame_limit_name = value;
+            },
+            "--frames" => {
+                options.frame_count = match value.parse::<usize>() {
+                    Ok(count) if count > 0 => Some(count),
+                    _ => return Err(format!("--frames should be a whole number above 0, not '{}'", value)),
+                };
+            },
+            "--out" => {
+                options.out_dir = value;
+                saw_out = true;
+            },
+            "--record" => {
+                if video_format_from_path(Path::new(&value)).is_none() {
+                    let names: Vec<&str> = VIDEO_FORMAT_NAMES.iter().map(|(name, _)| *name).collect();
+                    return Err(format!("--record should end in one of: .{}, not '{}'", names.join(", ."), value));
+                }
+                options.record_path = Some(value);
+            },
+            "--record-fps" => {
+                options.record_fps = match value.parse::<f32>() {
+                    Ok(fps) if fps > 0.0 && fps.is_finite() => Some(fps),
+                    _ => return Err(format!("--record-fps should be a number above 0, not '{}'", value)),
+                };
+            },
+            _ => return Err(format!("unknown option '{}'", name)),
+        }
+    }
+
+    options.frame_limit = match frame_limit_name.as_str() {
+        "vsync" => FrameLimit::Vsync,
+        "uncapped" => FrameLimit::Uncapped,
+        _ => FrameLimit::TargetFps(options.fps),
+    };
+
+    if options.show_help {
+        return Ok(options);
+    }
+    if options.scene_path.is_some() && (options.model_path.is_some() || options.texture_path.is_some()) {
+        return Err("--scene can't be used together with --model or --texture".to_string());
+    }
+    if !options.headless && (options.frame_count.is_some() || saw_out) {
+        return Err("--frames and --out only work with --headless".to_string());
+    }
+    if options.record_path.is_none() && options.record_fps.is_some() {
+        return Err("--record-fps only works with --record".to_string());
+    }
+    if !options.headless && options.svg {
+        return Err("--svg only works with --headless, in the window V saves the frame as an svg".to_string());
+    }
+    if let Some(anti_aliasing) = options.anti_aliasing {
+        if let Err(e) = anti_aliasing_sample_count(anti_aliasing, options.width, options.height) {
+            return Err(format!("--aa {}, pick a lighter mode or a smaller --resolution", e));
+        }
+    }
+    Ok(options)
+}
+
+fn parse_resolution(value: &str) -> Result<(usize, usize), String> {
+    let error = || format!("--resolution should look like 800x600, not '{}'", value);
+    let (width, height) = match value.split_once('x') {
+        Some(parts) => parts,
+        None => return Err(error()),
+    };
+    match (width.parse::<usize>(), height.parse::<usize>()) {
+        (Ok(width), Ok(height)) if width > MAX_RESOLUTION || height > MAX_RESOLUTION => Err(format!(
+            "--resolution can be at most {}x{}, not '{}'", MAX_RESOLUTION, MAX_RESOLUTION, value)),
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(error()),
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+// how many times a second the simulation is stepped forward, no matter how
+// fast the frames are being drawn
+pub const SIMULATION_RATE: f32 = 60.0;
+
+// the longest a single frame can count as. a frame that took longer, like
+// while the window was being dragged around, only moves the simulation on by
+// this much so it doesn't run hundreds of steps trying to catch up
+pub const MAX_FRAME_TIME: f32 = 0.25;
+
+// how often frames are drawn
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FrameLimit {
+    // as fast as possible
+    Uncapped,
+    // once per refresh of the display, the wait happens in present
+    Vsync,
+    // sleep at the end of each frame to hit this many frames per second
+    TargetFps(f32),
+}
+
+// measures how much real time passes between frames
+pub struct Clock {
+    start: Instant,
+    frame_start: Instant,
+    // seconds between the last two ticks, capped at MAX_FRAME_TIME
+    pub delta_time: f32,
+    // seconds since the clock was created
+    pub elapsed_time: f64,
+}
+
+pub fn clock_new() -> Clock {
+    let now = Instant::now();
+    Clock {
+        start: now,
+        frame_start: now,
+        delta_time: 0.0,
+        elapsed_time: 0.0,
+    }
+}
+
+// starts a new frame and returns how many seconds the previous one took
+pub fn clock_tick(clock: &mut Clock) -> f32 {
+    let now = Instant::now();
+    clock.delta_time = now.duration_since(clock.frame_start).as_secs_f32().min(MAX_FRAME_TIME);
+    clock.elapsed_time = now.duration_since(clock.start).as_secs_f64();
+    clock.frame_start = now;
+    clock.delta_time
+}
+
+// sleeps for whatever is left of the current frame when aiming for a target
+// frame rate. with vsync present already waited and uncapped doesn't wait
+pub fn clock_wait_for_frame_limit(clock: &Clock, frame_limit: FrameLimit) {
+    let fps = match frame_limit {
+        FrameLimit::TargetFps(fps) => fps,
+        FrameLimit::Uncapped | FrameLimit::Vsync => return,
+    };
+    let frame_time = Duration::from_secs_f32(1.0 / fps);
+    let frame_end = clock.frame_start + frame_time;
+    let now = Instant::now();
+    if frame_end > now {
+        thread::sleep(frame_end - now);
+    }
+}
+
+// steps the simulation in fixed slices of time. the real time of each frame
+// goes into the accumulator and a step is taken for every whole `step` in
+// there, the leftover carries over to the next frame
+pub struct FixedTimestep {
+    // seconds per step
+    pub step: f32,
+    pub accumulator: f32,
+}
+
+pub fn fixed_timestep_new(rate: f32) -> FixedTimestep {
+    FixedTimestep {
+        step: 1.0 / rate,
+        accumulator: 0.0,
+    }
+}
+
+// adds a frame's time and returns how many steps to run for it
+pub fn fixed_timestep_advance(timestep: &mut FixedTimestep, delta_time: f32) -> usize {
+    timestep.accumulator += delta_time.clamp(0.0, MAX_FRAME_TIME);
+    let mut steps = 0;
+    while timestep.accumulator >= timestep.step {
+        timestep.accumulator -= timestep.step;
+        steps += 1;
+    }
+    steps
+}
+
+// how far the frame is between the last step and the next one, from 0 to 1.
+// the frame is drawn this far between the previous and current states, so the
+// motion is smooth even when the frame rate and step rate don't line up
+pub fn fixed_timestep_alpha(timestep: &FixedTimestep) -> f32 {
+    (timestep.accumulator / timestep.step).clamp(0.0, 1.0)
+}
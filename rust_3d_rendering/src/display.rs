@@ -1,124 +1,790 @@
+use std::path::Path;
+
+use crate::font::{CHAR_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH, LINE_ADVANCE, glyph_pixel, glyph_rows};
+use crate::fragment::{
+    Fragment, FragmentBuffer, FragmentRows, fragment_buffer_clamp, fragment_buffer_new, fragment_buffer_split,
+    fragment_rows_clear, fragment_rows_count, fragment_rows_push, fragment_rows_sorted,
+};
+use crate::antialias::{
+    AntiAliasing, MAX_SAMPLES, anti_aliasing_name, anti_aliasing_sample_count, downsample_box, downsample_multisampled,
+    sample_offsets,
+};
+use crate::line::{LinePoint, LineStyle, draw_polyline, line_style_default};
+use crate::material::{BlendMode, blend_colors, blend_mode_is_transparent};
+use crate::vector::Vec2;
+
 use sdl2::{
-    pixels::{Color},
-    render::{Canvas, Texture},
+    pixels::{Color, PixelFormatEnum},
+    render::Canvas,
     video::Window, Sdl,
 };
 
+// a coverage mask with every sample of a pixel in it
+pub const ALL_SAMPLES: u32 = u32::MAX;
+
+// the window size used when no other resolution is asked for
 pub const SCREEN_WIDTH: usize = 800;
 pub const SCREEN_HEIGHT: usize = 600;
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum RenderMode {
     WireframeWithDot,
     Wireframe,
     FilledTriangles,
     FilledTrianglesAndWireframe,
     Textured,
-    TexturedAndWireframe
+    TexturedAndWireframe,
+    // a wireframe with the edges behind the faces taken out, or dimmed or
+    // dashed when the line style asks for that instead, for technical drawings
+    HiddenLine,
+    // the debug views, which colour each pixel by something other than the
+    // material to help track down broken models
+    // linear view space depth, white at the nearest point of the frame
+    // fading to black at the farthest
+    Depth,
+    // the camera space face normal, with x, y and z as red, green and blue
+    Normals,
+    // the texture coordinates as red and green, with a checkerboard over them
+    Uvs,
+    // a different colour for every triangle
+    TriangleIndex,
+    // a heat map of how many triangles covered each pixel
+    Overdraw,
+}
+
+// how see through triangles are put in front of each other
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TransparencyMode {
+    // blended as they're drawn, in the order of the triangles' average depth.
+    // fast, but wrong where see through triangles cross each other
+    Sorted,
+    // kept per pixel in an a-buffer and blended in depth order at the end of
+    // the frame, see fragment.rs
+    ABuffer,
+}
+
+// the settings that decide how the scene gets drawn, as opposed to what is in it
+#[derive(Debug, Copy, Clone)]
+pub struct RenderSettings {
+    pub render_mode: RenderMode,
+    pub backface_culling_enabled: bool,
+    pub transparency: TransparencyMode,
+    pub anti_aliasing: AntiAliasing,
+    // how the edges of the wireframe modes are drawn
+    pub line_style: LineStyle,
+}
+
+pub fn render_settings_default() -> RenderSettings {
+    RenderSettings {
+        render_mode: RenderMode::FilledTriangles,
+        backface_culling_enabled: true,
+        transparency: TransparencyMode::Sorted,
+        anti_aliasing: AntiAliasing::Off,
+        line_style: line_style_default(),
+    }
+}
+
+// the names used for the transparency modes in scene files and on the command line
+pub const TRANSPARENCY_MODE_NAMES: [(&str, TransparencyMode); 2] = [
+    ("sorted", TransparencyMode::Sorted),
+    ("a_buffer", TransparencyMode::ABuffer),
+];
+
+pub fn transparency_mode_from_name(name: &str) -> Option<TransparencyMode> {
+    TRANSPARENCY_MODE_NAMES
+        .iter()
+        .find(|(mode_name, _)| *mode_name == name)
+        .map(|(_, mode)| *mode)
+}
+
+pub fn transparency_mode_name(transparency: TransparencyMode) -> &'static str {
+    TRANSPARENCY_MODE_NAMES
+        .iter()
+        .find(|(_, mode)| *mode == transparency)
+        .map(|(mode_name, _)| *mode_name)
+        .unwrap_or("sorted")
+}
+
+// the names used for the render modes in scene files and on the command line
+pub const RENDER_MODE_NAMES: [(&str, RenderMode); 12] = [
+    ("wireframe_dot", RenderMode::WireframeWithDot),
+    ("wireframe", RenderMode::Wireframe),
+    ("filled", RenderMode::FilledTriangles),
+    ("filled_wireframe", RenderMode::FilledTrianglesAndWireframe),
+    ("textured", RenderMode::Textured),
+    ("textured_wireframe", RenderMode::TexturedAndWireframe),
+    ("hidden_line", RenderMode::HiddenLine),
+    ("depth", RenderMode::Depth),
+    ("normals", RenderMode::Normals),
+    ("uvs", RenderMode::Uvs),
+    ("triangle_index", RenderMode::TriangleIndex),
+    ("overdraw", RenderMode::Overdraw),
+];
+
+pub fn render_mode_from_name(name: &str) -> Option<RenderMode> {
+    RENDER_MODE_NAMES
+        .iter()
+        .find(|(mode_name, _)| *mode_name == name)
+        .map(|(_, mode)| *mode)
+}
+
+pub fn render_mode_name(render_mode: RenderMode) -> &'static str {
+    RENDER_MODE_NAMES
+        .iter()
+        .find(|(_, mode)| *mode == render_mode)
+        .map(|(mode_name, _)| *mode_name)
+        .unwrap_or("filled")
 }
 
 pub struct Display {
-    pub canvas: Canvas<Window>,
+    // None for a headless display, which only draws into the colour buffer
+    pub canvas: Option<Canvas<Window>>,
+    pub width: usize,
+    pub height: usize,
     color_buffer: Box<[u8]>,
+    // stores 1 - 1/w of the closest pixel drawn so far, so smaller is closer
+    // and a cleared buffer of 1.0 is infinitely far away
+    depth_buffer: Box<[f32]>,
+    anti_aliasing: AntiAliasing,
+    // with anti-aliasing on, the triangles are drawn into these instead and
+    // averaged into the colour buffer by resolve_samples. supersampled they
+    // are a bigger screen, multisampled they hold each pixel's samples one
+    // after another. empty with anti-aliasing off
+    sample_color_buffer: Box<[u8]>,
+    sample_depth_buffer: Box<[f32]>,
+    // only there while the a-buffer is turned on, it's the size of whatever
+    // the triangles are drawn into
+    fragment_buffer: Option<FragmentBuffer>,
+    // how render_triangles draws the edges of the wireframe modes
+    line_style: LineStyle,
+}
+
+// a horizontal band of rows of the display that can be drawn into on its own.
+// the tiles of a frame each borrow a disjoint part of the colour and depth
+// buffers, so separate threads can rasterize them at the same time. all the
+// drawing functions take full screen coordinates and just skip anything that
+// falls outside of the tile
+pub struct FrameTile<'a> {
+    color_buffer: &'a mut [u8],
+    depth_buffer: &'a mut [f32],
+    // the width of the whole screen, which is also the width of the tile
+    pub width: usize,
+    pub y_start: usize,
+    pub y_end: usize,
+    // the height of the whole screen. lines are clipped to the screen rather
+    // than to the tile, so they're stepped along the same way in every tile
+    pub screen_height: usize,
+    // where each pixel's samples are when multisampled, otherwise just the
+    // pixel itself. the colour and depth buffers hold this many values for
+    // every pixel, and the pixel functions work on all of them at once
+    sample_offsets: &'static [(f32, f32)],
+    // how many pixels have been drawn into the tile, counting a pixel again
+    // each time it's drawn over
+    pub pixels_written: usize,
+    // the tile's rows of the a-buffer, if it's turned on
+    fragments: Option<FragmentRows<'a>>,
+    // see through pixels that had to be blended straight away because the
+    // a-buffer was full
+    pub fragments_overflowed: usize,
 }
 
 impl Display {
-    pub fn new(sdl_context: &mut Sdl) -> Self {
+    // with vsync, present waits for the display to refresh before returning
+    pub fn new(sdl_context: &mut Sdl, width: usize, height: usize, vsync: bool) -> Self {
         let video_subsystem = sdl_context.video().unwrap();
         let window = video_subsystem
-            .window("3D Rendering", SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+            .window("3D Rendering", width as u32, height as u32)
             .position_centered()
             .build()
             .unwrap();
-        let canvas = window
-            .into_canvas()
-            .accelerated()
-            .present_vsync()
-            .build()
-            .unwrap();
-        let color_buffer = vec![0u8; SCREEN_WIDTH * SCREEN_HEIGHT * 4].into_boxed_slice();
-        
+        let mut canvas_builder = window.into_canvas().accelerated();
+        if vsync {
+            canvas_builder = canvas_builder.present_vsync();
+        }
+        let canvas = canvas_builder.build().unwrap();
+
+        let mut display = Display::new_headless(width, height);
+        display.canvas = Some(canvas);
+        display
+    }
+
+    // a display without a window, for rendering frames straight to files
+    pub fn new_headless(width: usize, height: usize) -> Self {
+        let color_buffer = vec![0u8; width * height * 4].into_boxed_slice();
+        let depth_buffer = vec![1.0f32; width * height].into_boxed_slice();
+
         Display {
-            canvas,
+            canvas: None,
+            width,
+            height,
             color_buffer,
+            depth_buffer,
+            anti_aliasing: AntiAliasing::Off,
+            sample_color_buffer: Box::new([]),
+            sample_depth_buffer: Box::new([]),
+            fragment_buffer: None,
+            line_style: line_style_default(),
         }
     }
 
-    pub fn draw_pixel(&mut self, x: usize, y: usize, color: Color, ) {
-        // if the x and y are not within bounds of the screen, return
-        if !(x < SCREEN_WIDTH && y < SCREEN_HEIGHT) {
-            return;
+    // switches anti-aliasing, making the sample buffers to match. they start
+    // out black and cleared, so this is best done between frames. when the
+    // samples would take too much memory it's an error and anti-aliasing is
+    // left as it was
+    pub fn set_anti_aliasing(&mut self, anti_aliasing: AntiAliasing) -> Result<(), String> {
+        if anti_aliasing == self.anti_aliasing {
+            return Ok(());
         }
-        // multiply by 4 because each pixel is split into 4 u8 array indexes and
-        // not one u32 array index
-        let x = x * 4;
-        let y = y * 4;
-        // not sure why, but I needed to swap the b and r values
-        // to get the correct color
-        self.color_buffer[(SCREEN_WIDTH * y) + x] = color.b;
-        self.color_buffer[(SCREEN_WIDTH * y) + x + 1] = color.g;
-        self.color_buffer[(SCREEN_WIDTH * y) + x + 2] = color.r;
-        self.color_buffer[(SCREEN_WIDTH * y) + x + 3] = color.a;
-    }    
+        let sample_count = anti_aliasing_sample_count(anti_aliasing, self.width, self.height)?;
+        let out_of_memory = |_| format!("couldn't get the memory for {}", anti_aliasing_name(anti_aliasing));
+        let mut sample_color_buffer = Vec::new();
+        sample_color_buffer.try_reserve_exact(sample_count * 4).map_err(out_of_memory)?;
+        sample_color_buffer.resize(sample_count * 4, 0u8);
+        let mut sample_depth_buffer = Vec::new();
+        sample_depth_buffer.try_reserve_exact(sample_count).map_err(out_of_memory)?;
+        sample_depth_buffer.resize(sample_count, 1.0f32);
 
-    pub fn draw_line(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, color: Color) {
-        let delta_x = x1 as i32 - x0 as i32;
-        let delta_y = y1 as i32 - y0 as i32;
+        self.anti_aliasing = anti_aliasing;
+        self.sample_color_buffer = sample_color_buffer.into_boxed_slice();
+        self.sample_depth_buffer = sample_depth_buffer.into_boxed_slice();
 
-        let longest_side_length = 
-            if delta_x.abs() >= delta_y.abs() { delta_x.abs() } 
-            else { delta_y.abs() };
-    
-        let x_inc = delta_x as f32 / longest_side_length as f32;
-        let y_inc = delta_y as f32 / longest_side_length as f32;
+        // the a-buffer has to cover the new size. if that's too big it's
+        // left off, which a_buffer_enabled shows
+        if let Some(buffer) = &self.fragment_buffer {
+            let fragments_per_pixel = buffer.fragments_per_pixel;
+            self.fragment_buffer = None;
+            let _ = self.enable_a_buffer(fragments_per_pixel);
+        }
+        Ok(())
+    }
+
+    pub fn anti_aliasing(&self) -> AntiAliasing {
+        self.anti_aliasing
+    }
+
+    pub fn set_line_style(&mut self, line_style: LineStyle) {
+        self.line_style = line_style;
+    }
 
-        let mut current_x = x0 as f32;
-        let mut current_y = y0 as f32;
+    pub fn line_style(&self) -> LineStyle {
+        self.line_style
+    }
+
+    // the size of the screen the triangles are drawn into, which is bigger
+    // than the display when supersampling. the pipeline should project to
+    // the display's own size, render_triangles scales the triangles up
+    pub fn render_size(&self) -> (usize, usize) {
+        match self.anti_aliasing {
+            AntiAliasing::Supersample(factor) => (self.width * factor, self.height * factor),
+            _ => (self.width, self.height),
+        }
+    }
+
+    fn samples_per_pixel(&self) -> usize {
+        match self.anti_aliasing {
+            AntiAliasing::Multisample(samples) => sample_offsets(samples).len(),
+            _ => 1,
+        }
+    }
 
-        for _i in 0..=longest_side_length {
-            self.draw_pixel(current_x.round() as usize, current_y.round() as usize, color);
-            current_x += x_inc;
-            current_y += y_inc;
+    // turns on the a-buffer with room for an average of `fragments_per_pixel`
+    // see through layers on each pixel. the pool is only reallocated when
+    // the size changes. when it can't be made the a-buffer is left off and
+    // the error says why
+    pub fn enable_a_buffer(&mut self, fragments_per_pixel: usize) -> Result<(), String> {
+        let (width, height) = self.render_size();
+        if let Some(buffer) = &self.fragment_buffer {
+            if buffer.fragments_per_pixel == fragment_buffer_clamp(width, fragments_per_pixel) {
+                return Ok(());
+            }
         }
+        self.fragment_buffer = None;
+        self.fragment_buffer = Some(fragment_buffer_new(width, height, fragments_per_pixel)?);
+        Ok(())
+    }
+
+    // turns the a-buffer off and frees it
+    pub fn disable_a_buffer(&mut self) {
+        self.fragment_buffer = None;
+    }
+
+    pub fn a_buffer_enabled(&self) -> bool {
+        self.fragment_buffer.is_some()
     }
 
-    pub fn draw_triangle(
-        &mut self, 
-        x0: usize, y0: usize,
-        x1: usize, y1: usize,
-        x2: usize, y2: usize,
-        color: Color) {
-        self.draw_line(x0, y0, x1, y1, color);
-        self.draw_line(x1, y1, x2, y2, color);
-        self.draw_line(x2, y2, x0, y0, color);
+    // the whole screen the triangles are drawn into as a single tile. with
+    // anti-aliasing on that's the sample buffers, and nothing drawn into it
+    // shows until resolve_samples
+    pub fn full_frame(&mut self) -> FrameTile<'_> {
+        let (width, height) = self.render_size();
+        let sample_offsets = sample_offsets(self.samples_per_pixel());
+        let (color_buffer, depth_buffer) = match self.anti_aliasing {
+            AntiAliasing::Off => (&mut self.color_buffer, &mut self.depth_buffer),
+            _ => (&mut self.sample_color_buffer, &mut self.sample_depth_buffer),
+        };
+        FrameTile {
+            color_buffer,
+            depth_buffer,
+            width,
+            y_start: 0,
+            y_end: height,
+            screen_height: height,
+            sample_offsets,
+            pixels_written: 0,
+            fragments: self.fragment_buffer.as_mut().and_then(|buffer| fragment_buffer_split(buffer, height).pop()),
+            fragments_overflowed: 0,
+        }
+    }
+
+    // split the screen the triangles are drawn into into bands of
+    // `tile_height` rows, top to bottom. the last tile is shorter if the
+    // height isn't a multiple of it
+    pub fn frame_tiles(&mut self, tile_height: usize) -> Vec<FrameTile<'_>> {
+        let tile_height = tile_height.max(1);
+        let (width, height) = self.render_size();
+        let samples_per_pixel = self.samples_per_pixel();
+        let sample_offsets = sample_offsets(samples_per_pixel);
+        let mut fragment_rows = match &mut self.fragment_buffer {
+            Some(buffer) => fragment_buffer_split(buffer, tile_height).into_iter().map(Some).collect(),
+            None => Vec::new(),
+        };
+        fragment_rows.resize_with(height.div_ceil(tile_height), || None);
+
+        let (color_buffer, depth_buffer) = match self.anti_aliasing {
+            AntiAliasing::Off => (&mut self.color_buffer, &mut self.depth_buffer),
+            _ => (&mut self.sample_color_buffer, &mut self.sample_depth_buffer),
+        };
+        let row_values = width * samples_per_pixel;
+        color_buffer
+            .chunks_mut(row_values * 4 * tile_height)
+            .zip(depth_buffer.chunks_mut(row_values * tile_height))
+            .zip(fragment_rows)
+            .enumerate()
+            .map(|(i, ((color_buffer, depth_buffer), fragments))| FrameTile {
+                color_buffer,
+                depth_buffer,
+                width,
+                y_start: i * tile_height,
+                y_end: (i * tile_height + tile_height).min(height),
+                screen_height: height,
+                sample_offsets,
+                pixels_written: 0,
+                fragments,
+                fragments_overflowed: 0,
+            })
+            .collect()
+    }
+
+    // the colour buffer itself, for drawing things over the finished frame
+    // like the hud, which shouldn't be anti-aliased
+    fn output_frame(&mut self) -> FrameTile<'_> {
+        FrameTile {
+            color_buffer: &mut self.color_buffer,
+            depth_buffer: &mut self.depth_buffer,
+            width: self.width,
+            y_start: 0,
+            y_end: self.height,
+            screen_height: self.height,
+            sample_offsets: sample_offsets(1),
+            pixels_written: 0,
+            fragments: None,
+            fragments_overflowed: 0,
+        }
+    }
+
+    // averages the samples into the colour buffer, once all the triangles of
+    // the frame are drawn. does nothing with anti-aliasing off
+    pub fn resolve_samples(&mut self) {
+        let samples_per_pixel = self.samples_per_pixel();
+        match self.anti_aliasing {
+            AntiAliasing::Off => {},
+            AntiAliasing::Supersample(factor) =>
+                downsample_box(&self.sample_color_buffer, &mut self.color_buffer, self.width, factor),
+            AntiAliasing::Multisample(_) => downsample_multisampled(
+                &self.sample_color_buffer, &mut self.color_buffer, samples_per_pixel),
+        }
+    }
+
+    pub fn draw_pixel(&mut self, x: usize, y: usize, color: Color, ) {
+        self.output_frame().draw_pixel(x, y, color);
+    }    
+
+    pub fn draw_line(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, color: Color) {
+        self.output_frame().draw_line(x0, y0, x1, y1, color);
+    }
+
+    pub fn draw_triangle(&mut self, points: &[Vec2; 3], color: Color) {
+        self.output_frame().draw_triangle(points, color);
     }
     
     pub fn clear_color_buffer(&mut self, color: Color) {
-        for y in 0..SCREEN_HEIGHT {
-            for x in 0..SCREEN_WIDTH {
+        for y in 0..self.height {
+            for x in 0..self.width {
                 self.draw_pixel(x, y, color);
             }
         }
+        for sample in self.sample_color_buffer.chunks_exact_mut(4) {
+            sample.copy_from_slice(&[color.b, color.g, color.r, color.a]);
+        }
+    }
+
+    pub fn clear_depth_buffer(&mut self) {
+        self.depth_buffer.fill(1.0);
+        self.sample_depth_buffer.fill(1.0);
     }
     
     pub fn draw_grid(&mut self) {
-        for y in (0..SCREEN_HEIGHT).step_by(10) {
-            for x in (0..SCREEN_WIDTH).step_by(10) {
+        for y in (0..self.height).step_by(10) {
+            for x in (0..self.width).step_by(10) {
                 self.draw_pixel(x, y, Color::RGBA(255, 255, 255, 255))
             }
         }
     }
     
-    pub fn draw_rect(&mut self, x: usize, y: usize, height: usize, width: usize, color: Color) {
-        for x_val in x..(x + width) {
-            for y_val in y..(y + height) {
-                self.draw_pixel(x_val, y_val, color);
-            }
-        }
+    pub fn draw_rect(&mut self, x: i32, y: i32, height: usize, width: usize, color: Color) {
+        self.output_frame().draw_rect(x, y, height, width, color);
+    }
+
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &str, scale: usize, color: Color) {
+        self.output_frame().draw_text(x, y, text, scale, color);
     }
     
-    pub fn render_color_buffer(&self, texture: &mut Texture) {
+    // copies the colour buffer to the window and shows it, a headless display
+    // has nowhere to show it so this does nothing
+    pub fn present(&mut self) {
+        let canvas = match &mut self.canvas {
+            Some(canvas) => canvas,
+            None => return,
+        };
+
+        let texture_creator = canvas.texture_creator();
+        let mut texture = texture_creator
+            .create_texture_streaming(
+                PixelFormatEnum::ARGB8888,
+                self.width as u32,
+                self.height as u32,
+            )
+            .unwrap();
+
         texture
-            .update(None, &*self.color_buffer, 4 * SCREEN_WIDTH)
+            .update(None, &self.color_buffer, 4 * self.width)
             .unwrap();
+        canvas.copy(&texture, None, None).unwrap();
+        // swap front buffer and back buffer
+        canvas.present();
     }
-}
\ No newline at end of file
+
+    // a copy of the colour buffer with the bgra pixels swapped back around to
+    // the rgba order the image crate wants, and fully opaque
+    pub fn color_buffer_rgba(&self) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.color_buffer.len());
+        for pixel in self.color_buffer.chunks_exact(4) {
+            rgba.extend_from_slice(&[pixel[2], pixel[1], pixel[0], 255]);
+        }
+        rgba
+    }
+
+    pub fn save_color_buffer_png(&self, path: &Path) -> Result<(), String> {
+        let rgba = self.color_buffer_rgba();
+        match image::save_buffer(path, &rgba, self.width as u32, self.height as u32, image::ColorType::Rgba8) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(format!("Failed to save image '{}': {}", path.display(), e)),
+        }
+    }
+}
+
+impl FrameTile<'_> {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x < self.width && y >= self.y_start && y < self.y_end
+    }
+
+    // the offsets of the samples in each pixel from the pixel's own point,
+    // more than one when multisampling
+    pub fn sample_offsets(&self) -> &'static [(f32, f32)] {
+        self.sample_offsets
+    }
+
+    // where the first sample of a pixel is in the depth buffer, times 4 for
+    // the colour buffer
+    fn sample_index(&self, x: usize, y: usize) -> usize {
+        ((y - self.y_start) * self.width + x) * self.sample_offsets.len()
+    }
+
+    fn sample_color(&self, index: usize) -> Color {
+        let index = index * 4;
+        Color::RGBA(
+            self.color_buffer[index + 2],
+            self.color_buffer[index + 1],
+            self.color_buffer[index],
+            self.color_buffer[index + 3])
+    }
+
+    fn set_sample_color(&mut self, index: usize, color: Color) {
+        // multiply by 4 because each pixel is split into 4 u8 array indexes and
+        // not one u32 array index
+        let index = index * 4;
+        // not sure why, but I needed to swap the b and r values
+        // to get the correct color
+        self.color_buffer[index] = color.b;
+        self.color_buffer[index + 1] = color.g;
+        self.color_buffer[index + 2] = color.r;
+        self.color_buffer[index + 3] = color.a;
+    }
+
+    // sets every sample of the pixel
+    pub fn draw_pixel(&mut self, x: usize, y: usize, color: Color, ) {
+        // if the x and y are not within bounds of the tile, return
+        if !self.contains(x, y) {
+            return;
+        }
+        let first = self.sample_index(x, y);
+        for index in first..first + self.sample_offsets.len() {
+            self.set_sample_color(index, color);
+        }
+        self.pixels_written += 1;
+    }
+
+    // the colour of a pixel, or of its first sample when multisampled. black
+    // for anything outside the tile
+    pub fn pixel_at(&self, x: usize, y: usize) -> Color {
+        if !self.contains(x, y) {
+            return Color::RGBA(0, 0, 0, 255);
+        }
+        self.sample_color(self.sample_index(x, y))
+    }
+
+    // draws a pixel combined with the one already there by the blend mode,
+    // sample by sample. returns false if nothing was drawn because a cutout
+    // pixel was skipped
+    pub fn blend_pixel(&mut self, x: usize, y: usize, color: Color, blend_mode: BlendMode) -> bool {
+        if blend_mode == BlendMode::Opaque {
+            self.draw_pixel(x, y, color);
+            return true;
+        }
+        self.blend_samples(x, y, ALL_SAMPLES, color, blend_mode) != 0
+    }
+
+    // blends the colour into the samples of the pixel picked out by the bits
+    // of `mask`, returning the bits of the ones that were drawn
+    fn blend_samples(&mut self, x: usize, y: usize, mask: u32, color: Color, blend_mode: BlendMode) -> u32 {
+        if !self.contains(x, y) {
+            return 0;
+        }
+        let first = self.sample_index(x, y);
+        let mut written = 0;
+        for sample in 0..self.sample_offsets.len() {
+            if mask & (1 << sample) == 0 {
+                continue;
+            }
+            if let Some(blended) = blend_colors(&color, &self.sample_color(first + sample), blend_mode) {
+                self.set_sample_color(first + sample, blended);
+                written |= 1 << sample;
+            }
+        }
+        if written != 0 {
+            self.pixels_written += 1;
+        }
+        written
+    }
+
+    // the depth stored at a pixel, anything outside the tile counts as being
+    // in front of everything so it never passes a depth test. when
+    // multisampled it's the nearest of the pixel's samples, so something
+    // only passes a test against it when it's in front of everything drawn
+    // into any part of the pixel
+    pub fn depth_at(&self, x: usize, y: usize) -> f32 {
+        if !self.contains(x, y) {
+            return f32::NEG_INFINITY;
+        }
+        let first = self.sample_index(x, y);
+        self.depth_buffer[first..first + self.sample_offsets.len()]
+            .iter()
+            .fold(f32::INFINITY, |nearest, &depth| nearest.min(depth))
+    }
+
+    // sets the depth of every sample of the pixel
+    pub fn set_depth(&mut self, x: usize, y: usize, depth: f32) {
+        if !self.contains(x, y) {
+            return;
+        }
+        let first = self.sample_index(x, y);
+        let sample_count = self.sample_offsets.len();
+        self.depth_buffer[first..first + sample_count].fill(depth);
+    }
+
+    // keeps the nearer of the stored depth and the one in `depths` for each
+    // sample of the pixel picked out by the bits of `coverage`
+    pub fn draw_sample_depths(&mut self, x: usize, y: usize, coverage: u32, depths: &[f32; MAX_SAMPLES]) {
+        if !self.contains(x, y) {
+            return;
+        }
+        let first = self.sample_index(x, y);
+        let sample_count = self.sample_offsets.len();
+        let stored_depths = &mut self.depth_buffer[first..first + sample_count];
+        for (sample, (stored, depth)) in stored_depths.iter_mut().zip(depths).enumerate() {
+            if coverage & (1 << sample) != 0 && *depth < *stored {
+                *stored = *depth;
+            }
+        }
+    }
+
+    // draws one shaded colour into the samples of a pixel picked out by the
+    // bits of `coverage`, each one depth tested against its own depth in
+    // `depths`. solid blend modes write the depths of the samples they draw,
+    // see through ones go into the a-buffer if it's on. returns whether
+    // anything was drawn
+    pub fn draw_samples(
+        &mut self, x: usize, y: usize, coverage: u32, depths: &[f32; MAX_SAMPLES], color: Color,
+        blend_mode: BlendMode) -> bool {
+        if !self.contains(x, y) {
+            return false;
+        }
+        let first = self.sample_index(x, y);
+        let stored_depths = &self.depth_buffer[first..first + self.sample_offsets.len()];
+        let mut visible = 0;
+        for (sample, (depth, stored)) in depths.iter().zip(stored_depths).enumerate() {
+            if coverage & (1 << sample) != 0 && depth < stored {
+                visible |= 1 << sample;
+            }
+        }
+        if visible == 0 {
+            return false;
+        }
+
+        let transparent = blend_mode_is_transparent(blend_mode);
+        if transparent {
+            if let Some(fragments) = &mut self.fragments {
+                // a fragment only has one depth, the nearest one is what
+                // decides which side of other fragments it goes
+                let nearest = (0..self.sample_offsets.len())
+                    .filter(|sample| visible & (1 << sample) != 0)
+                    .fold(f32::INFINITY, |nearest, sample| nearest.min(depths[sample]));
+                if fragment_rows_push(fragments, x, y, color, nearest, blend_mode, visible) {
+                    return true;
+                }
+                self.fragments_overflowed += 1;
+            }
+        }
+
+        let written = self.blend_samples(x, y, visible, color, blend_mode);
+        if !transparent {
+            let sample_count = self.sample_offsets.len();
+            let stored_depths = &mut self.depth_buffer[first..first + sample_count];
+            for (sample, (stored, depth)) in stored_depths.iter_mut().zip(depths).enumerate() {
+                if written & (1 << sample) != 0 {
+                    *stored = *depth;
+                }
+            }
+        }
+        written != 0
+    }
+
+    // draws a pixel of a see through triangle that has already passed the
+    // depth test. with the a-buffer on it's stored to be blended later by
+    // resolve_fragments, otherwise or when the a-buffer is full it's blended
+    // in right away
+    pub fn draw_transparent_pixel(&mut self, x: usize, y: usize, color: Color, depth: f32, blend_mode: BlendMode) {
+        self.draw_samples(x, y, ALL_SAMPLES, &[depth; MAX_SAMPLES], color, blend_mode);
+    }
+
+    // empties the tile's part of the a-buffer, before drawing a frame
+    pub fn clear_fragments(&mut self) {
+        if let Some(fragments) = &mut self.fragments {
+            fragment_rows_clear(fragments);
+        }
+    }
+
+    // how many see through pixels are waiting in the a-buffer
+    pub fn fragments_stored(&self) -> usize {
+        self.fragments.as_ref().map(fragment_rows_count).unwrap_or(0)
+    }
+
+    // blends every pixel's stored fragments into the colour buffer, farthest
+    // first, once all the triangles have been drawn. anything opaque that
+    // ended up in front of a fragment after it was stored still hides it
+    pub fn resolve_fragments(&mut self) {
+        let fragments = match self.fragments.take() {
+            Some(fragments) => fragments,
+            None => return,
+        };
+        let mut sorted: Vec<Fragment> = Vec::new();
+        for y in self.y_start..self.y_end {
+            for x in 0..self.width {
+                fragment_rows_sorted(&fragments, x, y, &mut sorted);
+                for fragment in &sorted {
+                    self.draw_samples(
+                        x, y, fragment.coverage, &[fragment.depth; MAX_SAMPLES], fragment.color, fragment.blend_mode);
+                }
+            }
+        }
+        self.fragments = Some(fragments);
+    }
+
+    // a one pixel wide line, clipped to the screen so only the part that's
+    // on it is stepped along
+    pub fn draw_line(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, color: Color) {
+        let points = [LinePoint { x: x0, y: y0, w: 1.0 }, LinePoint { x: x1, y: y1, w: 1.0 }];
+        draw_polyline(self, &points, false, color, &line_style_default());
+    }
+
+    // the outline of the triangle with its corners at `points`
+    pub fn draw_triangle(&mut self, points: &[Vec2; 3], color: Color) {
+        for i in 0..3 {
+            let (from, to) = (points[i], points[(i + 1) % 3]);
+            self.draw_line(from.x, from.y, to.x, to.y, color);
+        }
+    }
+
+    // fills `width` by `height` pixels with the top left corner at (x, y),
+    // which can be off the screen. only the part inside the tile is walked
+    pub fn draw_rect(&mut self, x: i32, y: i32, height: usize, width: usize, color: Color) {
+        let x_start = (x as i64).clamp(0, self.width as i64) as usize;
+        let x_end = (x as i64 + width as i64).clamp(0, self.width as i64) as usize;
+        let y_start = (y as i64).clamp(self.y_start as i64, self.y_end as i64) as usize;
+        let y_end = (y as i64 + height as i64).clamp(self.y_start as i64, self.y_end as i64) as usize;
+        for y_val in y_start..y_end {
+            for x_val in x_start..x_end {
+                self.draw_pixel(x_val, y_val, color);
+            }
+        }
+    }
+
+    // draws text with the bitmap font, x and y are the top left corner of the
+    // first character and can be off the screen like draw_rect's. each font
+    // pixel becomes a `scale` by `scale` square and a '\n' starts a new line
+    // back at x. only the glyph pixels are drawn, so whatever is behind the
+    // text shows through the gaps. characters wholly outside the tile are
+    // skipped
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &str, scale: usize, color: Color) {
+        let scale = scale.max(1) as i64;
+        let (glyph_width, glyph_height) = (GLYPH_WIDTH as i64 * scale, GLYPH_HEIGHT as i64 * scale);
+        for (line_index, line) in text.split('\n').enumerate() {
+            let line_y = y as i64 + line_index as i64 * LINE_ADVANCE as i64 * scale;
+            if line_y >= self.y_end as i64 {
+                break;
+            }
+            if line_y + glyph_height <= self.y_start as i64 {
+                continue;
+            }
+            for (char_index, c) in line.chars().enumerate() {
+                let char_x = x as i64 + char_index as i64 * CHAR_ADVANCE as i64 * scale;
+                if char_x >= self.width as i64 {
+                    break;
+                }
+                if char_x + glyph_width <= 0 {
+                    continue;
+                }
+                let rows = glyph_rows(c);
+                for glyph_y in 0..GLYPH_HEIGHT {
+                    for glyph_x in 0..GLYPH_WIDTH {
+                        if glyph_pixel(rows, glyph_x, glyph_y) {
+                            let pixel_x = char_x + glyph_x as i64 * scale;
+                            let pixel_y = line_y + glyph_y as i64 * scale;
+                            self.draw_rect(pixel_x as i32, pixel_y as i32, scale as usize, scale as usize, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
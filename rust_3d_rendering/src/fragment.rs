@@ -0,0 +1,186 @@
+use sdl2::pixels::Color;
+
+use crate::material::BlendMode;
+
+// an a-buffer for order independent transparency. instead of blending see
+// through pixels straight into the colour buffer in the order the triangles
+// happen to be sorted, each one is kept in a list for its pixel and the lists
+// are sorted by depth and blended once everything has been drawn. sorting
+// whole triangles by their average depth gets it wrong wherever see through
+// triangles cross or overlap unevenly, sorting each pixel can't.
+//
+// the fragments live in one fixed size pool so the memory use is known up
+// front. every row of the screen gets the same share of it, so the tiles can
+// each take their own rows and the result doesn't depend on how the screen
+// was split between threads. a row that runs out of room blends the rest of
+// its fragments straight away like the sorted mode does
+
+// the average number of see through layers a pixel can have before the
+// a-buffer runs out of room
+pub const DEFAULT_FRAGMENTS_PER_PIXEL: usize = 4;
+pub const MAX_FRAGMENTS_PER_PIXEL: usize = 64;
+
+// the most memory the a-buffer may take. it grows with the number of pixels
+// it covers, which supersampling multiplies by the factor squared
+pub const MAX_FRAGMENT_BUFFER_BYTES: usize = 1 << 30;
+
+// marks the end of a pixel's list
+const NO_FRAGMENT: u32 = u32::MAX;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Fragment {
+    pub color: Color,
+    // 1 - 1/w like the depth buffer, so smaller is closer
+    pub depth: f32,
+    pub blend_mode: BlendMode,
+    // a bit for each sample of a multisampled pixel the fragment covers
+    pub coverage: u32,
+    // the next fragment of the same pixel, as an index into its row's share
+    // of the pool
+    next: u32,
+}
+
+pub struct FragmentBuffer {
+    pub width: usize,
+    pub height: usize,
+    pub fragments_per_pixel: usize,
+    // the first fragment of each pixel's list
+    heads: Box<[u32]>,
+    fragments: Box<[Fragment]>,
+    // how much of each row's share of the pool is used
+    row_counts: Box<[u32]>,
+}
+
+// the rows of a FragmentBuffer that belong to one FrameTile
+pub struct FragmentRows<'a> {
+    heads: &'a mut [u32],
+    fragments: &'a mut [Fragment],
+    row_counts: &'a mut [u32],
+    width: usize,
+    y_start: usize,
+    // the number of fragments each row has room for
+    row_capacity: usize,
+}
+
+// the number of fragments per pixel a buffer `width` pixels wide ends up with
+// when asked for `fragments_per_pixel`
+pub fn fragment_buffer_clamp(width: usize, fragments_per_pixel: usize) -> usize {
+    // the indexes are u32s, so a row can't have more fragments than that
+    fragments_per_pixel.clamp(1, MAX_FRAGMENTS_PER_PIXEL.min(NO_FRAGMENT as usize / width.max(1)).max(1))
+}
+
+// makes an a-buffer, or an error when it would be bigger than
+// MAX_FRAGMENT_BUFFER_BYTES or the memory for it can't be had
+pub fn fragment_buffer_new(width: usize, height: usize, fragments_per_pixel: usize) -> Result<FragmentBuffer, String> {
+    let empty = Fragment {
+        color: Color::RGBA(0, 0, 0, 0),
+        depth: 1.0,
+        blend_mode: BlendMode::Alpha,
+        coverage: 0,
+        next: NO_FRAGMENT,
+    };
+    let fragments_per_pixel = fragment_buffer_clamp(width, fragments_per_pixel);
+    let too_big = || format!(
+        "an a-buffer of {}x{} pixels with {} fragments each needs more than the {} MiB allowed",
+        width, height, fragments_per_pixel, MAX_FRAGMENT_BUFFER_BYTES >> 20);
+    let pixel_count = width.checked_mul(height).ok_or_else(too_big)?;
+    let fragment_count = pixel_count.checked_mul(fragments_per_pixel).ok_or_else(too_big)?;
+    let bytes = fragment_count
+        .checked_mul(std::mem::size_of::<Fragment>())
+        .and_then(|bytes| bytes.checked_add(pixel_count * std::mem::size_of::<u32>()))
+        .ok_or_else(too_big)?;
+    if bytes > MAX_FRAGMENT_BUFFER_BYTES {
+        return Err(too_big());
+    }
+
+    let out_of_memory = |_| format!("couldn't get {} MiB of memory for the a-buffer", bytes >> 20);
+    let mut heads = Vec::new();
+    heads.try_reserve_exact(pixel_count).map_err(out_of_memory)?;
+    heads.resize(pixel_count, NO_FRAGMENT);
+    let mut fragments = Vec::new();
+    fragments.try_reserve_exact(fragment_count).map_err(out_of_memory)?;
+    fragments.resize(fragment_count, empty);
+
+    Ok(FragmentBuffer {
+        width,
+        height,
+        fragments_per_pixel,
+        heads: heads.into_boxed_slice(),
+        fragments: fragments.into_boxed_slice(),
+        row_counts: vec![0; height].into_boxed_slice(),
+    })
+}
+
+// splits the buffer into bands of `rows_per_part` rows, top to bottom, the
+// same way Display::frame_tiles splits the screen
+pub fn fragment_buffer_split(buffer: &mut FragmentBuffer, rows_per_part: usize) -> Vec<FragmentRows<'_>> {
+    let rows_per_part = rows_per_part.max(1);
+    let width = buffer.width;
+    let row_capacity = width * buffer.fragments_per_pixel;
+    buffer.heads
+        .chunks_mut(width * rows_per_part)
+        .zip(buffer.fragments.chunks_mut(row_capacity * rows_per_part))
+        .zip(buffer.row_counts.chunks_mut(rows_per_part))
+        .enumerate()
+        .map(|(i, ((heads, fragments), row_counts))| FragmentRows {
+            heads,
+            fragments,
+            row_counts,
+            width,
+            y_start: i * rows_per_part,
+            row_capacity,
+        })
+        .collect()
+}
+
+// empties every list, ready for the next frame
+pub fn fragment_rows_clear(rows: &mut FragmentRows) {
+    rows.heads.fill(NO_FRAGMENT);
+    rows.row_counts.fill(0);
+}
+
+// adds a fragment to the list of pixel (x, y), which has to be inside the
+// rows. returns false without storing it when its row is full
+pub fn fragment_rows_push(
+    rows: &mut FragmentRows, x: usize, y: usize, color: Color, depth: f32, blend_mode: BlendMode, coverage: u32) -> bool {
+    let row = y - rows.y_start;
+    let count = rows.row_counts[row] as usize;
+    if count >= rows.row_capacity {
+        return false;
+    }
+
+    let pixel = row * rows.width + x;
+    rows.fragments[row * rows.row_capacity + count] = Fragment {
+        color,
+        depth,
+        blend_mode,
+        coverage,
+        next: rows.heads[pixel],
+    };
+    rows.heads[pixel] = count as u32;
+    rows.row_counts[row] += 1;
+    true
+}
+
+// fills `out` with the fragments of pixel (x, y) in the order they should be
+// blended, the farthest first. fragments at the same depth keep the order
+// they were drawn in
+pub fn fragment_rows_sorted(rows: &FragmentRows, x: usize, y: usize, out: &mut Vec<Fragment>) {
+    out.clear();
+    let row = y - rows.y_start;
+    let row_fragments = &rows.fragments[row * rows.row_capacity..];
+    let mut index = rows.heads[row * rows.width + x];
+    while index != NO_FRAGMENT {
+        let fragment = row_fragments[index as usize];
+        out.push(fragment);
+        index = fragment.next;
+    }
+    // each fragment went on the front of the list, so it's newest first
+    out.reverse();
+    out.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+}
+
+// how many fragments are stored across all the rows
+pub fn fragment_rows_count(rows: &FragmentRows) -> usize {
+    rows.row_counts.iter().map(|&count| count as usize).sum()
+}
@@ -0,0 +1,107 @@
+use sdl2::pixels::Color;
+
+use crate::{
+    antialias::{AntiAliasing, anti_aliasing_name},
+    display::{Display, RenderMode, TransparencyMode, render_mode_name, transparency_mode_name},
+    font::text_size,
+    line::{LineStyle, hidden_lines_name, line_edges_name},
+    pipeline::PipelineStats,
+    vector::Vec3,
+};
+
+// how often the frame rate shown on the hud changes, any faster and the
+// numbers flicker too much to read
+const FRAME_RATE_UPDATE_INTERVAL: f32 = 0.5;
+
+// the gap between the edge of the screen and the panel, and between the
+// panel's edge and the text, in font pixels
+const HUD_MARGIN: usize = 4;
+const HUD_PADDING: usize = 3;
+
+// averages the frame times over a short interval, so the frame rate shown is
+// steady enough to read
+pub struct FrameRateCounter {
+    pub fps: f32,
+    pub frame_time_ms: f32,
+    frames: usize,
+    time: f32,
+}
+
+pub fn frame_rate_counter_new() -> FrameRateCounter {
+    FrameRateCounter {
+        fps: 0.0,
+        frame_time_ms: 0.0,
+        frames: 0,
+        time: 0.0,
+    }
+}
+
+// adds one frame that took `delta_time` seconds
+pub fn frame_rate_counter_add(counter: &mut FrameRateCounter, delta_time: f32) {
+    counter.frames += 1;
+    counter.time += delta_time;
+    if counter.time >= FRAME_RATE_UPDATE_INTERVAL {
+        counter.fps = counter.frames as f32 / counter.time;
+        counter.frame_time_ms = counter.time * 1000.0 / counter.frames as f32;
+        counter.frames = 0;
+        counter.time = 0.0;
+    }
+}
+
+// everything the hud shows
+pub struct HudInfo {
+    pub fps: f32,
+    pub frame_time_ms: f32,
+    pub stats: PipelineStats,
+    pub render_mode: RenderMode,
+    pub backface_culling_enabled: bool,
+    pub transparency: TransparencyMode,
+    // see through pixels that didn't fit in the a-buffer this frame
+    pub fragments_overflowed: usize,
+    pub anti_aliasing: AntiAliasing,
+    pub line_style: LineStyle,
+    pub camera_position: Vec3,
+}
+
+pub fn hud_text(info: &HudInfo) -> String {
+    format!(
+        "fps {:.1} ({:.2} ms)\n\
+         triangles submitted {}\n\
+         triangles culled {}\n\
+         triangles drawn {}\n\
+         mode {}\n\
+         culling {}\n\
+         transparency {}{}\n\
+         anti-aliasing {}\n\
+         lines {}px{}, {} edges, hidden {}\n\
+         camera {:.2} {:.2} {:.2}",
+        info.fps, info.frame_time_ms,
+        info.stats.triangles_submitted, info.stats.triangles_culled,
+        info.stats.triangles_drawn,
+        render_mode_name(info.render_mode),
+        if info.backface_culling_enabled { "on" } else { "off" },
+        transparency_mode_name(info.transparency),
+        if info.fragments_overflowed > 0 { format!(" ({} overflowed)", info.fragments_overflowed) } else { String::new() },
+        anti_aliasing_name(info.anti_aliasing),
+        info.line_style.width, if info.line_style.smooth { " smooth" } else { "" },
+        line_edges_name(info.line_style.edges),
+        hidden_lines_name(info.line_style.hidden),
+        info.camera_position.x, info.camera_position.y, info.camera_position.z)
+}
+
+// draws the hud in the top left corner on a dark panel, so it can be read
+// over anything. the font is scaled up on big screens
+pub fn draw_hud(display: &mut Display, info: &HudInfo) {
+    let scale = (display.height / 600).max(1);
+    let text = hud_text(info);
+    let (text_width, text_height) = text_size(&text, scale);
+
+    let padding = HUD_PADDING * scale;
+    let corner = (HUD_MARGIN * scale) as i32;
+    let text_corner = corner + padding as i32;
+    display.draw_rect(
+        corner, corner,
+        text_height + 2 * padding, text_width + 2 * padding,
+        Color::RGBA(20, 20, 20, 255));
+    display.draw_text(text_corner, text_corner, &text, scale, Color::RGBA(230, 230, 230, 255));
+}
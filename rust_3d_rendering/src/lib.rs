@@ -0,0 +1,30 @@
+// the renderer itself, shared by the viewer in main.rs and the tools in src/bin
+
+pub mod camera;
+pub mod display;
+pub mod mesh;
+pub mod matrix;
+pub mod triangle;
+pub mod vector;
+pub mod light;
+pub mod texture;
+pub mod swap;
+pub mod quaternion;
+pub mod transform;
+pub mod pipeline;
+pub mod raster;
+pub mod material;
+pub mod scene;
+pub mod scene_file;
+pub mod clock;
+pub mod font;
+pub mod hud;
+pub mod stats;
+pub mod fragment;
+pub mod antialias;
+pub mod line;
+pub mod svg;
+pub mod video;
+pub mod cli;
+
+extern crate image;
@@ -1,11 +1,27 @@
 use sdl2::pixels::Color;
 
-use crate::vector::Vec3;
+use crate::vector::{Vec3, vec3_dot};
 
+#[derive(Debug, Copy, Clone)]
 pub struct Light {
     pub direction: Vec3
 }
 
+// how brightly a face with the given unit normal is lit by all of the
+// directional lights added together. with no lights at all everything is
+// drawn at full brightness
+pub fn lights_intensity(lights: &[Light], normal: &Vec3) -> f32 {
+    if lights.is_empty() {
+        return 1.0;
+    }
+
+    let mut intensity = 0.0;
+    for light in lights {
+        intensity += (-vec3_dot(normal, &light.direction)).max(0.0);
+    }
+    intensity
+}
+
 pub fn light_apply_intensity(original_color: &Color, mut percentage_factor: f32) -> Color {
     percentage_factor = 
         if percentage_factor < 0.0 { 0.0 }
@@ -0,0 +1,476 @@
+use sdl2::pixels::Color;
+
+use crate::{
+    display::FrameTile,
+    material::BlendMode,
+    swap::swap,
+    triangle::Triangle,
+};
+
+// the lines of the wireframe render modes. one pixel wide lines are either
+// stepped along a pixel at a time like they always were, or smoothed with
+// Xiaolin Wu's algorithm, which shares each step between the two pixels the
+// line passes between. wider lines are drawn as every pixel within half the
+// width of the lines, so the corners where they meet come out round instead
+// of notched, and each pixel is only drawn once however many of the lines
+// of one polyline reach it. lines drawn one after another still blend over
+// each other where they cross.
+//
+// the lines can also be tested against the depth buffer, so the edges behind
+// the model are dimmed, dashed or left out. the faces are drawn into the
+// depth buffer first, then the lines are compared against it. and rather
+// than every edge of every triangle, just the outline of the model can be
+// drawn: its silhouette, its creases and any open borders
+
+// what happens to lines that are behind something in the depth buffer
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HiddenLines {
+    // drawn the same as every other line, without looking at the depth buffer
+    Show,
+    // drawn faintly
+    Dim,
+    // not drawn
+    Hide,
+    // drawn as dashes, the way technical drawings show hidden edges
+    Dashed,
+}
+
+// the names used in scene files and on the command line
+pub const HIDDEN_LINES_NAMES: [(&str, HiddenLines); 4] = [
+    ("show", HiddenLines::Show),
+    ("dim", HiddenLines::Dim),
+    ("hide", HiddenLines::Hide),
+    ("dashed", HiddenLines::Dashed),
+];
+
+pub fn hidden_lines_from_name(name: &str) -> Option<HiddenLines> {
+    HIDDEN_LINES_NAMES
+        .iter()
+        .find(|(lines_name, _)| *lines_name == name)
+        .map(|(_, hidden)| *hidden)
+}
+
+pub fn hidden_lines_name(hidden: HiddenLines) -> &'static str {
+    HIDDEN_LINES_NAMES
+        .iter()
+        .find(|(_, lines)| *lines == hidden)
+        .map(|(lines_name, _)| *lines_name)
+        .unwrap_or("show")
+}
+
+// which edges of the triangles the wireframe modes draw
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LineEdges {
+    All,
+    // only the edges on the silhouette, at a crease or on an open border, so
+    // a cube is drawn as six squares instead of twelve triangles
+    Outline,
+}
+
+// the names used in scene files and on the command line
+pub const LINE_EDGES_NAMES: [(&str, LineEdges); 2] = [
+    ("all", LineEdges::All),
+    ("outline", LineEdges::Outline),
+];
+
+pub fn line_edges_from_name(name: &str) -> Option<LineEdges> {
+    LINE_EDGES_NAMES
+        .iter()
+        .find(|(edges_name, _)| *edges_name == name)
+        .map(|(_, edges)| *edges)
+}
+
+pub fn line_edges_name(line_edges: LineEdges) -> &'static str {
+    LINE_EDGES_NAMES
+        .iter()
+        .find(|(_, edges)| *edges == line_edges)
+        .map(|(edges_name, _)| *edges_name)
+        .unwrap_or("all")
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LineStyle {
+    // in screen pixels. smooth lines thinner than a pixel are drawn fainter
+    pub width: f32,
+    pub smooth: bool,
+    pub hidden: HiddenLines,
+    pub edges: LineEdges,
+    // in screen pixels, both the dashes of HiddenLines::Dashed and the gaps
+    // between them
+    pub dash_length: f32,
+}
+
+pub fn line_style_default() -> LineStyle {
+    LineStyle {
+        width: 1.0,
+        smooth: false,
+        hidden: HiddenLines::Show,
+        edges: LineEdges::All,
+        dash_length: 4.0,
+    }
+}
+
+// how much of a hidden line still shows with HiddenLines::Dim
+pub const HIDDEN_LINE_OPACITY: f32 = 0.25;
+
+// an edge lies right on its own faces, so it's only hidden when it's this
+// much farther away than the depth buffer, as a fraction of the depth.
+// without it rounding would hide bits of the edges in front as well
+const LINE_DEPTH_BIAS: f32 = 0.02;
+
+// a point of a line in screen pixels, with the view space depth w the same
+// as a triangle's vertices
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LinePoint {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+}
+
+pub fn triangle_line_points(triangle: &Triangle) -> [LinePoint; 3] {
+    triangle.points.map(|point| LinePoint { x: point.x, y: point.y, w: point.w })
+}
+
+// draws lines joining up the points in order, and back from the last point
+// to the first when `closed`
+pub fn draw_polyline(tile: &mut FrameTile, points: &[LinePoint], closed: bool, color: Color, style: &LineStyle) {
+    if points.len() < 2 {
+        return;
+    }
+    let segment_count = if closed && points.len() > 2 { points.len() } else { points.len() - 1 };
+
+    if style.width > 1.0 {
+        draw_wide_polyline(tile, points, segment_count, color, style);
+        return;
+    }
+    for i in 0..segment_count {
+        let from = points[i];
+        let to = points[(i + 1) % points.len()];
+        if style.smooth {
+            draw_smooth_line(tile, from, to, color, style);
+        } else {
+            draw_stepped_line(tile, from, to, color, style);
+        }
+    }
+}
+
+// Liang-Barsky clipping of the line from `from` to `to` against the
+// rectangle from `min` to `max`. each side of the rectangle cuts the line
+// where it crosses it, and the line is inside the rectangle between the
+// last place it comes in and the first place it goes out. returns how far
+// along the line, from 0 to 1, those are, or None when the line misses the
+// rectangle. it's worked out in f64, a line reaching millions of pixels off
+// the screen would otherwise be cut tens of pixels away from the edge
+pub fn clip_line(from: (f32, f32), to: (f32, f32), min: (f32, f32), max: (f32, f32)) -> Option<(f64, f64)> {
+    if ![from.0, from.1, to.0, to.1].iter().all(|value| value.is_finite()) {
+        return None;
+    }
+    let (from, to) = ((from.0 as f64, from.1 as f64), (to.0 as f64, to.1 as f64));
+    let (min, max) = ((min.0 as f64, min.1 as f64), (max.0 as f64, max.1 as f64));
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let mut t_enter = 0.0f64;
+    let mut t_exit = 1.0f64;
+    // each side as how fast the line heads out through it, and how far inside
+    // of it the line starts
+    for (heading_out, inside) in [
+        (-dx, from.0 - min.0),
+        (dx, max.0 - from.0),
+        (-dy, from.1 - min.1),
+        (dy, max.1 - from.1),
+    ] {
+        if heading_out == 0.0 {
+            // parallel to this side, so either always inside of it or never
+            if inside < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = inside / heading_out;
+        if heading_out < 0.0 {
+            t_enter = t_enter.max(t);
+        } else {
+            t_exit = t_exit.min(t);
+        }
+    }
+    if t_enter > t_exit {
+        return None;
+    }
+    Some((t_enter, t_exit))
+}
+
+// the point `t` of the way along the line, exactly `from` or `to` at the ends
+fn point_along(from: LinePoint, to: LinePoint, t: f64) -> LinePoint {
+    if t <= 0.0 {
+        return from;
+    }
+    if t >= 1.0 {
+        return to;
+    }
+    LinePoint {
+        x: (from.x as f64 + (to.x as f64 - from.x as f64) * t) as f32,
+        y: (from.y as f64 + (to.y as f64 - from.y as f64) * t) as f32,
+        // w isn't linear across the screen but 1/w is
+        w: 1.0 / reciprocal_w_at(from, to, t as f32),
+    }
+}
+
+// the part of the line that's inside the rectangle from `min` to `max`
+fn clipped_line(from: LinePoint, to: LinePoint, min: (f32, f32), max: (f32, f32)) -> Option<(LinePoint, LinePoint)> {
+    let (t_enter, t_exit) = clip_line((from.x, from.y), (to.x, to.y), min, max)?;
+    Some((point_along(from, to, t_enter), point_along(from, to, t_exit)))
+}
+
+// a one pixel wide line stepping one pixel at a time along whichever of x
+// and y it goes further in, rounding the other to the nearest pixel. it's
+// clipped to the screen first, so a line running far off it doesn't step
+// along the part that can't be seen
+fn draw_stepped_line(tile: &mut FrameTile, from: LinePoint, to: LinePoint, color: Color, style: &LineStyle) {
+    let line = PlottedLine { segment: (from, to), color, style };
+    if tile.width == 0 || tile.screen_height == 0 {
+        return;
+    }
+    let screen_max = ((tile.width - 1) as f32, (tile.screen_height - 1) as f32);
+    let (from, to) = match clipped_line(from, to, (0.0, 0.0), screen_max) {
+        Some(line) => line,
+        None => return,
+    };
+
+    let (x0, y0) = (from.x as i32, from.y as i32);
+    let delta_x = to.x as i32 - x0;
+    let delta_y = to.y as i32 - y0;
+
+    let longest_side_length = delta_x.abs().max(delta_y.abs());
+    let x_inc = delta_x as f32 / longest_side_length as f32;
+    let y_inc = delta_y as f32 / longest_side_length as f32;
+
+    let mut current_x = x0 as f32;
+    let mut current_y = y0 as f32;
+
+    for i in 0..=longest_side_length {
+        let t = if longest_side_length > 0 { i as f32 / longest_side_length as f32 } else { 0.0 };
+        let (x, y) = (current_x.round() as i32, current_y.round() as i32);
+        plot_line_pixel(tile, &line, x, y, 1.0, reciprocal_w_at(from, to, t));
+        current_x += x_inc;
+        current_y += y_inc;
+    }
+}
+
+// Xiaolin Wu's line. it steps along the longer axis one pixel at a time and
+// splits each step between the two pixels either side of the line, by how
+// close the line passes to each. the ends are faded by how much of their
+// pixel the line reaches into
+fn draw_smooth_line(tile: &mut FrameTile, from: LinePoint, to: LinePoint, color: Color, style: &LineStyle) {
+    let line = PlottedLine { segment: (from, to), color, style };
+    // the pixels either side of the line reach one past where it's clipped,
+    // which keeps the faded ends off the screen
+    let screen_max = (tile.width as f32, tile.screen_height as f32);
+    let (from, to) = match clipped_line(from, to, (-1.0, -1.0), screen_max) {
+        Some(line) => line,
+        None => return,
+    };
+    let steep = (to.y - from.y).abs() > (to.x - from.x).abs();
+    // walk along x, swapping x and y for steep lines and the ends for lines
+    // going left. `flip` undoes the first for the pixels drawn
+    let (mut from, mut to) = (from, to);
+    if steep {
+        from = LinePoint { x: from.y, y: from.x, w: from.w };
+        to = LinePoint { x: to.y, y: to.x, w: to.w };
+    }
+    if from.x > to.x {
+        swap(&mut from, &mut to);
+    }
+    let flip = |a: i32, b: i32| if steep { (b, a) } else { (a, b) };
+
+    let delta_x = to.x - from.x;
+    let gradient = if delta_x == 0.0 { 1.0 } else { (to.y - from.y) / delta_x };
+    let t_at = |x: f32| if delta_x == 0.0 { 0.0 } else { ((x - from.x) / delta_x).clamp(0.0, 1.0) };
+    // thinner than a pixel just means fainter
+    let strength = style.width.clamp(0.0, 1.0);
+
+    let plot = |tile: &mut FrameTile, x: i32, y: f32, coverage: f32| {
+        let reciprocal_w = reciprocal_w_at(from, to, t_at(x as f32));
+        let row = y.floor();
+        let below = y - row;
+        let (px, py) = flip(x, row as i32);
+        plot_line_pixel(tile, &line, px, py, (1.0 - below) * coverage * strength, reciprocal_w);
+        let (px, py) = flip(x, row as i32 + 1);
+        plot_line_pixel(tile, &line, px, py, below * coverage * strength, reciprocal_w);
+    };
+
+    // the two ends, each in the pixel its x rounds to
+    let first_x = from.x.round();
+    let first_y = from.y + gradient * (first_x - from.x);
+    let last_x = to.x.round();
+    let last_y = from.y + gradient * (last_x - from.x);
+    if first_x == last_x {
+        plot(tile, first_x as i32, first_y, (to.x - from.x).min(1.0));
+        return;
+    }
+    plot(tile, first_x as i32, first_y, 1.0 - (from.x + 0.5).rem_euclid(1.0));
+    plot(tile, last_x as i32, last_y, (to.x + 0.5).rem_euclid(1.0));
+
+    let mut y = first_y + gradient;
+    for x in first_x as i32 + 1..last_x as i32 {
+        plot(tile, x, y, 1.0);
+        y += gradient;
+    }
+}
+
+// lines wider than a pixel. each row of the tile is only walked where one of
+// the lines can reach it, and every pixel there is drawn once with the
+// distance to the nearest line deciding how much of it is covered
+fn draw_wide_polyline(tile: &mut FrameTile, points: &[LinePoint], segment_count: usize, color: Color, style: &LineStyle) {
+    let radius = style.width / 2.0;
+    // how far from a line a pixel can be and still get some of it
+    let reach = if style.smooth { radius + 0.5 } else { radius };
+    let segments: Vec<(LinePoint, LinePoint)> =
+        (0..segment_count).map(|i| (points[i], points[(i + 1) % points.len()])).collect();
+
+    let min_y = points.iter().map(|point| point.y).fold(f32::INFINITY, f32::min) - reach;
+    let max_y = points.iter().map(|point| point.y).fold(f32::NEG_INFINITY, f32::max) + reach;
+    if !min_y.is_finite() || !max_y.is_finite() {
+        return;
+    }
+    let y_first = (min_y.floor() as i32).max(tile.y_start as i32);
+    let y_last = (max_y.ceil() as i32).min(tile.y_end as i32 - 1);
+
+    let mut spans: Vec<(i32, i32)> = Vec::with_capacity(segments.len());
+    for y in y_first..=y_last {
+        spans.clear();
+        spans.extend(segments.iter().filter_map(|(a, b)| segment_row_span(a, b, y as f32, reach)));
+        spans.sort();
+
+        // walk each column once even where the spans of the lines overlap
+        let mut next_x = 0;
+        for &(span_start, span_end) in &spans {
+            for x in span_start.max(next_x)..=span_end.min(tile.width as i32 - 1) {
+                let mut nearest = (f32::INFINITY, 0.0);
+                let mut nearest_segment = 0;
+                for (i, (a, b)) in segments.iter().enumerate() {
+                    let (distance_squared, t) = distance_squared_to_segment(a, b, x as f32, y as f32);
+                    if distance_squared < nearest.0 {
+                        nearest = (distance_squared, t);
+                        nearest_segment = i;
+                    }
+                }
+                let (distance_squared, t) = nearest;
+                if distance_squared > reach * reach {
+                    continue;
+                }
+                let distance = distance_squared.sqrt();
+                let coverage = if style.smooth {
+                    (radius + 0.5 - distance).clamp(0.0, 1.0)
+                } else if distance <= radius {
+                    1.0
+                } else {
+                    0.0
+                };
+                if coverage > 0.0 {
+                    let (a, b) = segments[nearest_segment];
+                    let line = PlottedLine { segment: (a, b), color, style };
+                    plot_line_pixel(tile, &line, x, y, coverage, reciprocal_w_at(a, b, t));
+                }
+            }
+            next_x = next_x.max(span_end + 1);
+        }
+    }
+}
+
+// the columns of row y that can be within `reach` of the line from a to b.
+// only the part of the line within `reach` of the row can be, so it's that
+// part's x range widened by `reach` either side
+fn segment_row_span(a: &LinePoint, b: &LinePoint, y: f32, reach: f32) -> Option<(i32, i32)> {
+    let (t_start, t_end) = if a.y == b.y {
+        if (y - a.y).abs() > reach {
+            return None;
+        }
+        (0.0, 1.0)
+    } else {
+        let t0 = (y - reach - a.y) / (b.y - a.y);
+        let t1 = (y + reach - a.y) / (b.y - a.y);
+        (t0.min(t1).max(0.0), t0.max(t1).min(1.0))
+    };
+    if t_start > t_end {
+        return None;
+    }
+    let x0 = a.x + (b.x - a.x) * t_start;
+    let x1 = a.x + (b.x - a.x) * t_end;
+    Some(((x0.min(x1) - reach).floor() as i32, (x0.max(x1) + reach).ceil() as i32))
+}
+
+// the square of how far (x, y) is from the nearest point of the line from a
+// to b, and how far along the line that point is from 0 at a to 1 at b
+fn distance_squared_to_segment(a: &LinePoint, b: &LinePoint, x: f32, y: f32) -> (f32, f32) {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared > 0.0 {
+        (((x - a.x) * dx + (y - a.y) * dy) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (nearest_x, nearest_y) = (a.x + dx * t, a.y + dy * t);
+    ((x - nearest_x).powi(2) + (y - nearest_y).powi(2), t)
+}
+
+// 1/w is what's linear across the screen, the same as for triangles
+fn reciprocal_w_at(from: LinePoint, to: LinePoint, t: f32) -> f32 {
+    1.0 / from.w + (1.0 / to.w - 1.0 / from.w) * t
+}
+
+// whether a line at `reciprocal_w` is behind what's in the depth buffer
+pub fn line_is_hidden(stored_depth: f32, reciprocal_w: f32) -> bool {
+    reciprocal_w < (1.0 - stored_depth) * (1.0 - LINE_DEPTH_BIAS)
+}
+
+// whether the point (x, y) near the line from a to b is on one of its dashes
+// rather than a gap. the dashes are measured from the same end of the line
+// whichever way round it's drawn, so an edge shared by two triangles gets
+// them in the same places both times
+fn dash_is_on(segment: (LinePoint, LinePoint), x: f32, y: f32, dash_length: f32) -> bool {
+    let (from, to) = segment;
+    let (a, b) = if (from.x, from.y) <= (to.x, to.y) { (from, to) } else { (to, from) };
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 || dash_length <= 0.0 {
+        return true;
+    }
+    let along = ((x - a.x) * dx + (y - a.y) * dy) / length;
+    ((along / dash_length).floor() as i64).rem_euclid(2) == 0
+}
+
+// the line a pixel is plotted for. `segment` is the whole line, before it
+// was clipped, which the dashes are measured along
+struct PlottedLine<'a> {
+    segment: (LinePoint, LinePoint),
+    color: Color,
+    style: &'a LineStyle,
+}
+
+// draws `coverage` of a line's pixel over what's already there, after
+// checking it against the depth buffer if hidden lines are treated
+// differently
+fn plot_line_pixel(tile: &mut FrameTile, line: &PlottedLine, x: i32, y: i32, coverage: f32, reciprocal_w: f32) {
+    let PlottedLine { segment, color, style } = *line;
+    if x < 0 || y < 0 || !tile.contains(x as usize, y as usize) {
+        return;
+    }
+    let (x, y) = (x as usize, y as usize);
+
+    let mut coverage = coverage;
+    if style.hidden != HiddenLines::Show && line_is_hidden(tile.depth_at(x, y), reciprocal_w) {
+        match style.hidden {
+            HiddenLines::Dim => coverage *= HIDDEN_LINE_OPACITY,
+            HiddenLines::Dashed if dash_is_on(segment, x as f32, y as f32, style.dash_length) => {},
+            _ => return,
+        }
+    }
+
+    if coverage >= 1.0 && color.a == 255 {
+        tile.draw_pixel(x, y, color);
+        return;
+    }
+    let alpha = (color.a as f32 * coverage.min(1.0)).round() as u8;
+    if alpha > 0 {
+        tile.blend_pixel(x, y, Color::RGBA(color.r, color.g, color.b, alpha), BlendMode::Alpha);
+    }
+}
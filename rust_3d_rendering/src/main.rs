@@ -1,125 +1,349 @@
-mod camera;
-mod display;
-mod mesh;
-mod matrix;
-mod triangle;
-mod vector;
-mod light;
-mod texture;
-mod swap;
-
-extern crate image;
-
 use std::{
-    thread,
-    time::{Duration, Instant}, f32::consts::PI,
+    env,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    process,
+    time::Instant,
 };
 
-use display::{Display, SCREEN_HEIGHT, SCREEN_WIDTH, RenderMode};
-
-use light::{Light, light_apply_intensity};
-use matrix::{mat4_make_scale, mat4_mul_vec4, mat4_make_translation, mat4_make_rotation_x, mat4_make_rotation_y, mat4_make_rotation_z, mat4_identity, mat4_mul_mat4, Mat4, mat4_make_perspective, mat4_mul_vec4_project};
-use mesh::{load_obj_file_data, Mesh, get_cube_mesh};
+use rust_3d_rendering::cli::{CliOptions, USAGE, parse_args};
+use rust_3d_rendering::antialias::{AntiAliasing, anti_aliasing_name, ANTI_ALIASING_NAMES};
+use rust_3d_rendering::camera::{camera_path_duration, camera_path_sample};
+use rust_3d_rendering::clock::{
+    Clock, FixedTimestep, FrameLimit, SIMULATION_RATE, clock_new, clock_tick, clock_wait_for_frame_limit,
+    fixed_timestep_advance, fixed_timestep_alpha, fixed_timestep_new,
+};
+use rust_3d_rendering::display::{Display, RenderMode, RenderSettings, TransparencyMode, render_settings_default};
+
+use rust_3d_rendering::hud::{FrameRateCounter, HudInfo, draw_hud, frame_rate_counter_add, frame_rate_counter_new};
+use rust_3d_rendering::light::Light;
+use rust_3d_rendering::line::{HIDDEN_LINES_NAMES, LineEdges, LineStyle};
+use rust_3d_rendering::mesh::{get_cube_mesh, load_obj_file};
+use rust_3d_rendering::pipeline::{VertexCache, vertex_cache_new, assemble_scene_triangles, sort_triangles_by_depth};
+use rust_3d_rendering::quaternion::{Quat, quat_from_euler, quat_mul, quat_normalize, quat_slerp, EulerOrder};
 use sdl2::{
     event::Event,
     keyboard::Scancode,
-    pixels::{Color, PixelFormatEnum},
+    pixels::Color,
     Sdl,
 };
-use texture::{Tex2, get_redbrick_texture, read_png_to_colors};
-use triangle::{Triangle, draw_filled_triangle, draw_textured_triangle};
-use vector::{
-    vec3_cross, vec3_dot, vec3_sub, Vec3, vec3_normalize, vec4_from_vec3, Vec4, vec3_from_vec4,
-};
-
-const FPS: f32 = 30.0;
-const FRAME_TARGET_TIME: f32 = 1000.0 / FPS;
+use rust_3d_rendering::scene::{Scene, scene_new, scene_node_new, scene_add_mesh, scene_add_texture, scene_add_node, scene_update_world_matrices, scene_face_count};
+use rust_3d_rendering::scene_file::{load_scene_file, save_scene_file, saved_scene_path};
+use rust_3d_rendering::texture::load_png_texture;
+use rust_3d_rendering::stats::{FRAME_STATS_CSV_HEADER, FrameStats, frame_stats_csv_row, frame_stats_new, overdraw};
+use rust_3d_rendering::raster::{render_triangles, render_thread_count_default};
+use rust_3d_rendering::svg::save_svg;
+use rust_3d_rendering::video::{VideoRecorder, video_recorder_add_frame, video_recorder_finish, video_recorder_new};
+use rust_3d_rendering::triangle::Triangle;
+use rust_3d_rendering::vector::Vec3;
+
+// where S saves the scene when it wasn't loaded from a scene file
+const SCENE_FILE_PATH: &str = "./scene.toml";
+
+const DEFAULT_TEXTURE_PATH: &str = "./assets/cube_texture.png";
+
+// how fast the spinning nodes turn around each axis, in radians per second
+const SPIN_SPEED: f32 = 0.3;
+
+// a node with spin set, and its rotation after the last two simulation
+// steps. each frame is drawn somewhere in between
+struct NodeSpin {
+    node: usize,
+    previous: Quat,
+    current: Quat,
+}
 
 pub struct MainLoop {
     is_running: bool,
-    sdl_context: Sdl,
-    timer: Instant,
-    previous_frame_time: u128,
+    // None when running headless
+    sdl_context: Option<Sdl>,
+    clock: Clock,
+    timestep: FixedTimestep,
+    frame_limit: FrameLimit,
+    // headless frames are each treated as taking exactly 1 / fps seconds, so
+    // the saved frames come out the same however long they took to render
+    headless_delta_time: f32,
     display: Display,
-    mesh: Mesh,
+    scene: Scene,
+    // where S saves the scene
+    save_path: String,
+    // the nodes that are spun around each frame
+    spins: Vec<NodeSpin>,
+    vertex_cache: VertexCache,
     triangles_to_render: Vec<Triangle>,
-    camera_position: Vec3,
     render_mode: RenderMode,
     backface_culling_enabled: bool,
-    proj_matrix: Mat4,
-    light: Light,
-    texture_width: usize,
-    texture_height: usize,
-    mesh_texture: Vec<Color>
+    transparency: TransparencyMode,
+    // how big the a-buffer is made when it's turned on
+    a_buffer_fragments: usize,
+    // true while T is held down, so the transparency only switches once per press
+    transparency_key_down: bool,
+    anti_aliasing: AntiAliasing,
+    // true while A is held down, so the anti-aliasing only changes once per press
+    anti_aliasing_key_down: bool,
+    line_style: LineStyle,
+    // true while L or E are held down, so they only change the lines once per press
+    smooth_lines_key_down: bool,
+    hidden_lines_key_down: bool,
+    line_edges_key_down: bool,
+    render_thread_count: usize,
+    // true while S is held down so the scene is only saved once per press
+    scene_saved: bool,
+    // true while V is held down so the frame is only saved once per press
+    svg_saved: bool,
+    hud_visible: bool,
+    // true while H is held down, so the hud only toggles once per press
+    hud_key_down: bool,
+    frame_rate: FrameRateCounter,
+    // filled in over the course of each frame
+    frame_stats: FrameStats,
+    // with --stats-csv, the file each frame's stats are written to
+    stats_csv: Option<BufWriter<File>>,
+    // with --headless, the frames still to render and where to save them
+    headless_frames_left: Option<usize>,
+    frame_index: usize,
+    // seconds since the first frame, which the camera path and the recording
+    // follow
+    time: f32,
+    // with --record, the video the frames are going into
+    recorder: Option<VideoRecorder>,
+    out_dir: String,
+    // with --svg, each headless frame is saved as an svg as well as a png
+    headless_svg: bool,
+    // a headless frame couldn't be saved, so the run stops and exits with an
+    // error once the recording and the stats are finished
+    headless_failed: bool
 }
 
 fn main() {
-    let mut main_loop = MainLoop::new();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {}\n\nRun with --help to see the options.", e);
+            process::exit(2);
+        }
+    };
+    if options.show_help {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let mut main_loop = match MainLoop::new(&options) {
+        Ok(main_loop) => main_loop,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
     main_loop.setup();
     main_loop.run_loop();
+    if main_loop.headless_failed {
+        process::exit(1);
+    }
 }
 
 impl MainLoop {
-    pub fn new() -> Self {
-        let mut sdl_context = sdl2::init().unwrap();
-        let timer = Instant::now();
-        let display = Display::new(&mut sdl_context);
-        //let mesh = load_obj_file_data("./assets/f22.obj");
-        let mesh = get_cube_mesh();
-        let triangles_to_render = vec![
-            Triangle {
-                points: [Vec4 { x: 0.0, y: 0.0, z: 0.0, w: 0.0 }; 3],
-                color: Color::RGBA(0, 0, 0, 255),
-                avg_depth: 0.0,
-                texcoords: [Tex2 { u: 0.0, v: 0.0 }; 3]
-            };
-            mesh.faces.len() * 2
-        ];
-
-        let fov = PI / 3.0;
-        let aspect = SCREEN_HEIGHT as f32 / SCREEN_WIDTH as f32;
-        let znear = 0.1;
-        let zfar = 100.0;
+    pub fn new(options: &CliOptions) -> Result<Self, String> {
+        let (scene, mut settings) = match &options.scene_path {
+            Some(path) => load_scene_file(path)?,
+            None => (
+                default_scene(options.model_path.as_deref(), options.texture_path.as_deref())?,
+                render_settings_default()),
+        };
+        if let Some(render_mode) = options.render_mode {
+            settings.render_mode = render_mode;
+        }
+        if let Some(backface_culling_enabled) = options.backface_culling_enabled {
+            settings.backface_culling_enabled = backface_culling_enabled;
+        }
+        if let Some(transparency) = options.transparency {
+            settings.transparency = transparency;
+        }
+        if let Some(anti_aliasing) = options.anti_aliasing {
+            settings.anti_aliasing = anti_aliasing;
+        }
+        if let Some(width) = options.line_width {
+            settings.line_style.width = width;
+        }
+        if let Some(smooth) = options.smooth_lines {
+            settings.line_style.smooth = smooth;
+        }
+        if let Some(hidden) = options.hidden_lines {
+            settings.line_style.hidden = hidden;
+        }
+        if let Some(edges) = options.line_edges {
+            settings.line_style.edges = edges;
+        }
 
-        MainLoop {
+        let (sdl_context, display) = if options.headless {
+            if let Err(e) = fs::create_dir_all(&options.out_dir) {
+                return Err(format!("Failed to create the output directory '{}': {}", options.out_dir, e));
+            }
+            (None, Display::new_headless(options.width, options.height))
+        } else {
+            let mut sdl_context = sdl2::init()?;
+            let display = Display::new(
+                &mut sdl_context, options.width, options.height, options.frame_limit == FrameLimit::Vsync);
+            (Some(sdl_context), display)
+        };
+
+        let stats_csv = match &options.stats_csv_path {
+            Some(path) => {
+                let file = match File::create(path) {
+                    Ok(file) => file,
+                    Err(e) => return Err(format!("Failed to create the stats file '{}': {}", path, e)),
+                };
+                let mut writer = BufWriter::new(file);
+                if let Err(e) = writeln!(writer, "{}", FRAME_STATS_CSV_HEADER) {
+                    return Err(format!("Failed to write the stats file '{}': {}", path, e));
+                }
+                Some(writer)
+            },
+            None => None,
+        };
+
+        let recorder = match &options.record_path {
+            Some(path) => Some(video_recorder_new(
+                Path::new(path), options.width, options.height, options.record_fps.unwrap_or(options.fps))?),
+            None => None,
+        };
+        // without --frames, render until the camera reaches the end of its
+        // path, with a frame right at the end
+        let frame_count = options.frame_count.unwrap_or_else(|| {
+            (camera_path_duration(&scene.camera_path) * options.fps).round() as usize + 1
+        });
+
+        let triangles_to_render = Vec::with_capacity(scene_face_count(&scene) * 2);
+        let spins = scene.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.spin)
+            .map(|(index, node)| {
+                let rotation = node.transform.rotation;
+                NodeSpin { node: index, previous: rotation, current: rotation }
+            })
+            .collect();
+        let max_vertex_count = scene.meshes.iter().map(|mesh| mesh.vertices.len()).max().unwrap_or(0);
+
+        Ok(MainLoop {
             is_running: true,
             sdl_context,
-            timer,
-            previous_frame_time: 0,
+            clock: clock_new(),
+            timestep: fixed_timestep_new(SIMULATION_RATE),
+            frame_limit: options.frame_limit,
+            headless_delta_time: 1.0 / options.fps,
             display,
-            mesh,
+            vertex_cache: vertex_cache_new(max_vertex_count),
+            spins,
+            scene,
+            save_path: options.scene_path.as_deref().map(saved_scene_path).unwrap_or(SCENE_FILE_PATH.to_string()),
             triangles_to_render,
-            camera_position: Vec3 {
-                x: 0.0,
-                y: 0.0,
-                z: 0.0,
-            },
-            backface_culling_enabled: true,
-            render_mode: RenderMode::FilledTriangles,
-            proj_matrix: mat4_make_perspective(fov, aspect, znear, zfar),
-            light: Light {
-                direction: Vec3 {
-                    x: 0.0, y: 0.0, z: 1.0
-                }
-            },
-            texture_height: 64,
-            texture_width: 64,
-            mesh_texture: read_png_to_colors("./assets/cube_texture.png").unwrap() //get_redbrick_texture()
-        }
+            backface_culling_enabled: settings.backface_culling_enabled,
+            render_mode: settings.render_mode,
+            transparency: settings.transparency,
+            a_buffer_fragments: options.a_buffer_fragments,
+            transparency_key_down: false,
+            anti_aliasing: settings.anti_aliasing,
+            anti_aliasing_key_down: false,
+            line_style: settings.line_style,
+            smooth_lines_key_down: false,
+            hidden_lines_key_down: false,
+            line_edges_key_down: false,
+            render_thread_count: render_thread_count_default(),
+            scene_saved: false,
+            svg_saved: false,
+            hud_visible: options.hud,
+            hud_key_down: false,
+            frame_rate: frame_rate_counter_new(),
+            frame_stats: frame_stats_new(),
+            stats_csv,
+            headless_frames_left: if options.headless { Some(frame_count) } else { None },
+            frame_index: 0,
+            time: 0.0,
+            recorder,
+            out_dir: options.out_dir.clone(),
+            headless_svg: options.svg,
+            headless_failed: false
+        })
     }
 
-    pub fn setup(&mut self) {}
+    pub fn setup(&mut self) {
+        // don't count the time spent loading as part of the first frame
+        clock_tick(&mut self.clock);
+    }
 
     pub fn run_loop(&mut self) {
         while self.is_running {
+            let input_start = Instant::now();
             self.process_input();
+            self.frame_stats.input_time = input_start.elapsed();
+
+            let update_start = Instant::now();
             self.update();
+            self.frame_stats.update_time = update_start.elapsed();
+
+            let render_start = Instant::now();
             self.render();
+            self.frame_stats.render_time = render_start.elapsed();
+
+            self.write_frame_stats();
+
+            if self.headless_frames_left.is_none() {
+                clock_wait_for_frame_limit(&self.clock, self.frame_limit);
+            }
+        }
+
+        self.finish_recording();
+        if let Some(mut writer) = self.stats_csv.take() {
+            if let Err(e) = writer.flush() {
+                eprintln!("Failed to write the frame stats: {}", e);
+            }
+        }
+    }
+
+    // writes the end of the video so it can be played, even when the
+    // recording stopped early
+    fn finish_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            let (path, frames) = (recorder.path.clone(), recorder.frames_written);
+            match video_recorder_finish(recorder) {
+                Ok(()) => println!("Saved {} frames of video to {}", frames, path),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    }
+
+    fn save_headless_frame(&self) -> Result<(), String> {
+        let path = Path::new(&self.out_dir).join(format!("frame_{:04}.png", self.frame_index));
+        // the recording takes the place of the pngs
+        if self.recorder.is_none() {
+            self.display.save_color_buffer_png(&path)?;
+        }
+        if self.headless_svg {
+            self.save_frame_svg(&path.with_extension("svg"))?;
+        }
+        Ok(())
+    }
+
+    fn write_frame_stats(&mut self) {
+        let writer = match &mut self.stats_csv {
+            Some(writer) => writer,
+            None => return,
+        };
+        if let Err(e) = writeln!(writer, "{}", frame_stats_csv_row(&self.frame_stats)) {
+            eprintln!("Failed to write the frame stats, no more will be saved: {}", e);
+            self.stats_csv = None;
         }
     }
 
     fn process_input(&mut self) {
-        let mut event_pump = self.sdl_context.event_pump().unwrap();
+        let sdl_context = match &self.sdl_context {
+            Some(sdl_context) => sdl_context,
+            None => return,
+        };
+        let mut event_pump = sdl_context.event_pump().unwrap();
         for event in event_pump.poll_iter() {
             match event {
                 Event::Quit { .. } => {
@@ -139,6 +363,57 @@ impl MainLoop {
         if keyboard_state.is_scancode_pressed(Scancode::D) {
             self.backface_culling_enabled = false;            
         }
+        if keyboard_state.is_scancode_pressed(Scancode::S) && !self.scene_saved {
+            self.save_scene();
+        }
+        self.scene_saved = keyboard_state.is_scancode_pressed(Scancode::S);
+        // the triangles are still the ones of the frame on the screen
+        if keyboard_state.is_scancode_pressed(Scancode::V) && !self.svg_saved {
+            let path = Path::new(&self.out_dir).join(format!("frame_{:04}.svg", self.frame_index.saturating_sub(1)));
+            match self.save_frame_svg(&path) {
+                Ok(()) => println!("Saved the frame to {}", path.display()),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        self.svg_saved = keyboard_state.is_scancode_pressed(Scancode::V);
+        if keyboard_state.is_scancode_pressed(Scancode::H) && !self.hud_key_down {
+            self.hud_visible = !self.hud_visible;
+        }
+        self.hud_key_down = keyboard_state.is_scancode_pressed(Scancode::H);
+        if keyboard_state.is_scancode_pressed(Scancode::T) && !self.transparency_key_down {
+            self.transparency = match self.transparency {
+                TransparencyMode::Sorted => TransparencyMode::ABuffer,
+                TransparencyMode::ABuffer => TransparencyMode::Sorted,
+            };
+        }
+        self.transparency_key_down = keyboard_state.is_scancode_pressed(Scancode::T);
+        if keyboard_state.is_scancode_pressed(Scancode::A) && !self.anti_aliasing_key_down {
+            let index = ANTI_ALIASING_NAMES
+                .iter()
+                .position(|(_, anti_aliasing)| *anti_aliasing == self.anti_aliasing)
+                .unwrap_or(0);
+            self.anti_aliasing = ANTI_ALIASING_NAMES[(index + 1) % ANTI_ALIASING_NAMES.len()].1;
+        }
+        self.anti_aliasing_key_down = keyboard_state.is_scancode_pressed(Scancode::A);
+        if keyboard_state.is_scancode_pressed(Scancode::L) && !self.smooth_lines_key_down {
+            self.line_style.smooth = !self.line_style.smooth;
+        }
+        self.smooth_lines_key_down = keyboard_state.is_scancode_pressed(Scancode::L);
+        if keyboard_state.is_scancode_pressed(Scancode::E) && !self.hidden_lines_key_down {
+            let index = HIDDEN_LINES_NAMES
+                .iter()
+                .position(|(_, hidden)| *hidden == self.line_style.hidden)
+                .unwrap_or(0);
+            self.line_style.hidden = HIDDEN_LINES_NAMES[(index + 1) % HIDDEN_LINES_NAMES.len()].1;
+        }
+        self.hidden_lines_key_down = keyboard_state.is_scancode_pressed(Scancode::E);
+        if keyboard_state.is_scancode_pressed(Scancode::O) && !self.line_edges_key_down {
+            self.line_style.edges = match self.line_style.edges {
+                LineEdges::All => LineEdges::Outline,
+                LineEdges::Outline => LineEdges::All,
+            };
+        }
+        self.line_edges_key_down = keyboard_state.is_scancode_pressed(Scancode::O);
         if keyboard_state.is_scancode_pressed(Scancode::Num1) {
             self.render_mode = RenderMode::WireframeWithDot;
         }
@@ -148,6 +423,9 @@ impl MainLoop {
         if keyboard_state.is_scancode_pressed(Scancode::Num3) {
             self.render_mode = RenderMode::FilledTriangles;
         }
+        if keyboard_state.is_scancode_pressed(Scancode::Num4) {
+            self.render_mode = RenderMode::HiddenLine;
+        }
         if keyboard_state.is_scancode_pressed(Scancode::Num5) {
             self.render_mode = RenderMode::FilledTrianglesAndWireframe;
         }
@@ -157,214 +435,229 @@ impl MainLoop {
         if keyboard_state.is_scancode_pressed(Scancode::Num7) {
             self.render_mode = RenderMode::TexturedAndWireframe;
         }
+        if keyboard_state.is_scancode_pressed(Scancode::F1) {
+            self.render_mode = RenderMode::Depth;
+        }
+        if keyboard_state.is_scancode_pressed(Scancode::F2) {
+            self.render_mode = RenderMode::Normals;
+        }
+        if keyboard_state.is_scancode_pressed(Scancode::F3) {
+            self.render_mode = RenderMode::Uvs;
+        }
+        if keyboard_state.is_scancode_pressed(Scancode::F4) {
+            self.render_mode = RenderMode::TriangleIndex;
+        }
+        if keyboard_state.is_scancode_pressed(Scancode::F5) {
+            self.render_mode = RenderMode::Overdraw;
+        }
     }
 
-    fn update(&mut self) {
-        // get the amount of time to sleep until the next frame should start
-        let time_to_wait: i128 = (FRAME_TARGET_TIME as i128)
-            - (self.timer.elapsed().as_millis() as i128 - self.previous_frame_time as i128);
-        if time_to_wait > 0 && time_to_wait <= (FRAME_TARGET_TIME as i128) {
-            thread::sleep(Duration::from_millis(time_to_wait as u64));
-        }
-        self.previous_frame_time = self.timer.elapsed().as_millis();
-
-        self.mesh.rotation.x += 0.01;
-        self.mesh.rotation.y += 0.01;
-        self.mesh.rotation.z += 0.01;
-
-        // self.mesh.scale.x += 0.002;
-        // self.mesh.scale.y += 0.001;
-        
-        // self.mesh.translation.x += 0.01;
-        self.mesh.translation.z = 5.0;
-        
-        let scale_matrix = mat4_make_scale(self.mesh.scale.x, self.mesh.scale.y, self.mesh.scale.z);
-        let translation_matrix = mat4_make_translation(self.mesh.translation.x, self.mesh.translation.y, self.mesh.translation.z);
-        let rotation_matrix_x = mat4_make_rotation_x(self.mesh.rotation.x);
-        let rotation_matrix_y = mat4_make_rotation_y(self.mesh.rotation.y);
-        let rotation_matrix_z = mat4_make_rotation_z(self.mesh.rotation.z);
-
-        // scale, then rotate, then translate in that order. otherwise issues occur
-        // with the matrix multiplications
-        let mut world_matrix = mat4_identity();
-        world_matrix = mat4_mul_mat4(&scale_matrix, &world_matrix);
-        world_matrix = mat4_mul_mat4(&rotation_matrix_x, &world_matrix);
-        world_matrix = mat4_mul_mat4(&rotation_matrix_y, &world_matrix);
-        world_matrix = mat4_mul_mat4(&rotation_matrix_z, &world_matrix);
-        world_matrix = mat4_mul_mat4(&translation_matrix, &world_matrix);
-
-        self.triangles_to_render.clear();
-
-        // loop all triangle faces of our mesh
-        for i in 0..self.mesh.faces.len() {
-            let mesh_face = &self.mesh.faces[i];
-            let face_vertices: [Vec3; 3] = [
-                self.mesh.vertices[mesh_face.a - 1],
-                self.mesh.vertices[mesh_face.b - 1],
-                self.mesh.vertices[mesh_face.c - 1],
-            ];
-
-            let mut projected_triangle = Triangle {
-                points: [Vec4 { x: 0.0, y: 0.0, z: 0.0, w: 0.0 }; 3],
-                texcoords: [
-                    mesh_face.a_uv.clone(),
-                    mesh_face.b_uv.clone(),
-                    mesh_face.c_uv.clone()
-                ],
-                color: mesh_face.color,
-                avg_depth: 0.0
-            };
-
-            let mut transformed_vertices = [Vec4 {
-                x: 0.0,
-                y: 0.0,
-                z: 0.0,
-                w: 1.0
-            }; 3];
-            // transform the vertices
-            for j in 0..3 {
-                let mut transformed_vertex = vec4_from_vec3(&face_vertices[j]);
-
-                transformed_vertex = mat4_mul_vec4(&world_matrix, &transformed_vertex);
-                transformed_vertices[j] = transformed_vertex;
-            }
-
-            // check backface culling
-            let vector_a = vec3_from_vec4(&transformed_vertices[0]);
-            let vector_b = vec3_from_vec4(&transformed_vertices[1]);
-            let vector_c = vec3_from_vec4(&transformed_vertices[2]);
-
-            let mut vector_ab = vec3_sub(&vector_b, &vector_a);
-            let mut vector_ac = vec3_sub(&vector_c, &vector_a);
-            vec3_normalize(&mut vector_ab);
-            vec3_normalize(&mut vector_ac);
+    fn save_scene(&self) {
+        let settings = RenderSettings {
+            render_mode: self.render_mode,
+            backface_culling_enabled: self.backface_culling_enabled,
+            transparency: self.transparency,
+            anti_aliasing: self.anti_aliasing,
+            line_style: self.line_style,
+        };
+        match save_scene_file(&self.save_path, &self.scene, &settings) {
+            Ok(()) => println!("Saved the scene to {}", self.save_path),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
 
-            let mut normal = vec3_cross(&vector_ab, &vector_ac);
-            vec3_normalize(&mut normal);
+    fn save_frame_svg(&self, path: &Path) -> Result<(), String> {
+        save_svg(
+            path,
+            &self.triangles_to_render,
+            self.display.width,
+            self.display.height,
+            self.render_mode,
+            &self.line_style)
+    }
 
-            let camera_ray = vec3_sub(&self.camera_position, &vector_a);
+    fn update(&mut self) {
+        let delta_time = if self.headless_frames_left.is_some() {
+            self.headless_delta_time
+        } else {
+            clock_tick(&mut self.clock)
+        };
+
+        frame_rate_counter_add(&mut self.frame_rate, delta_time);
+        self.frame_stats.frame_index = self.frame_index;
+        self.frame_stats.delta_time = delta_time;
+        // the first frame is where the time starts from
+        if self.frame_index > 0 {
+            self.time += delta_time;
+        }
 
-            let dot_normal_camera = vec3_dot(&normal, &camera_ray);
+        let steps = fixed_timestep_advance(&mut self.timestep, delta_time);
+        for _ in 0..steps {
+            self.simulate(self.timestep.step);
+        }
 
-            // bypass the triangles facing away from the camera
-            if dot_normal_camera < 0.0 && self.backface_culling_enabled {
-                continue;
-            }
+        // draw the spinning nodes part of the way from their previous rotation
+        // to the current one, by how far this frame is into the next step
+        let alpha = fixed_timestep_alpha(&self.timestep);
+        for spin in &self.spins {
+            self.scene.nodes[spin.node].transform.rotation = quat_slerp(&spin.previous, &spin.current, alpha);
+        }
+        if let Some((position, rotation)) = camera_path_sample(&self.scene.camera_path, self.time) {
+            self.scene.camera.position = position;
+            self.scene.camera.rotation = rotation;
+        }
 
-            // project the vertices
-            for j in 0..3 {
-                projected_triangle.points[j] = mat4_mul_vec4_project(&self.proj_matrix, &transformed_vertices[j]);
-                
-                // scale into the view
-                projected_triangle.points[j].x *= SCREEN_WIDTH as f32 / 2.0;
-                projected_triangle.points[j].y *= SCREEN_HEIGHT as f32 / 2.0;
+        scene_update_world_matrices(&mut self.scene);
 
-                // invert y values to account for flipped y screen coordinates
-                projected_triangle.points[j].y *= -1.0;
+        self.frame_stats.pipeline = assemble_scene_triangles(
+            &mut self.triangles_to_render,
+            &self.scene,
+            &mut self.vertex_cache,
+            self.display.width,
+            self.display.height,
+            self.backface_culling_enabled);
 
-                // translate to the middle of the screen
-                projected_triangle.points[j].x += SCREEN_WIDTH as f32 / 2.0;
-                projected_triangle.points[j].y += SCREEN_HEIGHT as f32 / 2.0;
-            }
-            let light_intensity_factor = -vec3_dot(&normal, &self.light.direction);
-            projected_triangle.color = light_apply_intensity(&projected_triangle.color, light_intensity_factor);
+        let sort_start = Instant::now();
+        sort_triangles_by_depth(&mut self.triangles_to_render);
+        self.frame_stats.sort_time = sort_start.elapsed();
+    }
 
-            projected_triangle.avg_depth = 
-                (transformed_vertices[0].z + 
-                 transformed_vertices[1].z + 
-                 transformed_vertices[2].z) / 3.0;
-            self.triangles_to_render.push(projected_triangle);
+    // moves the simulation forward by one fixed step of `step` seconds
+    fn simulate(&mut self, step: f32) {
+        // apply this step's rotation on top of the current orientation, the
+        // quaternion is renormalized so floating point drift doesn't build up
+        let angle = SPIN_SPEED * step;
+        let step_rotation = quat_from_euler(angle, angle, angle, EulerOrder::XYZ);
+        for spin in &mut self.spins {
+            spin.previous = spin.current;
+            spin.current = quat_normalize(&quat_mul(&spin.current, &step_rotation));
         }
-
-        // sort the triangles based upon avg_depth to implement a painters algorithm
-        self.triangles_to_render.sort_unstable_by(
-            |a, b| b.avg_depth.partial_cmp(&a.avg_depth).unwrap_or(std::cmp::Ordering::Equal));
     }
 
     fn render(&mut self) {
-        for i in 0..self.triangles_to_render.len() {
-            let triangle = &self.triangles_to_render[i];
-            match self.render_mode {
-                RenderMode::FilledTriangles | 
-                RenderMode::FilledTrianglesAndWireframe => {
-                    draw_filled_triangle(
-                        &mut self.display,
-                        triangle.points[0].x as usize,
-                        triangle.points[0].y as usize,
-                        triangle.points[1].x as usize,
-                        triangle.points[1].y as usize,
-                        triangle.points[2].x as usize,
-                        triangle.points[2].y as usize,
-                        triangle.color);    
-                },
-                _ => {}
-            };
-
-            match self.render_mode {
-                RenderMode::Textured |
-                RenderMode::TexturedAndWireframe => {
-                    draw_textured_triangle(
-                        &mut self.display,
-                        triangle.points[0].x as usize,
-                        triangle.points[0].y as usize,
-                        triangle.texcoords[0].u,
-                        triangle.texcoords[0].v,
-                        triangle.points[1].x as usize,
-                        triangle.points[1].y as usize,
-                        triangle.texcoords[1].u,
-                        triangle.texcoords[1].v,
-                        triangle.points[2].x as usize,
-                        triangle.points[2].y as usize,
-                        triangle.texcoords[2].u,
-                        triangle.texcoords[2].v,
-                        &self.mesh_texture,
-                        self.texture_width,
-                        self.texture_height);
-                },
-                _ => {}
-            };
-
-            match self.render_mode {
-                RenderMode::Wireframe |
-                RenderMode::WireframeWithDot |
-                RenderMode::FilledTrianglesAndWireframe |
-                RenderMode::TexturedAndWireframe => {
-                    self.display.draw_triangle(
-                        triangle.points[0].x as usize,
-                        triangle.points[0].y as usize,
-                        triangle.points[1].x as usize,
-                        triangle.points[1].y as usize,
-                        triangle.points[2].x as usize,
-                        triangle.points[2].y as usize,
-                        Color::RGBA(0, 0, 255, 255));
-                },
-                _ => {}
-            };
-
-            match self.render_mode {
-                RenderMode::WireframeWithDot => {
-                    self.display.draw_rect((triangle.points[0].x - 3.0) as usize, (triangle.points[0].y - 3.0) as usize, 6, 6, Color::RGBA(255, 0, 0, 0));
-                    self.display.draw_rect((triangle.points[1].x - 3.0) as usize, (triangle.points[1].y - 3.0) as usize, 6, 6, Color::RGBA(255, 0, 0, 0));
-                    self.display.draw_rect((triangle.points[2].x - 3.0) as usize, (triangle.points[2].y - 3.0) as usize, 6, 6, Color::RGBA(255, 0, 0, 0));
-                },
-                _ => {}
+        if self.display.anti_aliasing() != self.anti_aliasing {
+            // the new sample buffers start out empty, so clear them like the
+            // end of the last frame would have
+            if let Err(e) = self.display.set_anti_aliasing(self.anti_aliasing) {
+                self.anti_aliasing = self.display.anti_aliasing();
+                eprintln!("warning: {}, keeping {} instead", e, anti_aliasing_name(self.anti_aliasing));
+            }
+            self.display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+            self.display.clear_depth_buffer();
+        }
+        // after the anti-aliasing, since that sets how big the a-buffer is
+        match self.transparency {
+            TransparencyMode::Sorted => self.display.disable_a_buffer(),
+            TransparencyMode::ABuffer => {
+                if let Err(e) = self.display.enable_a_buffer(self.a_buffer_fragments) {
+                    eprintln!("warning: {}, using sorted transparency instead", e);
+                    self.transparency = TransparencyMode::Sorted;
+                }
+            },
+        }
+        self.display.set_line_style(self.line_style);
+
+        let raster_start = Instant::now();
+        let raster_stats = render_triangles(
+            &mut self.display,
+            &self.triangles_to_render,
+            self.render_mode,
+            &self.scene.textures,
+            self.render_thread_count);
+        self.frame_stats.raster_time = raster_start.elapsed();
+        self.frame_stats.pixels_written = raster_stats.pixels_written;
+        self.frame_stats.fragments_stored = raster_stats.fragments_stored;
+        self.frame_stats.fragments_overflowed = raster_stats.fragments_overflowed;
+        // supersampling writes samples rather than screen pixels
+        let (render_width, render_height) = self.display.render_size();
+        self.frame_stats.overdraw = overdraw(raster_stats.pixels_written, render_width, render_height);
+
+        // recorded before the hud goes on, so the video only has the scene in it
+        let record_start = Instant::now();
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = video_recorder_add_frame(recorder, &self.display.color_buffer_rgba(), self.time) {
+                eprintln!("{}, stopping the recording", e);
+                self.finish_recording();
+                if self.headless_frames_left.is_some() {
+                    self.headless_failed = true;
+                }
+            }
+        }
+        let record_time = record_start.elapsed();
+
+        if self.hud_visible {
+            let info = HudInfo {
+                fps: self.frame_rate.fps,
+                frame_time_ms: self.frame_rate.frame_time_ms,
+                stats: self.frame_stats.pipeline,
+                render_mode: self.render_mode,
+                backface_culling_enabled: self.backface_culling_enabled,
+                transparency: self.transparency,
+                fragments_overflowed: self.frame_stats.fragments_overflowed,
+                anti_aliasing: self.anti_aliasing,
+                line_style: self.line_style,
+                camera_position: self.scene.camera.position,
             };
+            draw_hud(&mut self.display, &info);
         }
 
-        let texture_creator = self.display.canvas.texture_creator();
-        let mut texture = texture_creator
-            .create_texture_streaming(
-                PixelFormatEnum::ARGB8888,
-                SCREEN_WIDTH as u32,
-                SCREEN_HEIGHT as u32,
-            )
-            .unwrap();
+        // saving a headless frame stands in for showing it in the window
+        let present_start = Instant::now();
+        if let Some(frames_left) = self.headless_frames_left {
+            if !self.headless_failed {
+                if let Err(e) = self.save_headless_frame() {
+                    eprintln!("{}", e);
+                    self.headless_failed = true;
+                }
+            }
+            self.headless_frames_left = Some(frames_left - 1);
+            if frames_left <= 1 || self.headless_failed {
+                self.is_running = false;
+            }
+        }
+        self.frame_index += 1;
 
-        self.display.render_color_buffer(&mut texture);
+        self.display.present();
         self.display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
-
-        self.display.canvas.copy(&texture, None, None).unwrap();
-        // swap front buffer and back buffer
-        self.display.canvas.present();
+        self.display.clear_depth_buffer();
+        self.frame_stats.present_time = record_time + present_start.elapsed();
     }
-}
\ No newline at end of file
+}
+
+// a single spinning node in front of the camera, showing the .obj file if one
+// was given or else the cube. the cube gets the crate texture when no other
+// texture was asked for
+fn default_scene(model_path: Option<&str>, texture_path: Option<&str>) -> Result<Scene, String> {
+    let mut scene = scene_new();
+    let mesh = match model_path {
+        Some(path) => scene_add_mesh(&mut scene, "model", path, load_obj_file(path)?),
+        None => scene_add_mesh(&mut scene, "cube", "builtin:cube", get_cube_mesh()),
+    };
+
+    let texture_path = match (model_path, texture_path) {
+        (_, Some(path)) => Some(path),
+        (None, None) => Some(DEFAULT_TEXTURE_PATH),
+        (Some(_), None) => None,
+    };
+    let texture = match texture_path {
+        Some(path) => match load_png_texture(path) {
+            Ok(texture) => Some(scene_add_texture(&mut scene, "texture", path, texture)),
+            Err(e) => return Err(format!("Failed to load texture '{}': {}", path, e)),
+        },
+        None => None,
+    };
+
+    let mut node = scene_node_new(if model_path.is_some() { "model" } else { "cube" });
+    node.mesh = Some(mesh);
+    node.material.texture = texture;
+    node.transform.translation.z = 5.0;
+    node.spin = true;
+    scene_add_node(&mut scene, None, node);
+
+    scene.lights.push(Light {
+        direction: Vec3 {
+            x: 0.0, y: 0.0, z: 1.0
+        }
+    });
+    Ok(scene)
+}
@@ -0,0 +1,117 @@
+use sdl2::pixels::Color;
+
+// how a material's pixels are combined with what's already on the screen.
+// opaque and cutout triangles are drawn first and write to the depth buffer,
+// the others are see through and are drawn afterwards, furthest first,
+// testing against the depth buffer without writing to it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BlendMode {
+    // replaces the pixel, alpha is ignored
+    Opaque,
+    // like opaque, but pixels with an alpha below the cutoff (0 to 1) are
+    // skipped altogether, for leaves and fences
+    Cutout(f32),
+    // mixes in by alpha, like glass
+    Alpha,
+    // adds on top scaled by alpha, for glows and fire
+    Additive,
+    // darkens what's behind by the colour, scaled by alpha, like tinted film
+    Multiply,
+}
+
+pub const DEFAULT_ALPHA_CUTOFF: f32 = 0.5;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Material {
+    // multiplied with the face colours of the mesh
+    pub color: Color,
+    // index into the scene's textures, used by the textured render modes
+    pub texture: Option<usize>,
+    // multiplied into the alpha of the colour and texture, from 0 to 1
+    pub opacity: f32,
+    pub blend_mode: BlendMode,
+}
+
+pub fn material_default() -> Material {
+    Material {
+        color: Color::RGBA(255, 255, 255, 255),
+        texture: None,
+        opacity: 1.0,
+        blend_mode: BlendMode::Opaque,
+    }
+}
+
+pub fn color_modulate(a: &Color, b: &Color) -> Color {
+    Color::RGBA(
+        ((a.r as u32 * b.r as u32) / 255) as u8,
+        ((a.g as u32 * b.g as u32) / 255) as u8,
+        ((a.b as u32 * b.b as u32) / 255) as u8,
+        ((a.a as u32 * b.a as u32) / 255) as u8)
+}
+
+// the names used for the blend modes in scene files. a cutout gets its
+// cutoff from a separate alpha_cutoff key
+pub const BLEND_MODE_NAMES: [&str; 5] = ["opaque", "cutout", "alpha", "additive", "multiply"];
+
+pub fn blend_mode_from_name(name: &str) -> Option<BlendMode> {
+    match name {
+        "opaque" => Some(BlendMode::Opaque),
+        "cutout" => Some(BlendMode::Cutout(DEFAULT_ALPHA_CUTOFF)),
+        "alpha" => Some(BlendMode::Alpha),
+        "additive" => Some(BlendMode::Additive),
+        "multiply" => Some(BlendMode::Multiply),
+        _ => None,
+    }
+}
+
+pub fn blend_mode_name(blend_mode: BlendMode) -> &'static str {
+    match blend_mode {
+        BlendMode::Opaque => "opaque",
+        BlendMode::Cutout(_) => "cutout",
+        BlendMode::Alpha => "alpha",
+        BlendMode::Additive => "additive",
+        BlendMode::Multiply => "multiply",
+    }
+}
+
+// whether triangles with this blend mode have to be drawn after the opaque
+// ones, sorted back to front and without writing depth
+pub fn blend_mode_is_transparent(blend_mode: BlendMode) -> bool {
+    matches!(blend_mode, BlendMode::Alpha | BlendMode::Additive | BlendMode::Multiply)
+}
+
+// the colour a pixel ends up when `source` is drawn over `destination`, or
+// None when a cutout pixel is skipped. the result keeps the destination's
+// alpha, except for opaque which copies the source like drawing always has
+pub fn blend_colors(source: &Color, destination: &Color, blend_mode: BlendMode) -> Option<Color> {
+    let alpha = source.a as u32;
+    let inverse_alpha = 255 - alpha;
+    let mix = |src: u8, dst: u8| ((src as u32 * alpha + dst as u32 * inverse_alpha + 127) / 255) as u8;
+    let add = |src: u8, dst: u8| (dst as u32 + (src as u32 * alpha + 127) / 255).min(255) as u8;
+    // multiplying by a colour of 255 leaves the destination alone, so the
+    // alpha fades the colour towards that
+    let multiply = |src: u8, dst: u8| {
+        let factor = mix(src, 255) as u32;
+        ((dst as u32 * factor + 127) / 255) as u8
+    };
+
+    match blend_mode {
+        BlendMode::Opaque => Some(*source),
+        BlendMode::Cutout(cutoff) => {
+            if (source.a as f32) < cutoff * 255.0 {
+                None
+            } else {
+                Some(Color::RGBA(source.r, source.g, source.b, 255))
+            }
+        },
+        BlendMode::Alpha => Some(Color::RGBA(
+            mix(source.r, destination.r), mix(source.g, destination.g), mix(source.b, destination.b), destination.a)),
+        BlendMode::Additive => Some(Color::RGBA(
+            add(source.r, destination.r), add(source.g, destination.g), add(source.b, destination.b), destination.a)),
+        BlendMode::Multiply => Some(Color::RGBA(
+            multiply(source.r, destination.r),
+            multiply(source.g, destination.g),
+            multiply(source.b, destination.b),
+            destination.a)),
+    }
+}
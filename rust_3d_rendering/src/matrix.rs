@@ -1,9 +1,21 @@
-use crate::vector::Vec4;
+use crate::vector::{Vec3, Vec4};
 
+// how close to singular a matrix can get before it's treated as one that
+// can't be inverted. the determinant is measured against the largest it
+// could be with rows (or columns) that long, which is when they're all at
+// right angles, so a tiny but well shaped matrix can still be inverted
+const SINGULAR_EPSILON: f32 = 1e-6;
+
+#[derive(Debug, Copy, Clone)]
 pub struct Mat4 {
     pub m: [[f32; 4]; 4]
 }
 
+#[derive(Debug, Copy, Clone)]
+pub struct Mat3 {
+    pub m: [[f32; 3]; 3]
+}
+
 pub fn mat4_identity() -> Mat4 {
     Mat4 {
         m: [
@@ -76,6 +88,63 @@ pub fn mat4_mul_vec4(m: &Mat4, v: &Vec4) -> Vec4 {
     }
 }
 
+// transforms every point in `points` (as a position, so w = 1) by the matrix
+// and writes the results into `out`, which is cleared first so the same
+// buffer can be reused every frame without reallocating. this does the whole
+// vertex array in one go so a vertex shared by several faces only gets
+// transformed once, and uses SSE on x86_64 to do the 4 rows at the same time.
+// in a release build that's about as fast as the plain loop, which the
+// compiler vectorizes well by itself, so the win is in doing each vertex once
+pub fn mat4_transform_points(m: &Mat4, points: &[Vec3], out: &mut Vec<Vec4>) {
+    out.clear();
+    out.reserve(points.len());
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        mat4_transform_points_sse(m, points, out);
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        mat4_transform_points_scalar(m, points, out);
+    }
+}
+
+// the plain version used where there's no SSE. it's public so it's built and
+// tested on x86_64 too, where mat4_transform_points never calls it. like that
+// it clears `out` first
+pub fn mat4_transform_points_scalar(m: &Mat4, points: &[Vec3], out: &mut Vec<Vec4>) {
+    out.clear();
+    for point in points {
+        out.push(mat4_mul_vec4(m, &Vec4 { x: point.x, y: point.y, z: point.z, w: 1.0 }));
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn mat4_transform_points_sse(m: &Mat4, points: &[Vec3], out: &mut Vec<Vec4>) {
+    use std::arch::x86_64::{_mm_add_ps, _mm_mul_ps, _mm_set1_ps, _mm_setr_ps, _mm_storeu_ps};
+
+    // SSE2 is part of the x86_64 baseline, so these are always available.
+    // each column of the matrix goes into one register, then a point is
+    // col0 * x + col1 * y + col2 * z + col3, added up in the same order as
+    // mat4_mul_vec4 so both give exactly the same result
+    unsafe {
+        let col0 = _mm_setr_ps(m.m[0][0], m.m[1][0], m.m[2][0], m.m[3][0]);
+        let col1 = _mm_setr_ps(m.m[0][1], m.m[1][1], m.m[2][1], m.m[3][1]);
+        let col2 = _mm_setr_ps(m.m[0][2], m.m[1][2], m.m[2][2], m.m[3][2]);
+        let col3 = _mm_setr_ps(m.m[0][3], m.m[1][3], m.m[2][3], m.m[3][3]);
+
+        let mut result = [0.0f32; 4];
+        for point in points {
+            let mut sum = _mm_mul_ps(col0, _mm_set1_ps(point.x));
+            sum = _mm_add_ps(sum, _mm_mul_ps(col1, _mm_set1_ps(point.y)));
+            sum = _mm_add_ps(sum, _mm_mul_ps(col2, _mm_set1_ps(point.z)));
+            sum = _mm_add_ps(sum, col3);
+            _mm_storeu_ps(result.as_mut_ptr(), sum);
+            out.push(Vec4 { x: result[0], y: result[1], z: result[2], w: result[3] });
+        }
+    }
+}
+
 pub fn mat4_mul_mat4(a: &Mat4, b: &Mat4) -> Mat4 {
     let mut m = mat4_identity();
 
@@ -115,4 +184,202 @@ pub fn mat4_mul_vec4_project(mat_proj: &Mat4, v: &Vec4) -> Vec4 {
         result.z /= result.w;
     }
     return result;
-}
\ No newline at end of file
+}
+
+pub fn mat4_transpose(m: &Mat4) -> Mat4 {
+    let mut result = mat4_identity();
+    for i in 0..4 {
+        for j in 0..4 {
+            result.m[i][j] = m.m[j][i];
+        }
+    }
+    result
+}
+
+// the 2x2 determinants of the top two rows and of the bottom two rows, which
+// both the determinant and the inverse are built from
+fn mat4_minors(a: &[[f32; 4]; 4]) -> ([f32; 6], [f32; 6]) {
+    let top = [
+        a[0][0] * a[1][1] - a[1][0] * a[0][1],
+        a[0][0] * a[1][2] - a[1][0] * a[0][2],
+        a[0][0] * a[1][3] - a[1][0] * a[0][3],
+        a[0][1] * a[1][2] - a[1][1] * a[0][2],
+        a[0][1] * a[1][3] - a[1][1] * a[0][3],
+        a[0][2] * a[1][3] - a[1][2] * a[0][3],
+    ];
+    let bottom = [
+        a[2][0] * a[3][1] - a[3][0] * a[2][1],
+        a[2][0] * a[3][2] - a[3][0] * a[2][2],
+        a[2][0] * a[3][3] - a[3][0] * a[2][3],
+        a[2][1] * a[3][2] - a[3][1] * a[2][2],
+        a[2][1] * a[3][3] - a[3][1] * a[2][3],
+        a[2][2] * a[3][3] - a[3][2] * a[2][3],
+    ];
+    (top, bottom)
+}
+
+// combines the minors into the full 4x4 determinant by the Laplace expansion
+fn mat4_minors_determinant(top: &[f32; 6], bottom: &[f32; 6]) -> f32 {
+    let [s0, s1, s2, s3, s4, s5] = *top;
+    let [c0, c1, c2, c3, c4, c5] = *bottom;
+    s0 * c5 - s1 * c4 + s2 * c3 + s3 * c2 - s4 * c1 + s5 * c0
+}
+
+pub fn mat4_determinant(m: &Mat4) -> f32 {
+    let (top, bottom) = mat4_minors(&m.m);
+    mat4_minors_determinant(&top, &bottom)
+}
+
+// whether a matrix with determinant `det` is too close to singular to invert.
+// the size of the determinant on its own depends on the scale of the matrix,
+// 0.001 on every axis gives 1e-9, so it's compared against the product of
+// the lengths of the rows or of the columns, whichever is smaller
+fn determinant_is_singular<const N: usize>(det: f32, a: &[[f32; N]; N]) -> bool {
+    let row_lengths: f32 = a.iter().map(|row| row.iter().map(|v| v * v).sum::<f32>().sqrt()).product();
+    let column_lengths: f32 = (0..N).map(|j| a.iter().map(|row| row[j] * row[j]).sum::<f32>().sqrt()).product();
+    det.is_nan() || det.abs() <= SINGULAR_EPSILON * row_lengths.min(column_lengths) || !(1.0 / det).is_finite()
+}
+
+// general inverse of any 4x4 matrix, returns None if the matrix is singular
+pub fn mat4_inverse(m: &Mat4) -> Option<Mat4> {
+    let a = &m.m;
+    let (top, bottom) = mat4_minors(a);
+    let det = mat4_minors_determinant(&top, &bottom);
+    if determinant_is_singular(det, a) {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let [s0, s1, s2, s3, s4, s5] = top;
+    let [c0, c1, c2, c3, c4, c5] = bottom;
+
+    let mut result = mat4_identity();
+    result.m[0][0] = ( a[1][1] * c5 - a[1][2] * c4 + a[1][3] * c3) * inv_det;
+    result.m[0][1] = (-a[0][1] * c5 + a[0][2] * c4 - a[0][3] * c3) * inv_det;
+    result.m[0][2] = ( a[3][1] * s5 - a[3][2] * s4 + a[3][3] * s3) * inv_det;
+    result.m[0][3] = (-a[2][1] * s5 + a[2][2] * s4 - a[2][3] * s3) * inv_det;
+
+    result.m[1][0] = (-a[1][0] * c5 + a[1][2] * c2 - a[1][3] * c1) * inv_det;
+    result.m[1][1] = ( a[0][0] * c5 - a[0][2] * c2 + a[0][3] * c1) * inv_det;
+    result.m[1][2] = (-a[3][0] * s5 + a[3][2] * s2 - a[3][3] * s1) * inv_det;
+    result.m[1][3] = ( a[2][0] * s5 - a[2][2] * s2 + a[2][3] * s1) * inv_det;
+
+    result.m[2][0] = ( a[1][0] * c4 - a[1][1] * c2 + a[1][3] * c0) * inv_det;
+    result.m[2][1] = (-a[0][0] * c4 + a[0][1] * c2 - a[0][3] * c0) * inv_det;
+    result.m[2][2] = ( a[3][0] * s4 - a[3][1] * s2 + a[3][3] * s0) * inv_det;
+    result.m[2][3] = (-a[2][0] * s4 + a[2][1] * s2 - a[2][3] * s0) * inv_det;
+
+    result.m[3][0] = (-a[1][0] * c3 + a[1][1] * c1 - a[1][2] * c0) * inv_det;
+    result.m[3][1] = ( a[0][0] * c3 - a[0][1] * c1 + a[0][2] * c0) * inv_det;
+    result.m[3][2] = (-a[3][0] * s3 + a[3][1] * s1 - a[3][2] * s0) * inv_det;
+    result.m[3][3] = ( a[2][0] * s3 - a[2][1] * s1 + a[2][2] * s0) * inv_det;
+    Some(result)
+}
+
+// faster inverse for matrices built only from scale, rotation and translation
+// (bottom row of 0 0 0 1), like the world matrix. it inverts the upper 3x3 part
+// and then moves the translation back through it
+pub fn mat4_inverse_affine(m: &Mat4) -> Option<Mat4> {
+    let inv_upper = mat3_inverse(&mat3_from_mat4(m))?;
+    let translation = Vec3 { x: m.m[0][3], y: m.m[1][3], z: m.m[2][3] };
+    let inv_translation = mat3_mul_vec3(&inv_upper, &translation);
+
+    let mut result = mat4_identity();
+    for i in 0..3 {
+        for j in 0..3 {
+            result.m[i][j] = inv_upper.m[i][j];
+        }
+    }
+    result.m[0][3] = -inv_translation.x;
+    result.m[1][3] = -inv_translation.y;
+    result.m[2][3] = -inv_translation.z;
+    Some(result)
+}
+
+// the inverse transpose of the upper 3x3 part of the world matrix. normals
+// have to be transformed with this instead of the world matrix itself, or a
+// non uniform scale would tilt them so they're no longer perpendicular to
+// the surface
+pub fn mat4_normal_matrix(m: &Mat4) -> Option<Mat3> {
+    let inv_upper = mat3_inverse(&mat3_from_mat4(m))?;
+    Some(mat3_transpose(&inv_upper))
+}
+
+pub fn mat3_identity() -> Mat3 {
+    Mat3 {
+        m: [
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0]
+        ]
+    }
+}
+
+pub fn mat3_from_mat4(m: &Mat4) -> Mat3 {
+    let mut result = mat3_identity();
+    for i in 0..3 {
+        for j in 0..3 {
+            result.m[i][j] = m.m[i][j];
+        }
+    }
+    result
+}
+
+pub fn mat3_transpose(m: &Mat3) -> Mat3 {
+    let mut result = mat3_identity();
+    for i in 0..3 {
+        for j in 0..3 {
+            result.m[i][j] = m.m[j][i];
+        }
+    }
+    result
+}
+
+pub fn mat3_determinant(m: &Mat3) -> f32 {
+    let a = &m.m;
+    a[0][0] * (a[1][1] * a[2][2] - a[1][2] * a[2][1]) -
+    a[0][1] * (a[1][0] * a[2][2] - a[1][2] * a[2][0]) +
+    a[0][2] * (a[1][0] * a[2][1] - a[1][1] * a[2][0])
+}
+
+pub fn mat3_inverse(m: &Mat3) -> Option<Mat3> {
+    let det = mat3_determinant(m);
+    if determinant_is_singular(det, &m.m) {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let a = &m.m;
+
+    let mut result = mat3_identity();
+    result.m[0][0] = (a[1][1] * a[2][2] - a[1][2] * a[2][1]) * inv_det;
+    result.m[0][1] = (a[0][2] * a[2][1] - a[0][1] * a[2][2]) * inv_det;
+    result.m[0][2] = (a[0][1] * a[1][2] - a[0][2] * a[1][1]) * inv_det;
+    result.m[1][0] = (a[1][2] * a[2][0] - a[1][0] * a[2][2]) * inv_det;
+    result.m[1][1] = (a[0][0] * a[2][2] - a[0][2] * a[2][0]) * inv_det;
+    result.m[1][2] = (a[0][2] * a[1][0] - a[0][0] * a[1][2]) * inv_det;
+    result.m[2][0] = (a[1][0] * a[2][1] - a[1][1] * a[2][0]) * inv_det;
+    result.m[2][1] = (a[0][1] * a[2][0] - a[0][0] * a[2][1]) * inv_det;
+    result.m[2][2] = (a[0][0] * a[1][1] - a[0][1] * a[1][0]) * inv_det;
+    Some(result)
+}
+
+pub fn mat3_mul_vec3(m: &Mat3, v: &Vec3) -> Vec3 {
+    Vec3 {
+        x: m.m[0][0] * v.x + m.m[0][1] * v.y + m.m[0][2] * v.z,
+        y: m.m[1][0] * v.x + m.m[1][1] * v.y + m.m[1][2] * v.z,
+        z: m.m[2][0] * v.x + m.m[2][1] * v.y + m.m[2][2] * v.z,
+    }
+}
+
+pub fn mat3_mul_mat3(a: &Mat3, b: &Mat3) -> Mat3 {
+    let mut m = mat3_identity();
+
+    for i in 0..3 {
+        for j in 0..3 {
+            m.m[i][j] = 
+                a.m[i][0] * b.m[0][j] + 
+                a.m[i][1] * b.m[1][j] + 
+                a.m[i][2] * b.m[2][j];
+        }
+    }
+    m
+}
@@ -2,17 +2,15 @@ use rand::Rng;
 use sdl2::pixels::Color;
 
 use crate::texture::Tex2;
-use crate::{triangle::Face, vector::Vec3};
+use crate::{triangle::Face, vector::{Vec3, vec3_cross, vec3_dot, vec3_max, vec3_min, vec3_normalize_or_zero}};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
 
 pub struct Mesh {
     pub vertices: Vec<Vec3>,
-    pub faces: Vec<Face>,
-    pub rotation: Vec3,
-    pub scale: Vec3,
-    pub translation: Vec3
+    pub faces: Vec<Face>
 }
 
 pub fn get_cube_vertices() -> Vec<Vec3> {
@@ -54,37 +52,169 @@ fn get_cube_faces() -> Vec<Face> {
 pub fn get_cube_mesh() -> Mesh {
     Mesh {
         vertices: get_cube_vertices(),
-        faces: get_cube_faces(),
-        rotation: Vec3 {
-            x: 0.0,
-            y: 0.0,
-            z: 0.0,
-        },
-        scale: Vec3 {
-            x: 1.0,
-            y: 1.0,
-            z: 1.0,
-        },
-        translation: Vec3 {
-            x: 0.0,
-            y: 0.0,
-            z: 0.0,
+        faces: get_cube_faces()
+    }
+}
+
+// a sphere of radius 1 made of `rings` bands from pole to pole, each cut into
+// `segments` quads. the vertices on the seam and the poles are shared, so
+// there are (rings - 1) * segments + 2 vertices and 2 * (rings - 1) * segments
+// faces. the faces wind the same way as the cube so backface culling works
+pub fn get_sphere_mesh(rings: usize, segments: usize) -> Mesh {
+    let rings = rings.max(2);
+    let segments = segments.max(3);
+    let white = Color::RGBA(255, 255, 255, 255);
+
+    let mut vertices = vec![Vec3 { x: 0.0, y: 1.0, z: 0.0 }];
+    for ring in 1..rings {
+        let polar = std::f32::consts::PI * ring as f32 / rings as f32;
+        for segment in 0..segments {
+            let azimuth = 2.0 * std::f32::consts::PI * segment as f32 / segments as f32;
+            vertices.push(Vec3 {
+                x: polar.sin() * azimuth.cos(),
+                y: polar.cos(),
+                z: polar.sin() * azimuth.sin(),
+            });
+        }
+    }
+    vertices.push(Vec3 { x: 0.0, y: -1.0, z: 0.0 });
+
+    // obj style indices that start at 1
+    let top = 1;
+    let bottom = vertices.len();
+    let ring_vertex = |ring: usize, segment: usize| 2 + (ring - 1) * segments + segment % segments;
+    let face = |a: usize, b: usize, c: usize| Face {
+        a,
+        b,
+        c,
+        a_uv: Tex2 { u: 0.0, v: 0.0 },
+        b_uv: Tex2 { u: 0.0, v: 0.0 },
+        c_uv: Tex2 { u: 0.0, v: 0.0 },
+        color: white,
+    };
+
+    let mut faces = Vec::with_capacity(2 * (rings - 1) * segments);
+    for segment in 0..segments {
+        faces.push(face(top, ring_vertex(1, segment + 1), ring_vertex(1, segment)));
+    }
+    for ring in 1..rings - 1 {
+        for segment in 0..segments {
+            let a = ring_vertex(ring, segment);
+            let b = ring_vertex(ring, segment + 1);
+            let c = ring_vertex(ring + 1, segment);
+            let d = ring_vertex(ring + 1, segment + 1);
+            faces.push(face(a, d, c));
+            faces.push(face(a, b, d));
+        }
+    }
+    for segment in 0..segments {
+        faces.push(face(bottom, ring_vertex(rings - 1, segment), ring_vertex(rings - 1, segment + 1)));
+    }
+
+    Mesh { vertices, faces }
+}
+
+// the smallest and largest x, y and z of all the vertices, or None for a
+// mesh without any
+pub fn mesh_bounds(mesh: &Mesh) -> Option<(Vec3, Vec3)> {
+    let first = *mesh.vertices.first()?;
+    let mut min = first;
+    let mut max = first;
+    for vertex in &mesh.vertices {
+        min = vec3_min(&min, vertex);
+        max = vec3_max(&max, vertex);
+    }
+    Some((min, max))
+}
+
+// how the faces of a mesh join up, worked out once when the mesh is added to
+// a scene so the outline of the wireframe can be found every frame without
+// searching for the neighbours again. edge 0 of a face runs from a to b,
+// edge 1 from b to c and edge 2 from c back to a
+pub struct MeshEdges {
+    // the face on the other side of each edge, or None on an open border or
+    // where more than two faces meet at the edge
+    pub neighbors: Vec<[Option<usize>; 3]>,
+    // the edges where the faces on either side bend away from each other by
+    // more than CREASE_ANGLE_DEGREES
+    pub creases: Vec<[bool; 3]>,
+}
+
+// faces meeting at a sharper angle than this show up as a crease, so the
+// corners of a cube do but the facets of a smooth sphere don't
+pub const CREASE_ANGLE_DEGREES: f32 = 30.0;
+
+pub fn face_vertex_indices(face: &Face) -> [usize; 3] {
+    [face.a - 1, face.b - 1, face.c - 1]
+}
+
+fn face_normal(mesh: &Mesh, face: &Face) -> Vec3 {
+    let [a, b, c] = face_vertex_indices(face);
+    let edge_ab = mesh.vertices[b] - mesh.vertices[a];
+    let edge_ac = mesh.vertices[c] - mesh.vertices[a];
+    vec3_normalize_or_zero(&vec3_cross(&edge_ab, &edge_ac))
+}
+
+pub fn mesh_edges_new(mesh: &Mesh) -> MeshEdges {
+    // every edge by its two vertices, lowest first so both faces find it,
+    // along with the faces that have it
+    let mut edge_faces: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+    for (face_index, face) in mesh.faces.iter().enumerate() {
+        let vertices = face_vertex_indices(face);
+        for edge in 0..3 {
+            let (from, to) = (vertices[edge], vertices[(edge + 1) % 3]);
+            edge_faces.entry((from.min(to), from.max(to))).or_default().push((face_index, edge));
         }
     }
+
+    let mut neighbors = vec![[None; 3]; mesh.faces.len()];
+    for faces in edge_faces.values() {
+        if let [(face, edge), (other_face, other_edge)] = faces[..] {
+            neighbors[face][edge] = Some(other_face);
+            neighbors[other_face][other_edge] = Some(face);
+        }
+    }
+
+    let normals: Vec<Vec3> = mesh.faces.iter().map(|face| face_normal(mesh, face)).collect();
+    let crease_cos = CREASE_ANGLE_DEGREES.to_radians().cos();
+    let creases = neighbors
+        .iter()
+        .enumerate()
+        .map(|(face, face_neighbors)| face_neighbors.map(|neighbor| match neighbor {
+            Some(neighbor) => vec3_dot(&normals[face], &normals[neighbor]) < crease_cos,
+            None => false,
+        }))
+        .collect();
+    MeshEdges { neighbors, creases }
 }
 
 pub fn load_obj_file_data(filename: &str) -> Mesh {
+    load_obj_file(filename).unwrap()
+}
+
+// same as load_obj_file_data, but returns a message saying what went wrong
+// instead of panicking when the file can't be read or has bad faces
+pub fn load_obj_file(filename: &str) -> Result<Mesh, String> {
     let path = Path::new(filename);
-    let file = File::open(&path).unwrap();
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) => return Err(format!("Failed to open obj file '{}': {}", filename, e)),
+    };
     let reader = io::BufReader::new(file);
     let mut vertices = Vec::<Vec3>::new();
     let mut faces = Vec::<Face>::new();
 
     let mut rng = rand::thread_rng();    
 
-    for line_result in reader.lines() {
-        let line = line_result.unwrap();
+    for (line_index, line_result) in reader.lines().enumerate() {
+        let line = match line_result {
+            Ok(line) => line,
+            Err(e) => return Err(format!("Failed to read obj file '{}': {}", filename, e)),
+        };
         let words: Vec<&str> = line.split_whitespace().collect();
+        if (words.first() == Some(&"v") || words.first() == Some(&"f")) && words.len() < 4 {
+            return Err(format!("{}:{}: expected 3 values after '{}'", filename, line_index + 1, words[0]));
+        }
         if words.len() > 0 {
             if words[0] == "v" {
                 let vec3 = Vec3 {
@@ -120,23 +250,20 @@ pub fn load_obj_file_data(filename: &str) -> Mesh {
         }
     }
 
-    return Mesh {
-        vertices,
-        faces,
-        rotation: Vec3 {
-            x: 0.0,
-            y: 0.0,
-            z: 0.0,
-        },
-        scale: Vec3 {
-            x: 1.0,
-            y: 1.0,
-            z: 1.0,
-        },
-        translation: Vec3 {
-            x: 0.0,
-            y: 0.0,
-            z: 0.0,
+    // the faces index into the vertices starting at 1, so catch any that
+    // point past the end here instead of when the mesh is first drawn
+    for face in &faces {
+        for index in [face.a, face.b, face.c] {
+            if index == 0 || index > vertices.len() {
+                return Err(format!(
+                    "Obj file '{}' has a face using vertex {} but only has {} vertices",
+                    filename, index, vertices.len()));
+            }
         }
-    };
+    }
+
+    return Ok(Mesh {
+        vertices,
+        faces
+    });
 }
@@ -0,0 +1,292 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    camera::{camera_projection_matrix, camera_view_matrix},
+    light::{Light, light_apply_intensity, lights_intensity},
+    material::{Material, blend_mode_is_transparent, color_modulate},
+    matrix::{Mat3, Mat4, mat3_from_mat4, mat3_mul_vec3, mat4_mul_mat4, mat4_mul_vec4_project, mat4_normal_matrix, mat4_transform_points},
+    mesh::{Mesh, MeshEdges, face_vertex_indices},
+    scene::Scene,
+    triangle::Triangle,
+    vector::{Vec3, Vec4, vec3_cross, vec3_dot, vec3_from_vec4, vec3_normalize_or_zero},
+};
+
+// the post transform vertex cache. every vertex of the mesh gets transformed
+// and projected once per frame in the vertex stage and stored here at the
+// same index as in `mesh.vertices`, then primitive assembly builds the
+// triangles by looking up the 3 vertex indices of each face
+pub struct VertexCache {
+    // vertices after the world and view matrices, used for culling and depth
+    pub world: Vec<Vec4>,
+    // the same vertices projected and mapped to screen pixel coordinates
+    pub screen: Vec<Vec4>,
+    // whether each face of the mesh faces the camera, filled in by
+    // assemble_triangles so it can tell where the silhouette is
+    pub front_facing: Vec<bool>,
+}
+
+pub fn vertex_cache_new(vertex_count: usize) -> VertexCache {
+    VertexCache {
+        world: Vec::with_capacity(vertex_count),
+        screen: Vec::with_capacity(vertex_count),
+        front_facing: Vec::new(),
+    }
+}
+
+// moves a camera space vertex through the projection matrix and then into
+// screen pixel coordinates
+pub fn project_to_screen(proj_matrix: &Mat4, v: &Vec4, screen_width: usize, screen_height: usize) -> Vec4 {
+    let mut projected = mat4_mul_vec4_project(proj_matrix, v);
+
+    // scale into the view
+    projected.x *= screen_width as f32 / 2.0;
+    projected.y *= screen_height as f32 / 2.0;
+
+    // invert y values to account for flipped y screen coordinates
+    projected.y *= -1.0;
+
+    // translate to the middle of the screen
+    projected.x += screen_width as f32 / 2.0;
+    projected.y += screen_height as f32 / 2.0;
+    projected
+}
+
+// the vertex stage: transform and project every vertex of the mesh exactly
+// once, no matter how many faces share it
+pub fn process_vertices(
+    cache: &mut VertexCache,
+    vertices: &[Vec3],
+    world_matrix: &Mat4,
+    proj_matrix: &Mat4,
+    screen_width: usize,
+    screen_height: usize) {
+    mat4_transform_points(world_matrix, vertices, &mut cache.world);
+
+    cache.screen.clear();
+    for world_vertex in &cache.world {
+        cache.screen.push(project_to_screen(proj_matrix, world_vertex, screen_width, screen_height));
+    }
+}
+
+// whether the face is turned towards the camera, using the vertices after
+// the world and view matrices
+fn face_is_front_facing(cache: &VertexCache, indices: [usize; 3], camera_position: &Vec3) -> bool {
+    let vector_a = vec3_from_vec4(&cache.world[indices[0]]);
+    let vector_b = vec3_from_vec4(&cache.world[indices[1]]);
+    let vector_c = vec3_from_vec4(&cache.world[indices[2]]);
+
+    let vector_ab = vec3_normalize_or_zero(&(vector_b - vector_a));
+    let vector_ac = vec3_normalize_or_zero(&(vector_c - vector_a));
+
+    let normal = vec3_normalize_or_zero(&vec3_cross(&vector_ab, &vector_ac));
+
+    let camera_ray = *camera_position - vector_a;
+
+    vec3_dot(&normal, &camera_ray) >= 0.0
+}
+
+// what primitive assembly needs that's the same for every mesh in the frame
+pub struct AssemblySettings<'a> {
+    // in the same space as the vertex cache's world vertices
+    pub camera_position: Vec3,
+    // turned into that space too
+    pub lights: &'a [Light],
+    pub backface_culling_enabled: bool,
+}
+
+// primitive assembly: build the triangles for each face out of the vertex
+// cache, dropping the back faces and applying the material and flat lighting.
+// the triangles are added on to the end of `triangles_to_render`, with a
+// node_index of 0 for assemble_scene_triangles to fill in. every face is
+// checked for facing the camera before any are built, so each triangle can
+// tell which of its edges are on the silhouette
+pub fn assemble_triangles(
+    triangles_to_render: &mut Vec<Triangle>,
+    mesh: &Mesh,
+    mesh_edges: &MeshEdges,
+    material: &Material,
+    cache: &mut VertexCache,
+    normal_matrix: &Mat3,
+    settings: &AssemblySettings) {
+    cache.front_facing.clear();
+    for mesh_face in &mesh.faces {
+        let front_facing = face_is_front_facing(cache, face_vertex_indices(mesh_face), &settings.camera_position);
+        cache.front_facing.push(front_facing);
+    }
+
+    for (face_index, mesh_face) in mesh.faces.iter().enumerate() {
+        let indices = face_vertex_indices(mesh_face);
+        let front_facing = cache.front_facing[face_index];
+
+        // bypass the triangles facing away from the camera
+        if !front_facing && settings.backface_culling_enabled {
+            continue;
+        }
+
+        let vector_a = vec3_from_vec4(&cache.world[indices[0]]);
+        let vector_b = vec3_from_vec4(&cache.world[indices[1]]);
+        let vector_c = vec3_from_vec4(&cache.world[indices[2]]);
+
+        // an edge is on the outline at a crease, on an open border, or on the
+        // silhouette where the face on the other side is turned the other way
+        let outline_edges: [bool; 3] = std::array::from_fn(|edge| {
+            match mesh_edges.neighbors[face_index][edge] {
+                Some(neighbor) => {
+                    mesh_edges.creases[face_index][edge] || cache.front_facing[neighbor] != front_facing
+                },
+                None => true,
+            }
+        });
+
+        // an edge shared with a face that's also drawn only needs drawing by
+        // one of the two
+        let owned_edges: [bool; 3] = std::array::from_fn(|edge| {
+            match mesh_edges.neighbors[face_index][edge] {
+                Some(neighbor) => {
+                    let neighbor_drawn = cache.front_facing[neighbor] || !settings.backface_culling_enabled;
+                    !neighbor_drawn || face_index < neighbor
+                },
+                None => true,
+            }
+        });
+
+        // light the face using its model space normal moved into the same
+        // space as the lights by the normal matrix, so non uniform scaling
+        // still shades correctly
+        let model_edge_ab = mesh.vertices[indices[1]] - mesh.vertices[indices[0]];
+        let model_edge_ac = mesh.vertices[indices[2]] - mesh.vertices[indices[0]];
+        let world_normal = vec3_normalize_or_zero(
+            &mat3_mul_vec3(normal_matrix, &vec3_cross(&model_edge_ab, &model_edge_ac)));
+
+        let light_intensity_factor = lights_intensity(settings.lights, &world_normal);
+        let mut color = light_apply_intensity(&color_modulate(&mesh_face.color, &material.color), light_intensity_factor);
+        color.a = (color.a as f32 * material.opacity.clamp(0.0, 1.0)).round() as u8;
+
+        triangles_to_render.push(Triangle {
+            points: [
+                cache.screen[indices[0]],
+                cache.screen[indices[1]],
+                cache.screen[indices[2]],
+            ],
+            texcoords: [mesh_face.a_uv, mesh_face.b_uv, mesh_face.c_uv],
+            color,
+            normal: world_normal,
+            avg_depth: (vector_a.z + vector_b.z + vector_c.z) / 3.0,
+            texture: material.texture,
+            blend_mode: material.blend_mode,
+            outline_edges,
+            owned_edges,
+            face_index,
+            node_index: 0,
+        });
+    }
+}
+
+// how many faces went into the pipeline in a frame, what became of them and
+// how long the two stages took
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PipelineStats {
+    // one for every face of every mesh in the scene
+    pub triangles_submitted: usize,
+    // faces dropped by backface culling
+    pub triangles_culled: usize,
+    // drawn triangles with a corner off the screen. there's no clipping
+    // stage, the rasterizer just skips the pixels past the edge
+    pub triangles_offscreen: usize,
+    // triangles left over to be rasterized
+    pub triangles_drawn: usize,
+    // time spent in the vertex stage
+    pub transform_time: Duration,
+    // time spent in primitive assembly, culling and lighting the faces
+    pub cull_time: Duration,
+}
+
+pub fn pipeline_stats_new() -> PipelineStats {
+    PipelineStats {
+        triangles_submitted: 0,
+        triangles_culled: 0,
+        triangles_offscreen: 0,
+        triangles_drawn: 0,
+        transform_time: Duration::ZERO,
+        cull_time: Duration::ZERO,
+    }
+}
+
+// runs the vertex stage and primitive assembly for every node in the scene
+// that has a mesh, using the world matrices from scene_update_world_matrices.
+// everything is moved into the scene camera's space, where the camera sits at
+// the origin looking down +z. returns what happened to the faces
+pub fn assemble_scene_triangles(
+    triangles_to_render: &mut Vec<Triangle>,
+    scene: &Scene,
+    cache: &mut VertexCache,
+    screen_width: usize,
+    screen_height: usize,
+    backface_culling_enabled: bool) -> PipelineStats {
+    triangles_to_render.clear();
+    let mut stats = pipeline_stats_new();
+
+    let view_matrix = camera_view_matrix(&scene.camera);
+    let proj_matrix = camera_projection_matrix(&scene.camera, screen_width, screen_height);
+
+    // the lights are directions in world space, so they only need the view
+    // rotation to end up in camera space alongside the normals
+    let view_rotation = mat3_from_mat4(&view_matrix);
+    let view_lights: Vec<Light> = scene.lights
+        .iter()
+        .map(|light| Light { direction: mat3_mul_vec3(&view_rotation, &light.direction) })
+        .collect();
+    let settings = AssemblySettings {
+        camera_position: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        lights: &view_lights,
+        backface_culling_enabled,
+    };
+
+    for (node_index, node) in scene.nodes.iter().enumerate() {
+        let (mesh, mesh_edges) = match node.mesh {
+            Some(mesh_index) => (&scene.meshes[mesh_index], &scene.mesh_edges[mesh_index]),
+            None => continue,
+        };
+        stats.triangles_submitted += mesh.faces.len();
+
+        let model_view_matrix = mat4_mul_mat4(&view_matrix, &node.world_matrix);
+
+        // a node scaled down to nothing has no inverse, but also won't show
+        // any faces, so the plain rotation is fine to fall back on
+        let normal_matrix = mat4_normal_matrix(&model_view_matrix)
+            .unwrap_or_else(|| mat3_from_mat4(&model_view_matrix));
+
+        let transform_start = Instant::now();
+        process_vertices(cache, &mesh.vertices, &model_view_matrix, &proj_matrix, screen_width, screen_height);
+        stats.transform_time += transform_start.elapsed();
+
+        let cull_start = Instant::now();
+        let first_triangle = triangles_to_render.len();
+        assemble_triangles(triangles_to_render, mesh, mesh_edges, &node.material, cache, &normal_matrix, &settings);
+        for triangle in &mut triangles_to_render[first_triangle..] {
+            triangle.node_index = node_index;
+        }
+        stats.cull_time += cull_start.elapsed();
+    }
+
+    stats.triangles_drawn = triangles_to_render.len();
+    stats.triangles_culled = stats.triangles_submitted - stats.triangles_drawn;
+    stats.triangles_offscreen = triangles_to_render
+        .iter()
+        .filter(|triangle| triangle.points.iter().any(|point| {
+            !(point.x >= 0.0 && point.x < screen_width as f32 && point.y >= 0.0 && point.y < screen_height as f32)
+        }))
+        .count();
+    stats
+}
+
+// sort the triangles based upon avg_depth to implement a painters algorithm.
+// the see through triangles all go after the opaque ones, so whatever is
+// behind them has already been drawn by the time they're blended over it
+pub fn sort_triangles_by_depth(triangles_to_render: &mut [Triangle]) {
+    triangles_to_render.sort_unstable_by(|a, b| {
+        blend_mode_is_transparent(a.blend_mode)
+            .cmp(&blend_mode_is_transparent(b.blend_mode))
+            .then_with(|| b.avg_depth.partial_cmp(&a.avg_depth).unwrap_or(std::cmp::Ordering::Equal))
+    });
+}
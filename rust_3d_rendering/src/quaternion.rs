@@ -0,0 +1,198 @@
+use crate::{matrix::{Mat4, mat4_identity}, vector::{Vec3, vec3_length}};
+
+#[derive(Debug, Copy, Clone)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+// the order the euler rotations are applied in, so XYZ rotates around x first,
+// then y, then z
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EulerOrder {
+    XYZ,
+    XZY,
+    YXZ,
+    YZX,
+    ZXY,
+    ZYX
+}
+
+pub fn quat_identity() -> Quat {
+    Quat {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+        w: 1.0
+    }
+}
+
+pub fn quat_from_axis_angle(axis: &Vec3, angle: f32) -> Quat {
+    let length = vec3_length(axis);
+    if length == 0.0 {
+        return quat_identity();
+    }
+
+    let s = (angle / 2.0).sin() / length;
+    Quat {
+        x: axis.x * s,
+        y: axis.y * s,
+        z: axis.z * s,
+        w: (angle / 2.0).cos()
+    }
+}
+
+pub fn quat_from_euler(x: f32, y: f32, z: f32, order: EulerOrder) -> Quat {
+    let qx = quat_from_axis_angle(&Vec3 { x: 1.0, y: 0.0, z: 0.0 }, x);
+    let qy = quat_from_axis_angle(&Vec3 { x: 0.0, y: 1.0, z: 0.0 }, y);
+    let qz = quat_from_axis_angle(&Vec3 { x: 0.0, y: 0.0, z: 1.0 }, z);
+
+    // the rotation applied first has to be on the right hand side of the
+    // multiplication, same as with the rotation matrices
+    let (first, second, third) = match order {
+        EulerOrder::XYZ => (qx, qy, qz),
+        EulerOrder::XZY => (qx, qz, qy),
+        EulerOrder::YXZ => (qy, qx, qz),
+        EulerOrder::YZX => (qy, qz, qx),
+        EulerOrder::ZXY => (qz, qx, qy),
+        EulerOrder::ZYX => (qz, qy, qx),
+    };
+    quat_mul(&third, &quat_mul(&second, &first))
+}
+
+pub fn quat_mul(a: &Quat, b: &Quat) -> Quat {
+    Quat {
+        x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+        y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+        z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+    }
+}
+
+pub fn quat_dot(a: &Quat, b: &Quat) -> f32 {
+    a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w
+}
+
+pub fn quat_length(q: &Quat) -> f32 {
+    quat_dot(q, q).sqrt()
+}
+
+pub fn quat_normalize(q: &Quat) -> Quat {
+    let length = quat_length(q);
+    if length == 0.0 {
+        return quat_identity();
+    }
+
+    Quat {
+        x: q.x / length,
+        y: q.y / length,
+        z: q.z / length,
+        w: q.w / length
+    }
+}
+
+pub fn quat_conjugate(q: &Quat) -> Quat {
+    Quat {
+        x: -q.x,
+        y: -q.y,
+        z: -q.z,
+        w: q.w
+    }
+}
+
+pub fn quat_rotate_vec3(q: &Quat, v: &Vec3) -> Vec3 {
+    let p = Quat { x: v.x, y: v.y, z: v.z, w: 0.0 };
+    let rotated = quat_mul(&quat_mul(q, &p), &quat_conjugate(q));
+    Vec3 {
+        x: rotated.x,
+        y: rotated.y,
+        z: rotated.z
+    }
+}
+
+pub fn quat_slerp(a: &Quat, b: &Quat, t: f32) -> Quat {
+    let mut b = *b;
+    let mut cos_theta = quat_dot(a, &b);
+
+    // q and -q are the same rotation, so flip b if needed to take the
+    // shortest path around the sphere
+    if cos_theta < 0.0 {
+        b = Quat { x: -b.x, y: -b.y, z: -b.z, w: -b.w };
+        cos_theta = -cos_theta;
+    }
+
+    // when the quaternions are almost the same the sin below gets close to
+    // zero, so fall back to a normalized linear interpolation
+    if cos_theta > 0.9995 {
+        return quat_normalize(&Quat {
+            x: a.x + (b.x - a.x) * t,
+            y: a.y + (b.y - a.y) * t,
+            z: a.z + (b.z - a.z) * t,
+            w: a.w + (b.w - a.w) * t,
+        });
+    }
+
+    let theta = cos_theta.acos();
+    let sin_theta = theta.sin();
+    let factor_a = ((1.0 - t) * theta).sin() / sin_theta;
+    let factor_b = (t * theta).sin() / sin_theta;
+
+    Quat {
+        x: a.x * factor_a + b.x * factor_b,
+        y: a.y * factor_a + b.y * factor_b,
+        z: a.z * factor_a + b.z * factor_b,
+        w: a.w * factor_a + b.w * factor_b,
+    }
+}
+
+// the x, y and z angles that quat_from_euler with EulerOrder::XYZ would turn
+// back into this rotation. when y is at +-90 degrees x and z spin around the
+// same axis, so all of that rotation is put into x and z is left at zero
+pub fn quat_to_euler_xyz(q: &Quat) -> Vec3 {
+    let m = quat_to_mat4(q);
+    let sin_y = (-m.m[2][0]).clamp(-1.0, 1.0);
+    let y = sin_y.asin();
+
+    if sin_y.abs() > 0.9999 {
+        Vec3 {
+            x: (m.m[0][1] * sin_y).atan2(m.m[1][1]),
+            y,
+            z: 0.0
+        }
+    } else {
+        Vec3 {
+            x: m.m[2][1].atan2(m.m[2][2]),
+            y,
+            z: m.m[1][0].atan2(m.m[0][0])
+        }
+    }
+}
+
+pub fn quat_to_mat4(q: &Quat) -> Mat4 {
+    let q = quat_normalize(q);
+    let xx = q.x * q.x;
+    let yy = q.y * q.y;
+    let zz = q.z * q.z;
+    let xy = q.x * q.y;
+    let xz = q.x * q.z;
+    let yz = q.y * q.z;
+    let wx = q.w * q.x;
+    let wy = q.w * q.y;
+    let wz = q.w * q.z;
+
+    let mut m = mat4_identity();
+    m.m[0][0] = 1.0 - 2.0 * (yy + zz);
+    m.m[0][1] = 2.0 * (xy - wz);
+    m.m[0][2] = 2.0 * (xz + wy);
+
+    m.m[1][0] = 2.0 * (xy + wz);
+    m.m[1][1] = 1.0 - 2.0 * (xx + zz);
+    m.m[1][2] = 2.0 * (yz - wx);
+
+    m.m[2][0] = 2.0 * (xz - wy);
+    m.m[2][1] = 2.0 * (yz + wx);
+    m.m[2][2] = 1.0 - 2.0 * (xx + yy);
+    m
+}
@@ -0,0 +1,428 @@
+use std::thread;
+
+use sdl2::pixels::Color;
+
+use crate::{
+    antialias::AntiAliasing,
+    display::{Display, FrameTile, RenderMode},
+    line::{HiddenLines, LineEdges, LinePoint, LineStyle, draw_polyline, triangle_line_points},
+    material::blend_mode_is_transparent,
+    texture::MeshTexture,
+    triangle::{
+        Triangle, draw_depth_triangle, draw_filled_triangle, draw_overdraw_triangle, draw_shaded_triangle,
+        draw_textured_triangle,
+    },
+    vector::Vec3,
+};
+
+// how many rows of pixels each tile covers when rasterizing on several threads
+pub const TILE_HEIGHT: usize = 32;
+
+// the vertex dots of WireframeWithDot reach this far past the triangle itself
+pub const DOT_RADIUS: f32 = 3.0;
+
+pub const WIREFRAME_COLOR: Color = Color::RGBA(0, 0, 255, 255);
+pub const DOT_COLOR: Color = Color::RGBA(255, 0, 0, 0);
+
+// what the rasterizer did over a frame
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RasterStats {
+    // every pixel written, counting the ones drawn over
+    pub pixels_written: usize,
+    // see through pixels kept in the a-buffer and blended at the end
+    pub fragments_stored: usize,
+    // see through pixels blended straight away because the a-buffer was full
+    pub fragments_overflowed: usize,
+}
+
+pub fn raster_stats_new() -> RasterStats {
+    RasterStats {
+        pixels_written: 0,
+        fragments_stored: 0,
+        fragments_overflowed: 0,
+    }
+}
+
+pub fn render_thread_count_default() -> usize {
+    thread::available_parallelism().map(|count| count.get()).unwrap_or(1)
+}
+
+// draws all the triangles for the frame in the order given. with a thread
+// count of 1 everything is drawn straight into the whole screen. otherwise the
+// screen is cut into tiles, each triangle is put into the bin of every tile it
+// overlaps, and the tiles are handed out to the threads. every tile still sees
+// its triangles in the original order and owns its own rows of the colour and
+// depth buffers, so the result is exactly the same as drawing on one thread.
+// with the a-buffer turned on each tile blends its see through pixels once
+// all of its triangles are drawn. with anti-aliasing on the triangles are
+// drawn into the display's samples, which are averaged into the colour
+// buffer at the end. the wireframe modes draw their edges in the display's
+// line style, and HiddenLine always tests them against the faces
+pub fn render_triangles(
+    display: &mut Display,
+    triangles: &[Triangle],
+    render_mode: RenderMode,
+    textures: &[MeshTexture],
+    thread_count: usize) -> RasterStats {
+    let depth_range = match render_mode {
+        RenderMode::Depth => frame_depth_range(triangles),
+        _ => (0.0, 0.0),
+    };
+
+    let scaled_triangles;
+    let mut line_style = display.line_style();
+    if render_mode == RenderMode::HiddenLine && line_style.hidden == HiddenLines::Show {
+        line_style.hidden = HiddenLines::Hide;
+    }
+    let triangles = match display.anti_aliasing() {
+        AntiAliasing::Supersample(factor) => {
+            scaled_triangles = supersample_triangles(triangles, factor);
+            line_style.width *= factor as f32;
+            line_style.dash_length *= factor as f32;
+            &scaled_triangles
+        },
+        _ => triangles,
+    };
+
+    let stats = if thread_count <= 1 {
+        let mut tile = display.full_frame();
+        draw_tile(&mut tile, triangles, 0..triangles.len(), render_mode, textures, depth_range, &line_style);
+        finish_tile(&mut tile, raster_stats_new())
+    } else {
+        render_tiles(display, triangles, render_mode, textures, thread_count, depth_range, &line_style)
+    };
+    display.resolve_samples();
+    stats
+}
+
+// the triangles moved onto the bigger screen that supersampling draws into.
+// each pixel's point stays in the middle of the block of samples that get
+// averaged into it
+fn supersample_triangles(triangles: &[Triangle], factor: usize) -> Vec<Triangle> {
+    let factor = factor as f32;
+    triangles
+        .iter()
+        .map(|triangle| {
+            let mut triangle = triangle.clone();
+            for point in &mut triangle.points {
+                point.x = (point.x + 0.5) * factor - 0.5;
+                point.y = (point.y + 0.5) * factor - 0.5;
+            }
+            triangle
+        })
+        .collect()
+}
+
+fn render_tiles(
+    display: &mut Display,
+    triangles: &[Triangle],
+    render_mode: RenderMode,
+    textures: &[MeshTexture],
+    thread_count: usize,
+    depth_range: (f32, f32),
+    line_style: &LineStyle) -> RasterStats {
+    let (_, render_height) = display.render_size();
+    let tiles = display.frame_tiles(TILE_HEIGHT);
+    // wide lines reach past the triangle as well
+    let margin = DOT_RADIUS.max(line_style.width / 2.0 + 1.0);
+    let bins = bin_triangles(triangles, tiles.len(), render_height, margin);
+
+    // deal the tiles out round robin, so neighbouring tiles which usually
+    // have a similar amount of work end up on different threads
+    let mut thread_tiles: Vec<Vec<(FrameTile, &Vec<usize>)>> = Vec::new();
+    for _ in 0..thread_count.min(tiles.len()) {
+        thread_tiles.push(Vec::new());
+    }
+    let group_count = thread_tiles.len();
+    for (i, (tile, bin)) in tiles.into_iter().zip(bins.iter()).enumerate() {
+        thread_tiles[i % group_count].push((tile, bin));
+    }
+
+    thread::scope(|scope| {
+        let threads: Vec<_> = thread_tiles
+            .into_iter()
+            .map(|group| scope.spawn(move || {
+                let mut stats = raster_stats_new();
+                for (mut tile, bin) in group {
+                    draw_tile(
+                        &mut tile, triangles, bin.iter().copied(), render_mode, textures, depth_range, line_style);
+                    stats = finish_tile(&mut tile, stats);
+                }
+                stats
+            }))
+            .collect();
+        threads.into_iter().fold(raster_stats_new(), |total, thread| {
+            let stats = thread.join().unwrap();
+            RasterStats {
+                pixels_written: total.pixels_written + stats.pixels_written,
+                fragments_stored: total.fragments_stored + stats.fragments_stored,
+                fragments_overflowed: total.fragments_overflowed + stats.fragments_overflowed,
+            }
+        })
+    })
+}
+
+// draws the triangles picked out by `triangle_indexes` into the tile, in
+// that order. when hidden lines are treated differently the edges can only
+// be tested against the depth buffer once every face is in it, so they're
+// all drawn after the faces
+fn draw_tile<I: Iterator<Item = usize> + Clone>(
+    tile: &mut FrameTile,
+    triangles: &[Triangle],
+    triangle_indexes: I,
+    render_mode: RenderMode,
+    textures: &[MeshTexture],
+    depth_range: (f32, f32),
+    line_style: &LineStyle) {
+    tile.clear_fragments();
+    let edges_last = line_style.hidden != HiddenLines::Show;
+    for triangle_index in triangle_indexes.clone() {
+        draw_triangle_with_mode(
+            tile, &triangles[triangle_index], render_mode, textures, depth_range, line_style, !edges_last);
+    }
+    if edges_last {
+        for triangle_index in triangle_indexes {
+            draw_triangle_edges(tile, &triangles[triangle_index], render_mode, line_style);
+        }
+    }
+}
+
+// blends the tile's a-buffer and adds what it did onto `stats`
+fn finish_tile(tile: &mut FrameTile, stats: RasterStats) -> RasterStats {
+    let fragments_stored = tile.fragments_stored();
+    tile.resolve_fragments();
+    RasterStats {
+        pixels_written: stats.pixels_written + tile.pixels_written,
+        fragments_stored: stats.fragments_stored + fragments_stored,
+        fragments_overflowed: stats.fragments_overflowed + tile.fragments_overflowed,
+    }
+}
+
+// for each tile, the indexes of the triangles that overlap its rows, kept in
+// the same order as the triangles slice. anything drawn around a triangle
+// can reach `margin` pixels past it
+fn bin_triangles(triangles: &[Triangle], tile_count: usize, screen_height: usize, margin: f32) -> Vec<Vec<usize>> {
+    let mut bins = vec![Vec::new(); tile_count];
+
+    for (triangle_index, triangle) in triangles.iter().enumerate() {
+        let ys = [triangle.points[0].y, triangle.points[1].y, triangle.points[2].y];
+
+        // a point that went off to infinity or NaN could end up anywhere
+        // once it's cast to pixels, so give it to every tile to be safe
+        let (first_tile, last_tile) = if ys.iter().all(|y| y.is_finite()) {
+            let min_y = ys[0].min(ys[1]).min(ys[2]) - margin;
+            let max_y = ys[0].max(ys[1]).max(ys[2]) + margin;
+            let min_row = min_y.floor().clamp(0.0, (screen_height - 1) as f32) as usize;
+            let max_row = max_y.ceil().clamp(0.0, (screen_height - 1) as f32) as usize;
+            (min_row / TILE_HEIGHT, (max_row / TILE_HEIGHT).min(tile_count - 1))
+        } else {
+            (0, tile_count - 1)
+        };
+
+        for bin in &mut bins[first_tile..=last_tile] {
+            bin.push(triangle_index);
+        }
+    }
+    bins
+}
+
+// the nearest and farthest view space depth of all the triangles' vertices,
+// which the depth view stretches its shades of grey across
+fn frame_depth_range(triangles: &[Triangle]) -> (f32, f32) {
+    let mut near = f32::INFINITY;
+    let mut far = 0.0f32;
+    for triangle in triangles {
+        for point in &triangle.points {
+            if point.w.is_finite() && point.w > 0.0 {
+                near = near.min(point.w);
+                far = far.max(point.w);
+            }
+        }
+    }
+    if near > far {
+        return (0.0, 0.0);
+    }
+    (near, far)
+}
+
+fn depth_color(depth: f32, (near, far): (f32, f32)) -> Color {
+    let closeness = if far > near { 1.0 - (depth - near) / (far - near) } else { 1.0 };
+    let value = (closeness.clamp(0.0, 1.0) * 255.0) as u8;
+    Color::RGBA(value, value, value, 255)
+}
+
+// maps each component from -1..1 to 0..255
+fn normal_color(normal: &Vec3) -> Color {
+    let channel = |value: f32| ((value * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0) as u8;
+    Color::RGBA(channel(normal.x), channel(normal.y), channel(normal.z), 255)
+}
+
+// the number of checkerboard squares across the texture in the uv view
+const UV_CHECKER_SQUARES: f32 = 8.0;
+
+fn uv_color(u: f32, v: f32) -> Color {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0) as u8;
+    let square = (u * UV_CHECKER_SQUARES).floor() as i32 + (v * UV_CHECKER_SQUARES).floor() as i32;
+    let shade = if square.rem_euclid(2) == 0 { 1.0 } else { 0.6 };
+    Color::RGBA(
+        (channel(u) as f32 * shade) as u8,
+        (channel(v) as f32 * shade) as u8,
+        (64.0 * shade) as u8,
+        255)
+}
+
+// scrambles the face index so neighbouring faces get very different colours,
+// and keeps every channel bright enough to see against the background. the
+// node index flips a different set of bits for each node, so the same face
+// of two meshes doesn't come out the same colour
+fn face_index_color(node_index: usize, face_index: usize) -> Color {
+    let hash = (face_index as u32).wrapping_add(1).wrapping_mul(2654435761)
+        ^ (node_index as u32).wrapping_mul(2246822519);
+    Color::RGBA(
+        (hash >> 24) as u8 | 0x40,
+        (hash >> 16) as u8 | 0x40,
+        (hash >> 8) as u8 | 0x40,
+        255)
+}
+
+fn draw_triangle_with_mode(
+    tile: &mut FrameTile,
+    triangle: &Triangle,
+    render_mode: RenderMode,
+    textures: &[MeshTexture],
+    depth_range: (f32, f32),
+    line_style: &LineStyle,
+    draw_edges: bool) {
+    if draw_debug_triangle(tile, triangle, render_mode, depth_range) {
+        return;
+    }
+
+    // textured modes fall back to the flat colour for triangles whose
+    // material doesn't have a texture
+    let texture = triangle.texture.and_then(|index| textures.get(index));
+    let draw_filled = match render_mode {
+        RenderMode::FilledTriangles |
+        RenderMode::FilledTrianglesAndWireframe => true,
+        RenderMode::Textured |
+        RenderMode::TexturedAndWireframe => texture.is_none(),
+        _ => false
+    };
+
+    if draw_filled {
+        draw_filled_triangle(tile, &triangle.points, triangle.color, triangle.blend_mode);
+    }
+
+    match (render_mode, texture) {
+        (RenderMode::Textured, Some(texture)) |
+        (RenderMode::TexturedAndWireframe, Some(texture)) => {
+            draw_textured_triangle(
+                tile, &triangle.points, &triangle.texcoords, texture, triangle.color.a, triangle.blend_mode);
+        },
+        _ => {}
+    };
+
+    // the edges left for later still need something to be hidden behind
+    let wireframe_only =
+        matches!(render_mode, RenderMode::Wireframe | RenderMode::WireframeWithDot | RenderMode::HiddenLine);
+    if !draw_edges && wireframe_only && !blend_mode_is_transparent(triangle.blend_mode) {
+        draw_depth_triangle(tile, &triangle.points);
+    }
+
+    if draw_edges {
+        draw_triangle_edges(tile, triangle, render_mode, line_style);
+    }
+}
+
+// draws the edges of the triangle for the wireframe modes, and the dots on
+// its corners for WireframeWithDot
+fn draw_triangle_edges(tile: &mut FrameTile, triangle: &Triangle, render_mode: RenderMode, line_style: &LineStyle) {
+    match render_mode {
+        RenderMode::Wireframe |
+        RenderMode::WireframeWithDot |
+        RenderMode::FilledTrianglesAndWireframe |
+        RenderMode::TexturedAndWireframe |
+        RenderMode::HiddenLine => draw_outline_edges(tile, triangle, render_mode, line_style),
+        _ => {}
+    };
+
+    if render_mode == RenderMode::WireframeWithDot {
+        let size = (DOT_RADIUS * 2.0) as usize;
+        for point in &triangle.points {
+            let (x, y) = ((point.x - DOT_RADIUS).floor() as i32, (point.y - DOT_RADIUS).floor() as i32);
+            tile.draw_rect(x, y, size, size, DOT_COLOR);
+        }
+    }
+}
+
+// draws the triangle's edges, or just the ones on the outline of its mesh.
+// they're kept joined up as one line where they follow on from each other,
+// so wide lines still get round corners. an edge shared by two triangles is
+// only drawn by one of them, so smooth and see through lines aren't blended
+// twice over, unless the faces are drawn with their edges. then the face
+// drawn second would cover half of the line, so both draw it
+fn draw_outline_edges(tile: &mut FrameTile, triangle: &Triangle, render_mode: RenderMode, line_style: &LineStyle) {
+    let points = triangle_line_points(triangle);
+    let edges_with_faces = line_style.hidden == HiddenLines::Show &&
+        matches!(render_mode, RenderMode::FilledTrianglesAndWireframe | RenderMode::TexturedAndWireframe);
+    let shared = if edges_with_faces { [true; 3] } else { triangle.owned_edges };
+    let candidates = match line_style.edges {
+        LineEdges::All => [true; 3],
+        LineEdges::Outline => triangle.outline_edges,
+    };
+    let drawn: [bool; 3] = std::array::from_fn(|edge| candidates[edge] && shared[edge]);
+    match drawn.iter().filter(|&&edge| edge).count() {
+        3 => draw_polyline(tile, &points, true, WIREFRAME_COLOR, line_style),
+        0 => {},
+        _ => {
+            // start just after an edge that isn't drawn and finish on it, so
+            // the ones that are come in order and the last line gets drawn
+            let gap = drawn.iter().position(|&edge| !edge).unwrap_or(0);
+            let mut polyline: Vec<LinePoint> = Vec::with_capacity(3);
+            for i in 1..=3 {
+                let edge = (gap + i) % 3;
+                if drawn[edge] {
+                    if polyline.is_empty() {
+                        polyline.push(points[edge]);
+                    }
+                    polyline.push(points[(edge + 1) % 3]);
+                } else if !polyline.is_empty() {
+                    draw_polyline(tile, &polyline, false, WIREFRAME_COLOR, line_style);
+                    polyline.clear();
+                }
+            }
+        },
+    }
+}
+
+// draws the triangle for the debug render modes, returning false for the
+// normal modes which are left to draw_triangle_with_mode
+fn draw_debug_triangle(
+    tile: &mut FrameTile,
+    triangle: &Triangle,
+    render_mode: RenderMode,
+    depth_range: (f32, f32)) -> bool {
+    let points = &triangle.points;
+
+    match render_mode {
+        RenderMode::Depth => {
+            draw_shaded_triangle(tile, points, |_, depth| depth_color(depth, depth_range));
+        },
+        RenderMode::Normals => {
+            let color = normal_color(&triangle.normal);
+            draw_shaded_triangle(tile, points, |_, _| color);
+        },
+        RenderMode::Uvs => {
+            let [uv0, uv1, uv2] = triangle.texcoords;
+            draw_shaded_triangle(tile, points, |weights, _| uv_color(
+                uv0.u * weights.x + uv1.u * weights.y + uv2.u * weights.z,
+                uv0.v * weights.x + uv1.v * weights.y + uv2.v * weights.z));
+        },
+        RenderMode::TriangleIndex => {
+            let color = face_index_color(triangle.node_index, triangle.face_index);
+            draw_shaded_triangle(tile, points, |_, _| color);
+        },
+        RenderMode::Overdraw => draw_overdraw_triangle(tile, points),
+        _ => return false,
+    }
+    true
+}
@@ -0,0 +1,141 @@
+use crate::{
+    camera::{Camera, CameraKey, camera_default},
+    light::Light,
+    material::{Material, material_default},
+    matrix::{Mat4, mat4_identity, mat4_mul_mat4},
+    mesh::{Mesh, MeshEdges, mesh_edges_new},
+    texture::MeshTexture,
+    transform::{Transform, transform_identity, transform_to_mat4},
+};
+
+// a node in the scene hierarchy. its transform is relative to its parent, so
+// moving a car node also moves the wheel nodes that are its children. meshes,
+// textures and other nodes are referred to by their index in the scene, which
+// lets several nodes share one mesh
+pub struct SceneNode {
+    pub name: String,
+    pub transform: Transform,
+    pub mesh: Option<usize>,
+    pub material: Material,
+    pub children: Vec<usize>,
+    // whether the viewer keeps turning this node around, which is spin = true
+    // in a scene file
+    pub spin: bool,
+    // the transform of this node combined with all of its parents, filled in
+    // by scene_update_world_matrices
+    pub world_matrix: Mat4,
+}
+
+// the name a mesh or texture is referred to by and the file it was loaded
+// from, so a scene can be saved back out. the path is one that opens from the
+// working directory
+#[derive(Debug, Clone)]
+pub struct AssetSource {
+    pub name: String,
+    pub path: String,
+}
+
+pub struct Scene {
+    pub nodes: Vec<SceneNode>,
+    // the nodes without a parent
+    pub roots: Vec<usize>,
+    pub meshes: Vec<Mesh>,
+    // same index as `meshes`
+    pub mesh_sources: Vec<AssetSource>,
+    // same index as `meshes`
+    pub mesh_edges: Vec<MeshEdges>,
+    pub textures: Vec<MeshTexture>,
+    // same index as `textures`
+    pub texture_sources: Vec<AssetSource>,
+    pub camera: Camera,
+    // where the camera moves over time, sorted by time. empty when it stays put
+    pub camera_path: Vec<CameraKey>,
+    pub lights: Vec<Light>,
+}
+
+pub fn scene_new() -> Scene {
+    Scene {
+        nodes: Vec::new(),
+        roots: Vec::new(),
+        meshes: Vec::new(),
+        mesh_sources: Vec::new(),
+        mesh_edges: Vec::new(),
+        textures: Vec::new(),
+        texture_sources: Vec::new(),
+        camera: camera_default(),
+        camera_path: Vec::new(),
+        lights: Vec::new(),
+    }
+}
+
+pub fn scene_node_new(name: &str) -> SceneNode {
+    SceneNode {
+        name: name.to_string(),
+        transform: transform_identity(),
+        mesh: None,
+        material: material_default(),
+        children: Vec::new(),
+        spin: false,
+        world_matrix: mat4_identity(),
+    }
+}
+
+pub fn scene_add_mesh(scene: &mut Scene, name: &str, path: &str, mesh: Mesh) -> usize {
+    scene.mesh_edges.push(mesh_edges_new(&mesh));
+    scene.meshes.push(mesh);
+    scene.mesh_sources.push(AssetSource { name: name.to_string(), path: path.to_string() });
+    scene.meshes.len() - 1
+}
+
+pub fn scene_add_texture(scene: &mut Scene, name: &str, path: &str, texture: MeshTexture) -> usize {
+    scene.textures.push(texture);
+    scene.texture_sources.push(AssetSource { name: name.to_string(), path: path.to_string() });
+    scene.textures.len() - 1
+}
+
+// adds the node under the given parent, or as a root node if there is none,
+// and returns the index of the new node
+pub fn scene_add_node(scene: &mut Scene, parent: Option<usize>, node: SceneNode) -> usize {
+    scene.nodes.push(node);
+    let index = scene.nodes.len() - 1;
+    match parent {
+        Some(parent) => scene.nodes[parent].children.push(index),
+        None => scene.roots.push(index),
+    }
+    index
+}
+
+pub fn scene_find_node(scene: &Scene, name: &str) -> Option<usize> {
+    scene.nodes.iter().position(|node| node.name == name)
+}
+
+pub fn scene_node_parent(scene: &Scene, index: usize) -> Option<usize> {
+    scene.nodes.iter().position(|node| node.children.contains(&index))
+}
+
+// walks the hierarchy from the roots down, so every node's world matrix is
+// its parent's world matrix times its own local transform
+pub fn scene_update_world_matrices(scene: &mut Scene) {
+    let mut stack: Vec<(usize, Mat4)> = scene.roots
+        .iter()
+        .map(|&root| (root, mat4_identity()))
+        .collect();
+
+    while let Some((index, parent_matrix)) = stack.pop() {
+        let local_matrix = transform_to_mat4(&scene.nodes[index].transform);
+        let world_matrix = mat4_mul_mat4(&parent_matrix, &local_matrix);
+        scene.nodes[index].world_matrix = world_matrix;
+
+        for &child in &scene.nodes[index].children {
+            stack.push((child, world_matrix));
+        }
+    }
+}
+
+pub fn scene_face_count(scene: &Scene) -> usize {
+    scene.nodes
+        .iter()
+        .filter_map(|node| node.mesh)
+        .map(|mesh| scene.meshes[mesh].faces.len())
+        .sum()
+}
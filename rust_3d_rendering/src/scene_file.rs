@@ -0,0 +1,814 @@
+// loading and saving whole scenes as a small TOML style text file, e.g.
+//
+//     [render]
+//     mode = "textured"
+//     backface_culling = true
+//     transparency = "sorted" # or "a_buffer"
+//     anti_aliasing = "off"   # or "ssaa2" to "ssaa4", "msaa2", "msaa4", "msaa8"
+//     line_width = 1          # pixels, for the wireframe modes
+//     smooth_lines = false
+//     hidden_lines = "show"   # or "dim", "hide", "dashed"
+//     line_edges = "all"      # or "outline" for just the silhouette and creases
+//
+//     [camera]
+//     position = [0, 0, 0]
+//     rotation = [0, 0, 0]    # degrees, applied around x, then y, then z
+//     fov = 60                # degrees
+//
+//     [[camera_key]]          # a scripted path the camera moves along
+//     time = 0                # seconds, each key later than the one before
+//     position = [0, 0, -5]
+//     rotation = [0, 0, 0]
+//
+//     [[light]]
+//     direction = [0, 0, 1]
+//
+//     [[texture]]
+//     name = "crate"
+//     path = "cube_texture.png"
+//
+//     [[mesh]]
+//     name = "cube"
+//     path = "builtin:cube"   # or an .obj file
+//
+//     [[node]]
+//     name = "box"
+//     mesh = "cube"
+//     texture = "crate"
+//     color = [255, 255, 255, 255]
+//     opacity = 1             # 0 to 1, multiplied into the colour's alpha
+//     blend = "opaque"        # or "cutout", "alpha", "additive", "multiply"
+//     alpha_cutoff = 0.5      # only for "cutout"
+//     translation = [0, 0, 5]
+//     rotation = [0, 45, 0]
+//     scale = [1, 1, 1]
+//     parent = "some other node"
+//     spin = true             # the viewer keeps turning it around
+//
+// relative paths are looked up next to the scene file, and are written out
+// relative to wherever the scene is saved. every section and key
+// is optional except for the names and paths, and anything left out gets the
+// same default the code would use
+
+use std::{fs, path::{Component, Path, PathBuf}};
+
+use sdl2::pixels::Color;
+
+use crate::{
+    antialias::{ANTI_ALIASING_NAMES, anti_aliasing_from_name, anti_aliasing_name},
+    camera::CameraKey,
+    line::{
+        HIDDEN_LINES_NAMES, LINE_EDGES_NAMES, hidden_lines_from_name, hidden_lines_name, line_edges_from_name,
+        line_edges_name,
+    },
+    display::{
+        RenderSettings, render_mode_from_name, render_mode_name, render_settings_default, transparency_mode_from_name,
+        transparency_mode_name, RENDER_MODE_NAMES, TRANSPARENCY_MODE_NAMES,
+    },
+    light::Light,
+    material::{BLEND_MODE_NAMES, BlendMode, blend_mode_from_name, blend_mode_name},
+    mesh::{get_cube_mesh, load_obj_file},
+    quaternion::{EulerOrder, quat_from_euler, quat_identity, quat_to_euler_xyz},
+    scene::{Scene, scene_add_mesh, scene_add_node, scene_add_texture, scene_new, scene_node_new, scene_node_parent},
+    texture::load_png_texture,
+    vector::{Vec3, vec3_normalize_or_zero},
+};
+
+// mesh paths starting with this are made in code instead of loaded from disk
+pub const BUILTIN_MESH_PREFIX: &str = "builtin:";
+
+#[derive(Debug, Clone)]
+enum Value {
+    String(String),
+    Bool(bool),
+    Number(f32),
+    List(Vec<f32>),
+}
+
+struct Entry {
+    key: String,
+    value: Value,
+    line: usize,
+}
+
+struct Section {
+    name: String,
+    // true for [[name]] sections, which can appear many times
+    is_list: bool,
+    line: usize,
+    entries: Vec<Entry>,
+}
+
+// the node sections are added to the scene after everything else so a node
+// can name a parent that comes later in the file
+struct NodeEntry {
+    parent: Option<(String, usize)>,
+    line: usize,
+    node: crate::scene::SceneNode,
+}
+
+pub fn load_scene_file(path: &str) -> Result<(Scene, RenderSettings), String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => return Err(format!("Failed to read scene file '{}': {}", path, e)),
+    };
+    let base_dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    parse_scene(&text, path, base_dir)
+}
+
+pub fn save_scene_file(path: &str, scene: &Scene, settings: &RenderSettings) -> Result<(), String> {
+    let base_dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    match fs::write(path, scene_file_to_string(scene, settings, base_dir)) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("Failed to write scene file '{}': {}", path, e)),
+    }
+}
+
+// where the viewer saves a scene that was loaded from `path`. it goes next to
+// it rather than over it, saving it would lose the file's comments and layout
+pub fn saved_scene_path(path: &str) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or("scene".into());
+    path.with_file_name(format!("{}_saved.toml", stem)).to_string_lossy().into_owned()
+}
+
+// `file_name` is only used in the error messages, and relative asset paths
+// are loaded from `base_dir`. the scene's asset sources keep the paths they
+// were loaded from, joined on to `base_dir`
+pub fn parse_scene(text: &str, file_name: &str, base_dir: &Path) -> Result<(Scene, RenderSettings), String> {
+    let sections = parse_sections(text, file_name)?;
+
+    let mut scene = scene_new();
+    let mut settings = render_settings_default();
+    let mut nodes: Vec<NodeEntry> = Vec::new();
+    let mut seen_render = false;
+    let mut seen_camera = false;
+
+    for section in &sections {
+        let err = |line: usize, message: String| format!("{}:{}: {}", file_name, line, message);
+
+        match (section.name.as_str(), section.is_list) {
+            ("render", false) => {
+                if seen_render {
+                    return Err(err(section.line, "[render] appears more than once".to_string()));
+                }
+                seen_render = true;
+                check_keys(
+                    section,
+                    &["mode", "backface_culling", "transparency", "anti_aliasing", "line_width", "smooth_lines",
+                        "hidden_lines", "line_edges"],
+                    file_name)?;
+                if let Some(entry) = find_entry(section, "mode") {
+                    let name = expect_string(entry, file_name)?;
+                    settings.render_mode = match render_mode_from_name(&name) {
+                        Some(mode) => mode,
+                        None => {
+                            let names: Vec<&str> = RENDER_MODE_NAMES.iter().map(|(name, _)| *name).collect();
+                            return Err(err(entry.line, format!(
+                                "unknown render mode '{}', expected one of: {}", name, names.join(", "))));
+                        }
+                    };
+                }
+                if let Some(entry) = find_entry(section, "backface_culling") {
+                    settings.backface_culling_enabled = expect_bool(entry, file_name)?;
+                }
+                if let Some(entry) = find_entry(section, "transparency") {
+                    let name = expect_string(entry, file_name)?;
+                    settings.transparency = match transparency_mode_from_name(&name) {
+                        Some(transparency) => transparency,
+                        None => {
+                            let names: Vec<&str> = TRANSPARENCY_MODE_NAMES.iter().map(|(name, _)| *name).collect();
+                            return Err(err(entry.line, format!(
+                                "unknown transparency mode '{}', expected one of: {}", name, names.join(", "))));
+                        }
+                    };
+                }
+                if let Some(entry) = find_entry(section, "anti_aliasing") {
+                    let name = expect_string(entry, file_name)?;
+                    settings.anti_aliasing = match anti_aliasing_from_name(&name) {
+                        Some(anti_aliasing) => anti_aliasing,
+                        None => {
+                            let names: Vec<&str> = ANTI_ALIASING_NAMES.iter().map(|(name, _)| *name).collect();
+                            return Err(err(entry.line, format!(
+                                "unknown anti-aliasing '{}', expected one of: {}", name, names.join(", "))));
+                        }
+                    };
+                }
+                if let Some(entry) = find_entry(section, "line_width") {
+                    let width = expect_number(entry, file_name)?;
+                    if !(width > 0.0 && width.is_finite()) {
+                        return Err(err(entry.line, format!("line_width must be above 0, got {}", width)));
+                    }
+                    settings.line_style.width = width;
+                }
+                if let Some(entry) = find_entry(section, "smooth_lines") {
+                    settings.line_style.smooth = expect_bool(entry, file_name)?;
+                }
+                if let Some(entry) = find_entry(section, "hidden_lines") {
+                    let name = expect_string(entry, file_name)?;
+                    settings.line_style.hidden = match hidden_lines_from_name(&name) {
+                        Some(hidden) => hidden,
+                        None => {
+                            let names: Vec<&str> = HIDDEN_LINES_NAMES.iter().map(|(name, _)| *name).collect();
+                            return Err(err(entry.line, format!(
+                                "unknown hidden_lines '{}', expected one of: {}", name, names.join(", "))));
+                        }
+                    };
+                }
+                if let Some(entry) = find_entry(section, "line_edges") {
+                    let name = expect_string(entry, file_name)?;
+                    settings.line_style.edges = match line_edges_from_name(&name) {
+                        Some(edges) => edges,
+                        None => {
+                            let names: Vec<&str> = LINE_EDGES_NAMES.iter().map(|(name, _)| *name).collect();
+                            return Err(err(entry.line, format!(
+                                "unknown line_edges '{}', expected one of: {}", name, names.join(", "))));
+                        }
+                    };
+                }
+            },
+            ("camera", false) => {
+                if seen_camera {
+                    return Err(err(section.line, "[camera] appears more than once".to_string()));
+                }
+                seen_camera = true;
+                check_keys(section, &["position", "rotation", "fov", "znear", "zfar"], file_name)?;
+                if let Some(entry) = find_entry(section, "position") {
+                    scene.camera.position = expect_vec3(entry, file_name)?;
+                }
+                if let Some(entry) = find_entry(section, "rotation") {
+                    scene.camera.rotation = rotation_from_degrees(&expect_vec3(entry, file_name)?);
+                }
+                if let Some(entry) = find_entry(section, "fov") {
+                    let fov = expect_number(entry, file_name)?;
+                    if fov <= 0.0 || fov >= 180.0 {
+                        return Err(err(entry.line, format!("fov must be between 0 and 180 degrees, got {}", fov)));
+                    }
+                    scene.camera.fov_angle = fov.to_radians();
+                }
+                if let Some(entry) = find_entry(section, "znear") {
+                    scene.camera.znear = expect_number(entry, file_name)?;
+                }
+                if let Some(entry) = find_entry(section, "zfar") {
+                    scene.camera.zfar = expect_number(entry, file_name)?;
+                }
+                if scene.camera.znear <= 0.0 || scene.camera.zfar <= scene.camera.znear {
+                    return Err(err(section.line, format!(
+                        "the camera needs 0 < znear < zfar, got znear = {} and zfar = {}",
+                        scene.camera.znear, scene.camera.zfar)));
+                }
+            },
+            ("camera_key", true) => {
+                check_keys(section, &["time", "position", "rotation"], file_name)?;
+                let time_entry = require_entry(section, "time", file_name)?;
+                let time = expect_number(time_entry, file_name)?;
+                if let Some(previous) = scene.camera_path.last() {
+                    if time <= previous.time {
+                        return Err(err(time_entry.line, format!(
+                            "camera keys must go forward in time, got {} after {}", time, previous.time)));
+                    }
+                } else if time < 0.0 {
+                    return Err(err(time_entry.line, format!("camera key time can't be below 0, got {}", time)));
+                }
+                let position = expect_vec3(require_entry(section, "position", file_name)?, file_name)?;
+                let rotation = match find_entry(section, "rotation") {
+                    Some(entry) => rotation_from_degrees(&expect_vec3(entry, file_name)?),
+                    None => quat_identity(),
+                };
+                scene.camera_path.push(CameraKey { time, position, rotation });
+            },
+            ("light", true) => {
+                check_keys(section, &["direction"], file_name)?;
+                let entry = require_entry(section, "direction", file_name)?;
+                let direction = expect_vec3(entry, file_name)?;
+                scene.lights.push(Light { direction: vec3_normalize_or_zero(&direction) });
+            },
+            ("texture", true) => {
+                check_keys(section, &["name", "path"], file_name)?;
+                let name = expect_string(require_entry(section, "name", file_name)?, file_name)?;
+                let path_entry = require_entry(section, "path", file_name)?;
+                let path = expect_string(path_entry, file_name)?;
+                if scene.texture_sources.iter().any(|source| source.name == name) {
+                    return Err(err(section.line, format!("there is already a texture named '{}'", name)));
+                }
+                let full_path = base_dir.join(&path);
+                let texture = match load_png_texture(&full_path) {
+                    Ok(texture) => texture,
+                    Err(e) => return Err(err(path_entry.line, format!("texture '{}': {}", name, e))),
+                };
+                scene_add_texture(&mut scene, &name, &full_path.to_string_lossy(), texture);
+            },
+            ("mesh", true) => {
+                check_keys(section, &["name", "path"], file_name)?;
+                let name = expect_string(require_entry(section, "name", file_name)?, file_name)?;
+                let path_entry = require_entry(section, "path", file_name)?;
+                let path = expect_string(path_entry, file_name)?;
+                if scene.mesh_sources.iter().any(|source| source.name == name) {
+                    return Err(err(section.line, format!("there is already a mesh named '{}'", name)));
+                }
+                let mut source_path = path.clone();
+                let mesh = match path.strip_prefix(BUILTIN_MESH_PREFIX) {
+                    Some("cube") => get_cube_mesh(),
+                    Some(builtin) => {
+                        return Err(err(path_entry.line, format!(
+                            "unknown builtin mesh '{}', the only one is '{}cube'", builtin, BUILTIN_MESH_PREFIX)));
+                    },
+                    None => {
+                        source_path = base_dir.join(&path).to_string_lossy().into_owned();
+                        match load_obj_file(&source_path) {
+                            Ok(mesh) => mesh,
+                            Err(e) => return Err(err(path_entry.line, format!("mesh '{}': {}", name, e))),
+                        }
+                    }
+                };
+                scene_add_mesh(&mut scene, &name, &source_path, mesh);
+            },
+            ("node", true) => {
+                check_keys(
+                    section,
+                    &[
+                        "name", "parent", "mesh", "texture", "color", "opacity", "blend", "alpha_cutoff",
+                        "translation", "rotation", "scale", "spin",
+                    ],
+                    file_name)?;
+                let name = expect_string(require_entry(section, "name", file_name)?, file_name)?;
+                if nodes.iter().any(|entry| entry.node.name == name) {
+                    return Err(err(section.line, format!("there is already a node named '{}'", name)));
+                }
+                let mut node = scene_node_new(&name);
+
+                if let Some(entry) = find_entry(section, "mesh") {
+                    let mesh_name = expect_string(entry, file_name)?;
+                    node.mesh = match scene.mesh_sources.iter().position(|source| source.name == mesh_name) {
+                        Some(index) => Some(index),
+                        None => return Err(err(entry.line, format!(
+                            "node '{}' uses mesh '{}', which isn't defined by a [[mesh]] above it", name, mesh_name))),
+                    };
+                }
+                if let Some(entry) = find_entry(section, "texture") {
+                    let texture_name = expect_string(entry, file_name)?;
+                    node.material.texture = match scene.texture_sources.iter().position(|source| source.name == texture_name) {
+                        Some(index) => Some(index),
+                        None => return Err(err(entry.line, format!(
+                            "node '{}' uses texture '{}', which isn't defined by a [[texture]] above it", name, texture_name))),
+                    };
+                }
+                if let Some(entry) = find_entry(section, "color") {
+                    node.material.color = expect_color(entry, file_name)?;
+                }
+                if let Some(entry) = find_entry(section, "opacity") {
+                    let opacity = expect_number(entry, file_name)?;
+                    if !(0.0..=1.0).contains(&opacity) {
+                        return Err(err(entry.line, format!("opacity must be between 0 and 1, got {}", opacity)));
+                    }
+                    node.material.opacity = opacity;
+                }
+                if let Some(entry) = find_entry(section, "blend") {
+                    let blend_name = expect_string(entry, file_name)?;
+                    node.material.blend_mode = match blend_mode_from_name(&blend_name) {
+                        Some(blend_mode) => blend_mode,
+                        None => return Err(err(entry.line, format!(
+                            "unknown blend mode '{}', expected one of: {}", blend_name, BLEND_MODE_NAMES.join(", ")))),
+                    };
+                }
+                if let Some(entry) = find_entry(section, "alpha_cutoff") {
+                    let cutoff = expect_number(entry, file_name)?;
+                    if !matches!(node.material.blend_mode, BlendMode::Cutout(_)) {
+                        return Err(err(entry.line, "alpha_cutoff only works with blend = \"cutout\"".to_string()));
+                    }
+                    if !(0.0..=1.0).contains(&cutoff) {
+                        return Err(err(entry.line, format!("alpha_cutoff must be between 0 and 1, got {}", cutoff)));
+                    }
+                    node.material.blend_mode = BlendMode::Cutout(cutoff);
+                }
+                if let Some(entry) = find_entry(section, "translation") {
+                    node.transform.translation = expect_vec3(entry, file_name)?;
+                }
+                if let Some(entry) = find_entry(section, "rotation") {
+                    node.transform.rotation = rotation_from_degrees(&expect_vec3(entry, file_name)?);
+                }
+                if let Some(entry) = find_entry(section, "scale") {
+                    node.transform.scale = expect_vec3(entry, file_name)?;
+                }
+                if let Some(entry) = find_entry(section, "spin") {
+                    node.spin = expect_bool(entry, file_name)?;
+                }
+
+                let parent = match find_entry(section, "parent") {
+                    Some(entry) => Some((expect_string(entry, file_name)?, entry.line)),
+                    None => None,
+                };
+                nodes.push(NodeEntry { parent, line: section.line, node });
+            },
+            (name, is_list) => {
+                let written = if is_list { format!("[[{}]]", name) } else { format!("[{}]", name) };
+                return Err(err(section.line, format!(
+                    "unknown section {}, expected [render], [camera], [[camera_key]], [[light]], [[texture]], [[mesh]] \
+                    or [[node]]",
+                    written)));
+            }
+        }
+    }
+
+    add_nodes_in_parent_order(&mut scene, nodes, file_name)?;
+    Ok((scene, settings))
+}
+
+// adds every node whose parent is already in the scene, over and over until
+// they're all in. anything left over has a missing parent or is in a loop
+fn add_nodes_in_parent_order(scene: &mut Scene, mut nodes: Vec<NodeEntry>, file_name: &str) -> Result<(), String> {
+    while !nodes.is_empty() {
+        let mut added_any = false;
+        let mut i = 0;
+        while i < nodes.len() {
+            let parent_index = match &nodes[i].parent {
+                None => Some(None),
+                Some((parent_name, _)) => scene.nodes
+                    .iter()
+                    .position(|node| node.name == *parent_name)
+                    .map(Some),
+            };
+            match parent_index {
+                Some(parent) => {
+                    let entry = nodes.remove(i);
+                    scene_add_node(scene, parent, entry.node);
+                    added_any = true;
+                },
+                None => i += 1,
+            }
+        }
+
+        if !added_any {
+            let entry = &nodes[0];
+            let (parent_name, line) = entry.parent.clone().unwrap_or_default();
+            let message = if nodes.iter().any(|other| other.node.name == parent_name) {
+                format!("node '{}' is part of a loop of parents", entry.node.name)
+            } else {
+                format!("node '{}' has parent '{}', but there is no node with that name", entry.node.name, parent_name)
+            };
+            return Err(format!("{}:{}: {}", file_name, line.max(entry.line), message));
+        }
+    }
+    Ok(())
+}
+
+// the asset paths are written relative to `base_dir`, the folder the file is
+// going in
+pub fn scene_file_to_string(scene: &Scene, settings: &RenderSettings, base_dir: &Path) -> String {
+    let mut out = String::new();
+
+    out.push_str("[render]\n");
+    out.push_str(&format!("mode = \"{}\"\n", render_mode_name(settings.render_mode)));
+    out.push_str(&format!("backface_culling = {}\n", settings.backface_culling_enabled));
+    out.push_str(&format!("transparency = \"{}\"\n", transparency_mode_name(settings.transparency)));
+    out.push_str(&format!("anti_aliasing = \"{}\"\n", anti_aliasing_name(settings.anti_aliasing)));
+    out.push_str(&format!("line_width = {}\n", settings.line_style.width));
+    out.push_str(&format!("smooth_lines = {}\n", settings.line_style.smooth));
+    out.push_str(&format!("hidden_lines = \"{}\"\n", hidden_lines_name(settings.line_style.hidden)));
+    out.push_str(&format!("line_edges = \"{}\"\n", line_edges_name(settings.line_style.edges)));
+
+    let camera = &scene.camera;
+    out.push_str("\n[camera]\n");
+    out.push_str(&format!("position = {}\n", format_vec3(&camera.position)));
+    out.push_str(&format!("rotation = {}\n", format_vec3(&rotation_to_degrees(&camera.rotation))));
+    out.push_str(&format!("fov = {}\n", round_degrees(camera.fov_angle)));
+    out.push_str(&format!("znear = {}\n", camera.znear));
+    out.push_str(&format!("zfar = {}\n", camera.zfar));
+
+    for key in &scene.camera_path {
+        out.push_str("\n[[camera_key]]\n");
+        out.push_str(&format!("time = {}\n", key.time));
+        out.push_str(&format!("position = {}\n", format_vec3(&key.position)));
+        out.push_str(&format!("rotation = {}\n", format_vec3(&rotation_to_degrees(&key.rotation))));
+    }
+
+    for light in &scene.lights {
+        out.push_str("\n[[light]]\n");
+        out.push_str(&format!("direction = {}\n", format_vec3(&light.direction)));
+    }
+
+    for source in &scene.texture_sources {
+        out.push_str("\n[[texture]]\n");
+        out.push_str(&format!("name = {}\n", format_string(&source.name)));
+        out.push_str(&format!("path = {}\n", format_string(&asset_path_relative_to(&source.path, base_dir))));
+    }
+
+    for source in &scene.mesh_sources {
+        out.push_str("\n[[mesh]]\n");
+        out.push_str(&format!("name = {}\n", format_string(&source.name)));
+        out.push_str(&format!("path = {}\n", format_string(&asset_path_relative_to(&source.path, base_dir))));
+    }
+
+    // write the parents out before their children so the file reads top down
+    let mut stack: Vec<usize> = scene.roots.iter().rev().copied().collect();
+    while let Some(index) = stack.pop() {
+        let node = &scene.nodes[index];
+        out.push_str("\n[[node]]\n");
+        out.push_str(&format!("name = {}\n", format_string(&node.name)));
+        if let Some(parent) = scene_node_parent(scene, index) {
+            out.push_str(&format!("parent = {}\n", format_string(&scene.nodes[parent].name)));
+        }
+        if let Some(mesh) = node.mesh {
+            out.push_str(&format!("mesh = {}\n", format_string(&scene.mesh_sources[mesh].name)));
+        }
+        if let Some(texture) = node.material.texture {
+            out.push_str(&format!("texture = {}\n", format_string(&scene.texture_sources[texture].name)));
+        }
+        let color = node.material.color;
+        out.push_str(&format!("color = [{}, {}, {}, {}]\n", color.r, color.g, color.b, color.a));
+        out.push_str(&format!("opacity = {}\n", node.material.opacity));
+        out.push_str(&format!("blend = {}\n", format_string(blend_mode_name(node.material.blend_mode))));
+        if let BlendMode::Cutout(cutoff) = node.material.blend_mode {
+            out.push_str(&format!("alpha_cutoff = {}\n", cutoff));
+        }
+        out.push_str(&format!("translation = {}\n", format_vec3(&node.transform.translation)));
+        out.push_str(&format!("rotation = {}\n", format_vec3(&rotation_to_degrees(&node.transform.rotation))));
+        out.push_str(&format!("scale = {}\n", format_vec3(&node.transform.scale)));
+        if node.spin {
+            out.push_str("spin = true\n");
+        }
+
+        for &child in node.children.iter().rev() {
+            stack.push(child);
+        }
+    }
+    out
+}
+
+fn parse_sections(text: &str, file_name: &str) -> Result<Vec<Section>, String> {
+    let mut sections: Vec<Section> = Vec::new();
+
+    for (line_index, raw_line) in text.lines().enumerate() {
+        let line_number = line_index + 1;
+        let err = |message: String| format!("{}:{}: {}", file_name, line_number, message);
+        let line = strip_comment(raw_line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            let (name, is_list) = if line.starts_with("[[") && line.ends_with("]]") {
+                (&line[2..line.len() - 2], true)
+            } else if !line.starts_with("[[") && line.ends_with(']') {
+                (&line[1..line.len() - 1], false)
+            } else {
+                return Err(err(format!("section header '{}' isn't closed properly", line)));
+            };
+            sections.push(Section {
+                name: name.trim().to_string(),
+                is_list,
+                line: line_number,
+                entries: Vec::new(),
+            });
+            continue;
+        }
+
+        let (key, value_text) = match line.split_once('=') {
+            Some((key, value_text)) => (key.trim(), value_text.trim()),
+            None => return Err(err(format!("expected 'key = value' or a [section], found '{}'", line))),
+        };
+        if key.is_empty() {
+            return Err(err("missing the key before '='".to_string()));
+        }
+        let value = match parse_value(value_text) {
+            Ok(value) => value,
+            Err(message) => return Err(err(format!("bad value for '{}': {}", key, message))),
+        };
+
+        let section = match sections.last_mut() {
+            Some(section) => section,
+            None => return Err(err(format!("'{}' has to be inside a [section]", key))),
+        };
+        if section.entries.iter().any(|entry| entry.key == key) {
+            return Err(err(format!("'{}' is set more than once in this section", key)));
+        }
+        section.entries.push(Entry {
+            key: key.to_string(),
+            value,
+            line: line_number,
+        });
+    }
+    Ok(sections)
+}
+
+// everything after a # that isn't inside of a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == '#' {
+            return &line[..i];
+        }
+    }
+    line
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    if text.is_empty() {
+        return Err("the value is missing".to_string());
+    }
+
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut result = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    if !chars.as_str().trim().is_empty() {
+                        return Err(format!("unexpected '{}' after the closing quote", chars.as_str().trim()));
+                    }
+                    return Ok(Value::String(result));
+                },
+                '\\' => match chars.next() {
+                    Some('"') => result.push('"'),
+                    Some('\\') => result.push('\\'),
+                    Some('n') => result.push('\n'),
+                    Some(other) => return Err(format!("unknown escape '\\{}' in string", other)),
+                    None => break,
+                },
+                _ => result.push(c),
+            }
+        }
+        return Err("the string is missing its closing quote".to_string());
+    }
+
+    if text == "true" {
+        return Ok(Value::Bool(true));
+    }
+    if text == "false" {
+        return Ok(Value::Bool(false));
+    }
+
+    if let Some(inner) = text.strip_prefix('[') {
+        let inner = match inner.strip_suffix(']') {
+            Some(inner) => inner,
+            None => return Err("the list is missing its closing ']'".to_string()),
+        };
+        let mut numbers = Vec::new();
+        for item in inner.split(',') {
+            let item = item.trim();
+            if item.is_empty() {
+                continue;
+            }
+            match item.parse::<f32>() {
+                Ok(number) if number.is_finite() => numbers.push(number),
+                _ => return Err(format!("'{}' in the list isn't a number", item)),
+            }
+        }
+        return Ok(Value::List(numbers));
+    }
+
+    match text.parse::<f32>() {
+        Ok(number) if number.is_finite() => Ok(Value::Number(number)),
+        _ => Err(format!("'{}' isn't a number, true/false, a \"string\" or a [list]", text)),
+    }
+}
+
+fn find_entry<'a>(section: &'a Section, key: &str) -> Option<&'a Entry> {
+    section.entries.iter().find(|entry| entry.key == key)
+}
+
+fn require_entry<'a>(section: &'a Section, key: &str, file_name: &str) -> Result<&'a Entry, String> {
+    match find_entry(section, key) {
+        Some(entry) => Ok(entry),
+        None => Err(format!("{}:{}: [[{}]] is missing '{}'", file_name, section.line, section.name, key)),
+    }
+}
+
+fn check_keys(section: &Section, allowed: &[&str], file_name: &str) -> Result<(), String> {
+    for entry in &section.entries {
+        if !allowed.contains(&entry.key.as_str()) {
+            return Err(format!(
+                "{}:{}: unknown key '{}' in {}, expected one of: {}",
+                file_name, entry.line, entry.key, section_label(section), allowed.join(", ")));
+        }
+    }
+    Ok(())
+}
+
+fn section_label(section: &Section) -> String {
+    if section.is_list {
+        format!("[[{}]]", section.name)
+    } else {
+        format!("[{}]", section.name)
+    }
+}
+
+fn type_error(entry: &Entry, expected: &str, file_name: &str) -> String {
+    let found = match &entry.value {
+        Value::String(_) => "a string".to_string(),
+        Value::Bool(_) => "true/false".to_string(),
+        Value::Number(_) => "a number".to_string(),
+        Value::List(numbers) => format!("a list of {} numbers", numbers.len()),
+    };
+    format!("{}:{}: '{}' should be {}, found {}", file_name, entry.line, entry.key, expected, found)
+}
+
+fn expect_string(entry: &Entry, file_name: &str) -> Result<String, String> {
+    match &entry.value {
+        Value::String(s) => Ok(s.clone()),
+        _ => Err(type_error(entry, "a \"string\"", file_name)),
+    }
+}
+
+fn expect_bool(entry: &Entry, file_name: &str) -> Result<bool, String> {
+    match entry.value {
+        Value::Bool(b) => Ok(b),
+        _ => Err(type_error(entry, "true or false", file_name)),
+    }
+}
+
+fn expect_number(entry: &Entry, file_name: &str) -> Result<f32, String> {
+    match entry.value {
+        Value::Number(n) => Ok(n),
+        _ => Err(type_error(entry, "a number", file_name)),
+    }
+}
+
+fn expect_vec3(entry: &Entry, file_name: &str) -> Result<Vec3, String> {
+    match &entry.value {
+        Value::List(numbers) if numbers.len() == 3 => Ok(Vec3 { x: numbers[0], y: numbers[1], z: numbers[2] }),
+        _ => Err(type_error(entry, "a list of 3 numbers like [0, 0, 0]", file_name)),
+    }
+}
+
+fn expect_color(entry: &Entry, file_name: &str) -> Result<Color, String> {
+    let numbers = match &entry.value {
+        Value::List(numbers) if numbers.len() == 3 || numbers.len() == 4 => numbers,
+        _ => return Err(type_error(entry, "a list of 3 or 4 numbers from 0 to 255", file_name)),
+    };
+    for &number in numbers {
+        if !(0.0..=255.0).contains(&number) || number.fract() != 0.0 {
+            return Err(format!(
+                "{}:{}: colour values have to be whole numbers from 0 to 255, found {}",
+                file_name, entry.line, number));
+        }
+    }
+    let alpha = if numbers.len() == 4 { numbers[3] as u8 } else { 255 };
+    Ok(Color::RGBA(numbers[0] as u8, numbers[1] as u8, numbers[2] as u8, alpha))
+}
+
+fn rotation_from_degrees(degrees: &Vec3) -> crate::quaternion::Quat {
+    quat_from_euler(degrees.x.to_radians(), degrees.y.to_radians(), degrees.z.to_radians(), EulerOrder::XYZ)
+}
+
+fn rotation_to_degrees(rotation: &crate::quaternion::Quat) -> Vec3 {
+    let radians = quat_to_euler_xyz(rotation);
+    Vec3 {
+        x: round_degrees(radians.x),
+        y: round_degrees(radians.y),
+        z: round_degrees(radians.z),
+    }
+}
+
+// rounded to a thousandth of a degree so the saved file reads 45 rather than
+// 45.000004 after the trip through radians, and -0 becomes 0
+fn round_degrees(radians: f32) -> f32 {
+    (radians.to_degrees() * 1000.0).round() / 1000.0 + 0.0
+}
+
+// the path to write for an asset at `path`, which is either absolute or
+// relative to the working directory, into a scene file in `base_dir`. it's
+// made relative to `base_dir` so the two can be moved around together, unless
+// they only share the root of the file system. builtin meshes and files that
+// can't be found are left as they are
+fn asset_path_relative_to(path: &str, base_dir: &Path) -> String {
+    if path.starts_with(BUILTIN_MESH_PREFIX) {
+        return path.to_string();
+    }
+    let base_dir = if base_dir.as_os_str().is_empty() { Path::new(".") } else { base_dir };
+    let (asset, base_dir) = match (fs::canonicalize(path), fs::canonicalize(base_dir)) {
+        (Ok(asset), Ok(base_dir)) => (asset, base_dir),
+        _ => return path.to_string(),
+    };
+    let shared_components: Vec<Component> =
+        asset.components().zip(base_dir.components()).take_while(|(a, b)| a == b).map(|(a, _)| a).collect();
+    if !shared_components.iter().any(|component| matches!(component, Component::Normal(_))) {
+        return asset.to_string_lossy().into_owned();
+    }
+    let shared = shared_components.len();
+    let mut relative = PathBuf::new();
+    for _ in shared..base_dir.components().count() {
+        relative.push("..");
+    }
+    relative.extend(asset.components().skip(shared));
+    relative.to_string_lossy().into_owned()
+}
+
+fn format_vec3(v: &Vec3) -> String {
+    format!("[{}, {}, {}]", v.x, v.y, v.z)
+}
+
+fn format_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use crate::pipeline::{PipelineStats, pipeline_stats_new};
+
+// where the time went in one frame and how much work it did, for finding out
+// why a frame was slow. the phases nest like this:
+//
+//   input
+//   update     simulation steps, scene update, transform, cull, sort
+//   render     raster, hud, present
+//
+// the time spent waiting for the frame limit isn't in any of them
+#[derive(Debug, Copy, Clone)]
+pub struct FrameStats {
+    pub frame_index: usize,
+    // the time the simulation moved on by this frame, in seconds
+    pub delta_time: f32,
+    pub input_time: Duration,
+    pub update_time: Duration,
+    pub sort_time: Duration,
+    pub render_time: Duration,
+    pub raster_time: Duration,
+    // showing the frame and clearing the buffers for the next one
+    pub present_time: Duration,
+    // the face counts and the transform and cull times
+    pub pipeline: PipelineStats,
+    // every pixel the rasterizer wrote, counting the ones drawn over
+    pub pixels_written: usize,
+    // see through pixels kept in the a-buffer, and the ones that didn't fit
+    pub fragments_stored: usize,
+    pub fragments_overflowed: usize,
+    // pixels written for each pixel on the screen, 1.0 would be the same as
+    // filling the screen exactly once
+    pub overdraw: f32,
+}
+
+pub fn frame_stats_new() -> FrameStats {
+    FrameStats {
+        frame_index: 0,
+        delta_time: 0.0,
+        input_time: Duration::ZERO,
+        update_time: Duration::ZERO,
+        sort_time: Duration::ZERO,
+        render_time: Duration::ZERO,
+        raster_time: Duration::ZERO,
+        present_time: Duration::ZERO,
+        pipeline: pipeline_stats_new(),
+        pixels_written: 0,
+        fragments_stored: 0,
+        fragments_overflowed: 0,
+        overdraw: 0.0,
+    }
+}
+
+pub fn overdraw(pixels_written: usize, screen_width: usize, screen_height: usize) -> f32 {
+    let screen_pixels = screen_width * screen_height;
+    if screen_pixels == 0 {
+        return 0.0;
+    }
+    pixels_written as f32 / screen_pixels as f32
+}
+
+// the columns of frame_stats_csv_row, the times are all in milliseconds
+pub const FRAME_STATS_CSV_HEADER: &str = "frame,delta_ms,input_ms,update_ms,transform_ms,cull_ms,sort_ms,\
+render_ms,raster_ms,present_ms,triangles_submitted,triangles_culled,triangles_offscreen,triangles_drawn,\
+pixels_written,overdraw,fragments_stored,fragments_overflowed";
+
+pub fn frame_stats_csv_row(stats: &FrameStats) -> String {
+    let ms = |duration: Duration| format!("{:.3}", duration.as_secs_f64() * 1000.0);
+    format!(
+        "{},{:.3},{},{},{},{},{},{},{},{},{},{},{},{},{},{:.3},{},{}",
+        stats.frame_index,
+        stats.delta_time * 1000.0,
+        ms(stats.input_time),
+        ms(stats.update_time),
+        ms(stats.pipeline.transform_time),
+        ms(stats.pipeline.cull_time),
+        ms(stats.sort_time),
+        ms(stats.render_time),
+        ms(stats.raster_time),
+        ms(stats.present_time),
+        stats.pipeline.triangles_submitted,
+        stats.pipeline.triangles_culled,
+        stats.pipeline.triangles_offscreen,
+        stats.pipeline.triangles_drawn,
+        stats.pixels_written,
+        stats.overdraw,
+        stats.fragments_stored,
+        stats.fragments_overflowed)
+}
@@ -0,0 +1,232 @@
+use std::{fs, path::Path};
+
+use sdl2::pixels::Color;
+
+use crate::{
+    display::{RenderMode, render_mode_name},
+    line::{HIDDEN_LINE_OPACITY, HiddenLines, LineEdges, LineStyle, triangle_line_points},
+    material::BlendMode,
+    raster::{DOT_COLOR, DOT_RADIUS, WIREFRAME_COLOR},
+    triangle::Triangle,
+};
+
+// writes a frame's projected and sorted triangles out as an svg, so it can
+// be scaled to any size for diagrams. the triangles go in the order given,
+// which after sort_triangles_by_depth is back to front, so nearer ones are
+// painted over farther ones instead of using a depth buffer. the filled modes
+// become polygons in the triangles' flat colours, with textures left out,
+// and the wireframe modes become strokes in the display's line style.
+//
+// hidden lines are taken out the same way: each triangle is filled with the
+// background before its edges are drawn, which covers up whatever edges it's
+// in front of. dimmed or dashed hidden lines are then drawn again over all of
+// that, each edge once. where an edge is in front this only goes over its
+// own solid stroke, so it's the hidden ones that change
+
+// the colour the frame is cleared to, the same as the viewer's
+const BACKGROUND_COLOR: Color = Color::RGBA(0, 0, 0, 255);
+
+// the svg's coordinates run along the edges of the pixels, where the
+// rasterizer's are at their middles
+const PIXEL_CENTER: f32 = 0.5;
+
+pub fn svg_from_triangles(
+    triangles: &[Triangle],
+    width: usize,
+    height: usize,
+    render_mode: RenderMode,
+    line_style: &LineStyle) -> Result<String, String> {
+    let (fill, stroke) = match render_mode {
+        RenderMode::WireframeWithDot |
+        RenderMode::Wireframe |
+        RenderMode::HiddenLine => (false, true),
+        RenderMode::FilledTriangles |
+        RenderMode::Textured => (true, false),
+        RenderMode::FilledTrianglesAndWireframe |
+        RenderMode::TexturedAndWireframe => (true, true),
+        _ => {
+            return Err(format!(
+                "render mode '{}' can't be saved as an svg, only the wireframe, filled and textured ones can",
+                render_mode_name(render_mode)));
+        }
+    };
+    let mut hidden = line_style.hidden;
+    if render_mode == RenderMode::HiddenLine && hidden == HiddenLines::Show {
+        hidden = HiddenLines::Hide;
+    }
+    let triangles: Vec<&Triangle> = triangles.iter().filter(|triangle| triangle_is_drawable(triangle)).collect();
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        width, height, width, height));
+    out.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>\n", width, height, svg_color(BACKGROUND_COLOR)));
+    out.push_str(&format!("<g transform=\"translate({} {})\">\n", PIXEL_CENTER, PIXEL_CENTER));
+
+    let stroke_attributes = format!(
+        "fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"",
+        svg_color(WIREFRAME_COLOR), format_number(line_style.width));
+
+    out.push_str(&format!("<g {}>\n", stroke_attributes));
+    for triangle in &triangles {
+        let points = polygon_points(triangle);
+        if fill {
+            out.push_str(&format!("<polygon points=\"{}\" {}/>\n", points, fill_attributes(triangle)));
+        } else if stroke && hidden != HiddenLines::Show && triangle_hides_lines(triangle) {
+            out.push_str(&format!("<polygon points=\"{}\" fill=\"{}\" stroke=\"none\"/>\n",
+                points, svg_color(BACKGROUND_COLOR)));
+        }
+        if stroke {
+            push_path(&mut out, &edges_path(triangle, line_style, false));
+        }
+    }
+    out.push_str("</g>\n");
+
+    // the hidden lines, over the fills that would bury them
+    if stroke && (hidden == HiddenLines::Dim || hidden == HiddenLines::Dashed) {
+        let hidden_attributes = match hidden {
+            HiddenLines::Dim => format!("stroke-opacity=\"{}\"", format_number(HIDDEN_LINE_OPACITY)),
+            _ => format!("stroke-dasharray=\"{}\"", format_number(line_style.dash_length)),
+        };
+        out.push_str(&format!("<g {} {}>\n", stroke_attributes, hidden_attributes));
+        for triangle in &triangles {
+            push_path(&mut out, &edges_path(triangle, line_style, true));
+        }
+        out.push_str("</g>\n");
+    }
+
+    // the dots aren't hidden by anything, the same as when rasterized
+    if render_mode == RenderMode::WireframeWithDot {
+        let size = DOT_RADIUS * 2.0;
+        out.push_str(&format!("<g fill=\"{}\">\n", svg_color(DOT_COLOR)));
+        for triangle in &triangles {
+            for point in &triangle.points {
+                out.push_str(&format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+                    format_number(point.x - DOT_RADIUS - PIXEL_CENTER), format_number(point.y - DOT_RADIUS - PIXEL_CENTER),
+                    format_number(size), format_number(size)));
+            }
+        }
+        out.push_str("</g>\n");
+    }
+
+    out.push_str("</g>\n</svg>\n");
+    Ok(out)
+}
+
+pub fn save_svg(
+    path: &Path,
+    triangles: &[Triangle],
+    width: usize,
+    height: usize,
+    render_mode: RenderMode,
+    line_style: &LineStyle) -> Result<(), String> {
+    let svg = svg_from_triangles(triangles, width, height, render_mode, line_style)?;
+    match fs::write(path, svg) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("Failed to save svg '{}': {}", path.display(), e)),
+    }
+}
+
+// a point that went off to infinity or NaN can't be written out, and the
+// rasterizer wouldn't have drawn anything sensible for it either
+fn triangle_is_drawable(triangle: &Triangle) -> bool {
+    triangle.points.iter().all(|point| point.x.is_finite() && point.y.is_finite())
+}
+
+// see through triangles don't go into the depth buffer, so they don't hide
+// the lines behind them either
+fn triangle_hides_lines(triangle: &Triangle) -> bool {
+    matches!(triangle.blend_mode, BlendMode::Opaque | BlendMode::Cutout(_))
+}
+
+fn fill_attributes(triangle: &Triangle) -> String {
+    let color = triangle.color;
+    // the fill's own outline covers the hairline gaps svg viewers leave
+    // between polygons that share an edge
+    let mut attributes = format!(
+        "fill=\"{}\" stroke=\"{}\" stroke-width=\"0.5\"", svg_color(color), svg_color(color));
+    let opacity = format_number(color.a as f32 / 255.0);
+    match triangle.blend_mode {
+        BlendMode::Opaque => {},
+        // a flat colour is either all kept or all cut away
+        BlendMode::Cutout(cutoff) => {
+            if (color.a as f32 / 255.0) < cutoff {
+                attributes = "fill=\"none\" stroke=\"none\"".to_string();
+            }
+        },
+        BlendMode::Alpha => {
+            attributes = format!("fill=\"{}\" fill-opacity=\"{}\" stroke=\"none\"", svg_color(color), opacity);
+        },
+        BlendMode::Additive => {
+            attributes = format!(
+                "fill=\"{}\" fill-opacity=\"{}\" stroke=\"none\" style=\"mix-blend-mode:plus-lighter\"",
+                svg_color(color), opacity);
+        },
+        BlendMode::Multiply => {
+            attributes = format!(
+                "fill=\"{}\" fill-opacity=\"{}\" stroke=\"none\" style=\"mix-blend-mode:multiply\"",
+                svg_color(color), opacity);
+        },
+    }
+    attributes
+}
+
+fn polygon_points(triangle: &Triangle) -> String {
+    let [a, b, c] = &triangle.points;
+    format!(
+        "{},{} {},{} {},{}",
+        format_number(a.x), format_number(a.y),
+        format_number(b.x), format_number(b.y),
+        format_number(c.x), format_number(c.y))
+}
+
+// the edges of the triangle the line style draws, as path data. with
+// `owned_only` an edge shared with another triangle is left to whichever of
+// the two owns it
+fn edges_path(triangle: &Triangle, line_style: &LineStyle, owned_only: bool) -> String {
+    let points = triangle_line_points(triangle);
+    let candidates = match line_style.edges {
+        LineEdges::All => [true; 3],
+        LineEdges::Outline => triangle.outline_edges,
+    };
+    let drawn: [bool; 3] =
+        std::array::from_fn(|edge| candidates[edge] && (triangle.owned_edges[edge] || !owned_only));
+    if drawn == [true; 3] {
+        return format!(
+            "M{} {}L{} {}L{} {}Z",
+            format_number(points[0].x), format_number(points[0].y),
+            format_number(points[1].x), format_number(points[1].y),
+            format_number(points[2].x), format_number(points[2].y));
+    }
+    let mut path = String::new();
+    for edge in 0..3 {
+        if drawn[edge] {
+            let (from, to) = (points[edge], points[(edge + 1) % 3]);
+            path.push_str(&format!(
+                "M{} {}L{} {}", format_number(from.x), format_number(from.y), format_number(to.x), format_number(to.y)));
+        }
+    }
+    path
+}
+
+// leaves out the paths of triangles that don't have any of their edges drawn
+fn push_path(out: &mut String, path: &str) {
+    if !path.is_empty() {
+        out.push_str(&format!("<path d=\"{}\"/>\n", path));
+    }
+}
+
+fn svg_color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+// two decimal places is a hundredth of a pixel, without the trailing zeros
+fn format_number(value: f32) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        return "0".to_string();
+    }
+    text.to_string()
+}
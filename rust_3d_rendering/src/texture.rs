@@ -8,6 +8,38 @@ pub struct Tex2 {
     pub v: f32
 }
 
+// a texture's pixels along with its size, so meshes can use textures that
+// aren't all the same size
+#[derive(Debug, Clone)]
+pub struct MeshTexture {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>
+}
+
+pub fn load_png_texture<P: AsRef<Path>>(path: P) -> Result<MeshTexture, String> {
+    let img = match image::open(path) {
+        Ok(image) => image,
+        Err(e) => return Err(format!("Failed to open image: {}", e)),
+    };
+
+    let (width, height) = img.dimensions();
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let rgba = img.get_pixel(x, y).to_rgba();
+            pixels.push(Color::RGBA(rgba[0], rgba[1], rgba[2], rgba[3]));
+        }
+    }
+
+    Ok(MeshTexture {
+        width: width as usize,
+        height: height as usize,
+        pixels
+    })
+}
+
 pub fn read_png_to_colors<P: AsRef<Path>>(path: P) -> Result<Vec<Color>, String> {
     let img = match image::open(path) {
         Ok(image) => image,
@@ -0,0 +1,32 @@
+use crate::{
+    matrix::{Mat4, mat4_make_scale, mat4_make_translation, mat4_mul_mat4},
+    quaternion::{Quat, quat_identity, quat_to_mat4},
+    vector::Vec3,
+};
+
+#[derive(Debug, Copy, Clone)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+pub fn transform_identity() -> Transform {
+    Transform {
+        translation: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        rotation: quat_identity(),
+        scale: Vec3 { x: 1.0, y: 1.0, z: 1.0 }
+    }
+}
+
+pub fn transform_to_mat4(transform: &Transform) -> Mat4 {
+    let scale_matrix = mat4_make_scale(transform.scale.x, transform.scale.y, transform.scale.z);
+    let rotation_matrix = quat_to_mat4(&transform.rotation);
+    let translation_matrix = mat4_make_translation(
+        transform.translation.x, transform.translation.y, transform.translation.z);
+
+    // scale, then rotate, then translate in that order. otherwise issues occur
+    // with the matrix multiplications
+    let world_matrix = mat4_mul_mat4(&rotation_matrix, &scale_matrix);
+    mat4_mul_mat4(&translation_matrix, &world_matrix)
+}
@@ -1,6 +1,13 @@
 use sdl2::pixels::Color;
 
-use crate::{vector::{Vec4, vec2_sub, Vec2, Vec3}, display::Display, texture::Tex2, swap::swap};
+use crate::{
+    antialias::MAX_SAMPLES,
+    vector::{Vec4, Vec2, Vec3, vec3_div, vec3_dot},
+    display::FrameTile,
+    texture::{MeshTexture, Tex2},
+    swap::swap,
+    material::{BlendMode, blend_mode_is_transparent},
+};
 
 #[derive(Debug, Clone)]
 pub struct Face {
@@ -18,213 +25,483 @@ pub struct Triangle {
     pub points: [Vec4; 3],
     pub texcoords: [Tex2; 3],
     pub color: Color,
-    pub avg_depth: f32
+    // the unit face normal in camera space
+    pub normal: Vec3,
+    pub avg_depth: f32,
+    // index into the scene's textures of the material this triangle came from
+    pub texture: Option<usize>,
+    pub blend_mode: BlendMode,
+    // which of the edges a-b, b-c and c-a are on the outline of the mesh,
+    // along the silhouette, at a crease or on an open border
+    pub outline_edges: [bool; 3],
+    // which of the edges this triangle draws when every edge of the mesh is
+    // only drawn once. an edge between two faces that are both drawn
+    // belongs to the one that comes first in the mesh
+    pub owned_edges: [bool; 3],
+    // which face of its mesh this triangle was built from, which stays the
+    // same from frame to frame while its place in the sorted list doesn't
+    pub face_index: usize,
+    // which node of the scene this triangle's mesh was drawn for, so the
+    // same face of two meshes, or of one mesh used twice, can be told apart
+    pub node_index: usize,
 }
 
-pub fn draw_filled_triangle(
-    display: &mut Display, 
-    mut x0: usize, mut y0: usize,
-    mut x1: usize, mut y1: usize,
-    mut x2: usize, mut y2: usize,
-    color: Color) {
+// draws a solid triangle, testing every pixel against the depth buffer using
+// 1/w interpolated across the triangle. the points are in screen pixels and
+// w is the original view space z kept from the projection. without
+// multisampling they're snapped to whole pixels first. transparent blend
+// modes are depth tested but don't write to the depth buffer
+pub fn draw_filled_triangle(tile: &mut FrameTile, points: &[Vec4; 3], color: Color, blend_mode: BlendMode) {
+    if tile.sample_offsets().len() > 1 {
+        rasterize_multisampled(tile, points, |tile, x, y, _, coverage, depths| {
+            tile.draw_samples(x, y, coverage, depths, color, blend_mode);
+        });
+        return;
+    }
+
+    let [(mut x0, mut y0, mut w0), (mut x1, mut y1, mut w1), (mut x2, mut y2, mut w2)] =
+        points.map(|point| (point.x as i32, point.y as i32, point.w));
+
     if y0 > y1 {
         swap(&mut y0, &mut y1);
         swap(&mut x0, &mut x1);
+        swap(&mut w0, &mut w1);
     }
     if y1 > y2 {
         swap(&mut y1, &mut y2);
         swap(&mut x1, &mut x2);
+        swap(&mut w1, &mut w2);
     }
     if y0 > y1 {
         swap(&mut y0, &mut y1);
         swap(&mut x0, &mut x1);
+        swap(&mut w0, &mut w1);
     }
-    
-    if y1 == y2 {
-        // no need to draw botton triangle in this case
-        fill_flat_bottom_triangle(display, x0, y0, x1, y1, x2, y2, color);
-    } else if y0 == y1 {
-        // no need to draw the top triangle in this case
-        fill_flat_top_triangle(display, x0, y0, x1, y1, x2, y2, color);
-    } else {
-        // need to draw both halves of the triangle
-        let my = y1;    
-        let mx = ((((x2 as isize - x0 as isize) * (y1 as isize - y0 as isize)) as f32 / (y2 as isize - y0 as isize) as f32) as f32 + x0 as f32) as usize;
 
-        fill_flat_bottom_triangle(display, x0, y0, x1, y1, mx as usize, my, color);
-        fill_flat_top_triangle(display, x1, y1, mx as usize, my, x2, y2, color);
-    }    
-}
+    // the corners snapped to whole pixels, with their w
+    let corners = [(x0, y0, w0), (x1, y1, w1), (x2, y2, w2)]
+        .map(|(x, y, w)| Vec4 { x: x as f32, y: y as f32, z: 0.0, w });
 
-fn fill_flat_bottom_triangle(
-    display: &mut Display, 
-    x0: usize, y0: usize,
-    x1: usize, y1: usize,
-    x2: usize, y2: usize,
-    color: Color) {
-    let inv_slope_1 = if y1 != y0 {
-        (x1 as isize - x0 as isize) as f32 / (y1 as isize - y0 as isize) as f32
-    } else {
-        0.0
-    };
-
-    let inv_slope_2 = if y2 != y0 {
-        (x2 as isize - x0 as isize) as f32 / (y2 as isize - y0 as isize) as f32
-    } else {
-        0.0
-    };
+    // only walk the rows that are inside of this tile
+    let y_first = y0.max(tile.y_start as i32);
+    let y_last = y2.min(tile.y_end as i32 - 1);
 
-    let mut x_start = x0 as f32;
-    let mut x_end = x0 as f32;
+    for y in y_first..=y_last {
+        let (x_start, x_end) = scanline_span(y, x0, y0, x1, y1, x2, y2);
 
-    for y in y0..=y2 {
-        display.draw_line(x_start as usize, y, x_end as usize, y, color);
-        x_start += inv_slope_1;
-        x_end += inv_slope_2;            
+        for x in x_start.max(0)..x_end.min(tile.width as i32) {
+            draw_triangle_pixel(tile, x as usize, y as usize, color, blend_mode, &corners);
+        }
     }
 }
 
-fn fill_flat_top_triangle(
-    display: &mut Display, 
-    x0: usize, y0: usize,
-    x1: usize, y1: usize,
-    x2: usize, y2: usize,
-    color: Color) {
-    let inv_slope_1 = if y2 != y0 {
-        (x2 as isize - x0 as isize) as f32 / (y2 as isize - y0 as isize) as f32
-    } else {
-        0.0
-    };
+// the x range [start, end) that a triangle covers on row y. the vertices have
+// to be sorted by y already. rows above y1 are between the edges 0->1 and
+// 0->2, and rows from y1 down are between the edges 1->2 and 0->2. each end is
+// worked out directly from the row instead of stepping it along from the row
+// above, so it comes out the same no matter which row the drawing starts at
+fn scanline_span(y: i32, x0: i32, y0: i32, x1: i32, y1: i32, x2: i32, y2: i32) -> (i32, i32) {
+    if y2 == y0 {
+        return (0, 0);
+    }
 
-    let inv_slope_2 = if y2 != y1 {
-        (x2 as isize - x1 as isize) as f32 / (y2 as isize - y1 as isize) as f32
+    let inv_slope_long = (x2 - x0) as f32 / (y2 - y0) as f32;
+    let x_long = (x0 as f32 + (y - y0) as f32 * inv_slope_long) as i32;
+
+    let x_short = if y < y1 {
+        let inv_slope = (x1 - x0) as f32 / (y1 - y0) as f32;
+        (x0 as f32 + (y - y0) as f32 * inv_slope) as i32
+    } else if y2 != y1 {
+        let inv_slope = (x2 - x1) as f32 / (y2 - y1) as f32;
+        (x1 as f32 + (y - y1) as f32 * inv_slope) as i32
     } else {
-        0.0
+        x1
     };
 
-    let mut x_start = x2 as f32;
-    let mut x_end = x2 as f32;
-
-    for y in (y0..=y2).rev() {
-        display.draw_line(x_start as usize, y, x_end as usize, y, color);
-        x_start -= inv_slope_1;
-        x_end -= inv_slope_2;            
+    if x_long < x_short {
+        (x_long, x_short)
+    } else {
+        (x_short, x_long)
     }
 }
 
-fn draw_texel(display: &mut Display, x: usize, y: usize, texture: &Vec<Color>, texture_width: usize, texture_height: usize,
-    point_a: &Vec2, point_b: &Vec2, point_c: &Vec2,
-    u0: f32, v0: f32, u1: f32, v1: f32, u2: f32, v2: f32) {
+fn draw_triangle_pixel(
+    tile: &mut FrameTile, x: usize, y: usize, color: Color, blend_mode: BlendMode, corners: &[Vec4; 3]) {
+    let [point_a, point_b, point_c] = corners.map(|corner| Vec2 { x: corner.x, y: corner.y });
+    let [w0, w1, w2] = corners.map(|corner| corner.w);
     let point_p = Vec2 { x: x as f32, y: y as f32 };
-    let weights = barycentric_weights(point_a, point_b, point_c, &point_p);
+    let weights = barycentric_weights(&point_a, &point_b, &point_c, &point_p);
 
     let alpha = weights.x;
     let beta = weights.y;
     let gamma = weights.z;
 
-    let interpolated_u = u0 * alpha + u1 * beta + u2 * gamma;
-    let interpolated_v = v0 * alpha + v1 * beta + v2 * gamma;
-
-    let tex_x = (interpolated_u * texture_width as f32).abs() as usize;
-    let tex_y = (interpolated_v * texture_height as f32).abs() as usize;
+    // 1/w is linear in screen space where w itself isn't, so that is what gets
+    // interpolated and compared. 1 - 1/w makes smaller values closer
+    let interpolated_reciprocal_w = alpha / w0 + beta / w1 + gamma / w2;
+    let depth = 1.0 - interpolated_reciprocal_w;
 
-    let texture_index = (texture_width * tex_y) + tex_x;
-    if texture_index < texture.len() {
-        display.draw_pixel(x, y, texture[texture_index]);
+    if depth < tile.depth_at(x, y) {
+        draw_blended_pixel(tile, x, y, color, depth, blend_mode);
     }
 }
 
-pub fn draw_textured_triangle(
-    display: &mut Display, 
-    mut x0: usize, mut y0: usize,
-    mut u0: f32, mut v0: f32,
-    mut x1: usize, mut y1: usize,
-    mut u1: f32, mut v1: f32,
-    mut x2: usize, mut y2: usize,
-    mut u2: f32, mut v2: f32,
-    texture: &Vec<Color>, texture_width: usize, texture_height: usize) {
-    if y0 > y1 {
-        swap(&mut y0, &mut y1);
-        swap(&mut x0, &mut x1);
-        swap(&mut u0, &mut u1);
-        swap(&mut v0, &mut v1);
+// draws a pixel that passed the depth test by its blend mode. only pixels
+// that end up solid write their depth, see through ones go through the
+// tile so they can be kept in the a-buffer
+fn draw_blended_pixel(tile: &mut FrameTile, x: usize, y: usize, color: Color, depth: f32, blend_mode: BlendMode) {
+    if blend_mode_is_transparent(blend_mode) {
+        tile.draw_transparent_pixel(x, y, color, depth, blend_mode);
+    } else if tile.blend_pixel(x, y, color, blend_mode) {
+        tile.set_depth(x, y, depth);
     }
-    if y1 > y2 {
-        swap(&mut y1, &mut y2);
-        swap(&mut x1, &mut x2);
-        swap(&mut u1, &mut u2);
-        swap(&mut v1, &mut v2);
+}
+
+// the texel at (u, v) with its alpha scaled by the opacity, or None when the
+// coordinates fall outside of the texture
+fn texel_color(texture: &MeshTexture, opacity: u8, u: f32, v: f32) -> Option<Color> {
+    let tex_x = (u * texture.width as f32).abs() as usize;
+    let tex_y = (v * texture.height as f32).abs() as usize;
+
+    let texture_index = (texture.width * tex_y) + tex_x;
+    if texture_index < texture.pixels.len() {
+        let texel = texture.pixels[texture_index];
+        Some(Color::RGBA(texel.r, texel.g, texel.b, ((texel.a as u32 * opacity as u32) / 255) as u8))
+    } else {
+        None
     }
-    if y0 > y1 {
-        swap(&mut y0, &mut y1);
-        swap(&mut x0, &mut x1);
-        swap(&mut u0, &mut u1);
-        swap(&mut v0, &mut v1);
+}
+
+// draws a triangle with its texture stretched across it, the texture
+// coordinates going in a straight line across the screen between the points.
+// the texels' alpha is scaled by `opacity`. pixels are depth tested the same
+// as draw_filled_triangle
+pub fn draw_textured_triangle(
+    tile: &mut FrameTile, points: &[Vec4; 3], texcoords: &[Tex2; 3], texture: &MeshTexture, opacity: u8,
+    blend_mode: BlendMode) {
+    let [uv0, uv1, uv2] = *texcoords;
+    if tile.sample_offsets().len() > 1 {
+        rasterize_multisampled(tile, points, |tile, x, y, weights, coverage, depths| {
+            let u = uv0.u * weights.x + uv1.u * weights.y + uv2.u * weights.z;
+            let v = uv0.v * weights.x + uv1.v * weights.y + uv2.v * weights.z;
+            if let Some(color) = texel_color(texture, opacity, u, v) {
+                tile.draw_samples(x, y, coverage, depths, color, blend_mode);
+            }
+        });
+        return;
     }
 
+    let mut vertices = [0, 1, 2].map(|i| (points[i].x as i32, points[i].y as i32, points[i].w, texcoords[i]));
+    vertices.sort_by_key(|vertex| vertex.1);
+    let [(x0, y0, w0, uv0), (x1, y1, w1, uv1), (x2, y2, w2, uv2)] = vertices;
+
     let point_a = Vec2 { x: x0 as f32, y: y0 as f32};
     let point_b = Vec2 { x: x1 as f32, y: y1 as f32};
     let point_c = Vec2 { x: x2 as f32, y: y2 as f32};
-    
-    // render top part of the triangle
-    let mut inv_slope_1: f32 = 0.0;
-    let mut inv_slope_2: f32 = 0.0; 
-
-    if y1 as isize - y0 as isize != 0 {
-        inv_slope_1 = (x1 as isize - x0 as isize) as f32 / ((y1 as isize - y0 as isize) as f32).abs();
-    }    
-    if y2 as isize - y0 as isize != 0 {
-        inv_slope_2 = (x2 as isize - x0 as isize) as f32 / ((y2 as isize - y0 as isize) as f32).abs();
-    }
-    
-    if y1 as isize - y0 as isize != 0 {
-        for y in y0..=y1 {
-            let mut x_start = (x1 as f32 + (y as isize - y1 as isize) as f32 * inv_slope_1) as usize;
-            let mut x_end = (x0 as f32 + (y as isize - y0 as isize) as f32 * inv_slope_2) as usize;
-    
-            if x_end < x_start {
-                swap(&mut x_start, &mut x_end);
+
+    // only walk the rows that are inside of this tile
+    let y_first = y0.max(tile.y_start as i32);
+    let y_last = y2.min(tile.y_end as i32 - 1);
+
+    for y in y_first..=y_last {
+        let (x_start, x_end) = scanline_span(y, x0, y0, x1, y1, x2, y2);
+
+        for x in x_start.max(0)..x_end.min(tile.width as i32) {
+            let (x, y) = (x as usize, y as usize);
+            let weights = barycentric_weights(&point_a, &point_b, &point_c, &Vec2 { x: x as f32, y: y as f32 });
+
+            let interpolated_reciprocal_w = weights.x / w0 + weights.y / w1 + weights.z / w2;
+            let depth = 1.0 - interpolated_reciprocal_w;
+            if depth >= tile.depth_at(x, y) {
+                continue;
+            }
+
+            let u = uv0.u * weights.x + uv1.u * weights.y + uv2.u * weights.z;
+            let v = uv0.v * weights.x + uv1.v * weights.y + uv2.v * weights.z;
+            if let Some(color) = texel_color(texture, opacity, u, v) {
+                draw_blended_pixel(tile, x, y, color, depth, blend_mode);
+            }
+        }
+    }
+}
+
+// draws a triangle with the colour of each pixel worked out by `shade`, for
+// the debug render modes. it gets the perspective correct weights of the
+// three points in the order they were passed in, and the pixel's view space
+// depth. pixels are depth tested the same as draw_filled_triangle
+pub fn draw_shaded_triangle<F: FnMut(&Vec3, f32) -> Color>(tile: &mut FrameTile, points: &[Vec4; 3], mut shade: F) {
+    let [w0, w1, w2] = points.map(|point| point.w);
+    if tile.sample_offsets().len() > 1 {
+        rasterize_multisampled(tile, points, |tile, x, y, weights, coverage, depths| {
+            let interpolated_reciprocal_w = weights.x / w0 + weights.y / w1 + weights.z / w2;
+            let vertex_weights = Vec3 {
+                x: weights.x / w0 / interpolated_reciprocal_w,
+                y: weights.y / w1 / interpolated_reciprocal_w,
+                z: weights.z / w2 / interpolated_reciprocal_w,
+            };
+            let color = shade(&vertex_weights, 1.0 / interpolated_reciprocal_w);
+            tile.draw_samples(x, y, coverage, depths, color, BlendMode::Opaque);
+        });
+        return;
+    }
+
+    // sort the vertices by y, keeping track of where each one came from
+    let mut vertices = [0, 1, 2].map(|i| (points[i].x as i32, points[i].y as i32, points[i].w, i));
+    vertices.sort_by_key(|vertex| vertex.1);
+    let [(x0, y0, w0, _), (x1, y1, w1, _), (x2, y2, w2, _)] = vertices;
+
+    let point_a = Vec2 { x: x0 as f32, y: y0 as f32 };
+    let point_b = Vec2 { x: x1 as f32, y: y1 as f32 };
+    let point_c = Vec2 { x: x2 as f32, y: y2 as f32 };
+
+    // only walk the rows that are inside of this tile
+    let y_first = y0.max(tile.y_start as i32);
+    let y_last = y2.min(tile.y_end as i32 - 1);
+
+    for y in y_first..=y_last {
+        let (x_start, x_end) = scanline_span(y, x0, y0, x1, y1, x2, y2);
+
+        for x in x_start.max(0)..x_end.min(tile.width as i32) {
+            let (x, y) = (x as usize, y as usize);
+            let point_p = Vec2 { x: x as f32, y: y as f32 };
+            let weights = barycentric_weights(&point_a, &point_b, &point_c, &point_p);
+
+            let interpolated_reciprocal_w = weights.x / w0 + weights.y / w1 + weights.z / w2;
+            let depth = 1.0 - interpolated_reciprocal_w;
+            if depth >= tile.depth_at(x, y) {
+                continue;
+            }
+
+            // dividing each weight by its w and then by the interpolated 1/w
+            // undoes the perspective, so attributes come out as they would
+            // across the triangle in 3d
+            let sorted_weights = [
+                weights.x / w0 / interpolated_reciprocal_w,
+                weights.y / w1 / interpolated_reciprocal_w,
+                weights.z / w2 / interpolated_reciprocal_w,
+            ];
+            let mut vertex_weights = [0.0; 3];
+            for (vertex, weight) in vertices.iter().zip(sorted_weights) {
+                vertex_weights[vertex.3] = weight;
+            }
+            let vertex_weights = Vec3 { x: vertex_weights[0], y: vertex_weights[1], z: vertex_weights[2] };
+
+            let color = shade(&vertex_weights, 1.0 / interpolated_reciprocal_w);
+            tile.draw_pixel(x, y, color);
+            tile.set_depth(x, y, depth);
+        }
+    }
+}
+
+// walks a triangle for multisampling, calling `shade` once for every pixel
+// where the triangle covers at least one of the samples. it gets the screen
+// space weights of the points, in the order they were passed in, at the
+// middle of the covered samples so they never reach outside the triangle,
+// along with a bit for each covered sample and the depth at every sample.
+// the points aren't snapped to whole pixels, so coverage follows where the
+// edges really are. a sample exactly on an edge counts as covered
+fn rasterize_multisampled<F: FnMut(&mut FrameTile, usize, usize, &Vec3, u32, &[f32; MAX_SAMPLES])>(
+    tile: &mut FrameTile,
+    points: &[Vec4; 3],
+    mut shade: F) {
+    let [a, b, c] = points.map(|point| Vec2 { x: point.x, y: point.y });
+    let [w0, w1, w2] = points.map(|point| point.w);
+    let area = (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x);
+    // a point that went off to infinity or NaN would make every weight NaN
+    if area == 0.0 || !area.is_finite() {
+        return;
+    }
+
+    // the weights change by the same amount for every step across or down,
+    // so each sample's weights are the pixel's plus its offset times these
+    let origin = barycentric_weights(&a, &b, &c, &Vec2 { x: 0.0, y: 0.0 });
+    let step_x = barycentric_weights(&a, &b, &c, &Vec2 { x: 1.0, y: 0.0 }) - origin;
+    let step_y = barycentric_weights(&a, &b, &c, &Vec2 { x: 0.0, y: 1.0 }) - origin;
+    let reciprocal_w = Vec3 { x: 1.0 / w0, y: 1.0 / w1, z: 1.0 / w2 };
+    let sample_steps: Vec<Vec3> = tile.sample_offsets()
+        .iter()
+        .map(|&(offset_x, offset_y)| step_x * offset_x + step_y * offset_y)
+        .collect();
+
+    // the rows whose samples, up to half a pixel above or below, can reach
+    // the triangle
+    let min_y = a.y.min(b.y).min(c.y);
+    let max_y = a.y.max(b.y).max(c.y);
+    let y_first = ((min_y - 0.5).ceil() as i32).max(tile.y_start as i32);
+    let y_last = ((max_y + 0.5).floor() as i32).min(tile.y_end as i32 - 1);
+
+    let mut depths = [0.0; MAX_SAMPLES];
+    for y in y_first..=y_last {
+        let (x_start, x_end) = match band_span(y, &[a, b, c]) {
+            Some(span) => span,
+            None => continue,
+        };
+        for x in x_start.max(0)..=x_end.min(tile.width as i32 - 1) {
+            let pixel_weights = origin + step_x * x as f32 + step_y * y as f32;
+            let mut coverage: u32 = 0;
+            let mut centroid = Vec3 { x: 0.0, y: 0.0, z: 0.0 };
+            for (sample, sample_step) in sample_steps.iter().enumerate() {
+                let weights = pixel_weights + *sample_step;
+                if weights.x < 0.0 || weights.y < 0.0 || weights.z < 0.0 {
+                    continue;
+                }
+                coverage |= 1 << sample;
+                depths[sample] = 1.0 - vec3_dot(&weights, &reciprocal_w);
+                centroid += weights;
             }
-    
-            for x in x_start..x_end {
-                draw_texel(display, x, y, texture, texture_width, texture_height, &point_a, &point_b, &point_c, u0, v0, u1, v1, u2, v2);
+            if coverage == 0 {
+                continue;
             }
+
+            let weights = vec3_div(&centroid, coverage.count_ones() as f32);
+            shade(tile, x as usize, y as usize, &weights, coverage, &depths);
         }
     }
+}
 
-    // render the bottom part of the triangle
-    inv_slope_1 = 0.0;
-    inv_slope_2 = 0.0;
-
-    if y2 as isize - y1 as isize != 0 {
-        inv_slope_1 = (x2 as isize - x1 as isize) as f32 / ((y2 as isize - y1 as isize) as f32).abs();
-    }    
-    if y2 as isize - y0 as isize != 0 {
-        inv_slope_2 = (x2 as isize - x0 as isize) as f32 / ((y2 as isize - y0 as isize) as f32).abs();
-    }
-    
-    if y2 as isize - y1 as isize != 0 {
-        for y in y1..=y2 {
-            let mut x_start = (x1 as f32 + (y as isize - y1 as isize) as f32 * inv_slope_1) as usize;
-            let mut x_end = (x0 as f32 + (y as isize - y0 as isize) as f32 * inv_slope_2) as usize;
-    
-            if x_end < x_start {
-                swap(&mut x_start, &mut x_end);
+// the first and last column of row y that the triangle reaches into
+// anywhere within half a pixel above or below the row, which is as far as
+// the sample points go
+fn band_span(y: i32, points: &[Vec2; 3]) -> Option<(i32, i32)> {
+    let top = y as f32 - 0.5;
+    let bottom = y as f32 + 0.5;
+    let mut min_x = f32::INFINITY;
+    let mut max_x = f32::NEG_INFINITY;
+    for i in 0..3 {
+        let p = points[i];
+        let q = points[(i + 1) % 3];
+        if p.y >= top && p.y <= bottom {
+            min_x = min_x.min(p.x);
+            max_x = max_x.max(p.x);
+        }
+        if p.y == q.y {
+            continue;
+        }
+        for edge_y in [top, bottom] {
+            let t = (edge_y - p.y) / (q.y - p.y);
+            if (0.0..=1.0).contains(&t) {
+                let x = p.x + (q.x - p.x) * t;
+                min_x = min_x.min(x);
+                max_x = max_x.max(x);
             }
-    
-            for x in x_start..x_end {
-                draw_texel(display, x, y, texture, texture_width, texture_height, &point_a, &point_b, &point_c, u0, v0, u1, v1, u2, v2);
+        }
+    }
+    if min_x > max_x {
+        return None;
+    }
+    Some((min_x.floor() as i32, max_x.ceil() as i32))
+}
+
+// a line's pixels can land a pixel or so away from where the faces
+// around it work out their depth, so the faces in the depth pass are pushed
+// back by how much their depth changes over this many pixels. on faces seen
+// almost edge on that's a long way, and without it the silhouette of a
+// curved model would be broken up by the faces right next to it
+const DEPTH_SLOPE_PIXELS: f32 = 1.5;
+
+// writes the triangle into the depth buffer without drawing any of it, so
+// the wireframe modes can test their lines against the faces. when
+// multisampling each sample the triangle covers gets its own depth
+pub fn draw_depth_triangle(tile: &mut FrameTile, points: &[Vec4; 3]) {
+    // 1/w is a flat plane across the screen, so its slope is the same all
+    // over the triangle
+    let [a, b, c] = points;
+    let (ab, ac) = (Vec2 { x: b.x - a.x, y: b.y - a.y }, Vec2 { x: c.x - a.x, y: c.y - a.y });
+    let (ab_w, ac_w) = (1.0 / b.w - 1.0 / a.w, 1.0 / c.w - 1.0 / a.w);
+    let area = ab.x * ac.y - ac.x * ab.y;
+    let offset = if area != 0.0 {
+        let slope_x = (ab_w * ac.y - ac_w * ab.y) / area;
+        let slope_y = (ac_w * ab.x - ab_w * ac.x) / area;
+        (slope_x.abs() + slope_y.abs()) * DEPTH_SLOPE_PIXELS
+    } else {
+        0.0
+    };
+
+    if tile.sample_offsets().len() > 1 {
+        rasterize_multisampled(tile, points, |tile, x, y, _, coverage, depths| {
+            tile.draw_sample_depths(x, y, coverage, &depths.map(|depth| depth + offset));
+        });
+        return;
+    }
+
+    let mut vertices = points.map(|point| (point.x as i32, point.y as i32, point.w));
+    vertices.sort_by_key(|vertex| vertex.1);
+    let [(x0, y0, w0), (x1, y1, w1), (x2, y2, w2)] = vertices;
+
+    let point_a = Vec2 { x: x0 as f32, y: y0 as f32 };
+    let point_b = Vec2 { x: x1 as f32, y: y1 as f32 };
+    let point_c = Vec2 { x: x2 as f32, y: y2 as f32 };
+
+    let y_first = y0.max(tile.y_start as i32);
+    let y_last = y2.min(tile.y_end as i32 - 1);
+
+    for y in y_first..=y_last {
+        let (x_start, x_end) = scanline_span(y, x0, y0, x1, y1, x2, y2);
+
+        for x in x_start.max(0)..x_end.min(tile.width as i32) {
+            let (x, y) = (x as usize, y as usize);
+            let weights = barycentric_weights(&point_a, &point_b, &point_c, &Vec2 { x: x as f32, y: y as f32 });
+            let depth = 1.0 - (weights.x / w0 + weights.y / w1 + weights.z / w2) + offset;
+            if depth < tile.depth_at(x, y) {
+                tile.set_depth(x, y, depth);
             }
         }
     }
 }
 
+// the colours of the overdraw heat map, from one write up to five or more
+const OVERDRAW_COLORS: [(u8, u8, u8); 5] = [
+    (20, 40, 160),
+    (20, 160, 60),
+    (220, 220, 30),
+    (240, 130, 20),
+    (230, 30, 30),
+];
+
+// counts every pixel the triangle covers without any depth test and colours
+// it by how many triangles have covered it so far. the count is kept in the
+// depth buffer, which starts at 1.0 when cleared, since nothing else reads
+// it while drawing the heat map. it's a count of whole pixels, so when
+// multisampling a pixel counts once the triangle covers any of its samples
+// and the count goes in all of them
+pub fn draw_overdraw_triangle(tile: &mut FrameTile, points: &[Vec4; 3]) {
+    if tile.sample_offsets().len() > 1 {
+        rasterize_multisampled(tile, points, |tile, x, y, _, _, _| draw_overdraw_pixel(tile, x, y));
+        return;
+    }
+
+    let mut vertices = points.map(|point| (point.x as i32, point.y as i32));
+    vertices.sort_by_key(|vertex| vertex.1);
+    let [(x0, y0), (x1, y1), (x2, y2)] = vertices;
+
+    let y_first = y0.max(tile.y_start as i32);
+    let y_last = y2.min(tile.y_end as i32 - 1);
+
+    for y in y_first..=y_last {
+        let (x_start, x_end) = scanline_span(y, x0, y0, x1, y1, x2, y2);
+
+        for x in x_start.max(0)..x_end.min(tile.width as i32) {
+            draw_overdraw_pixel(tile, x as usize, y as usize);
+        }
+    }
+}
+
+fn draw_overdraw_pixel(tile: &mut FrameTile, x: usize, y: usize) {
+    // the stored value is one more than the writes before this one, which
+    // makes it the count including this write
+    let count = tile.depth_at(x, y);
+    tile.set_depth(x, y, count + 1.0);
+
+    let (r, g, b) = OVERDRAW_COLORS[(count as usize).clamp(1, OVERDRAW_COLORS.len()) - 1];
+    tile.draw_pixel(x, y, Color::RGBA(r, g, b, 255));
+}
+
 pub fn barycentric_weights(a: &Vec2, b: &Vec2, c: &Vec2, p: &Vec2) -> Vec3 {
-    let ac = vec2_sub(c, a);
-    let ab = vec2_sub(b, a);
-    let pc = vec2_sub(c, p);
-    let pb = vec2_sub(b, p);
-    let ap = vec2_sub(p, a);
+    let ac = *c - *a;
+    let ab = *b - *a;
+    let pc = *c - *p;
+    let pb = *b - *p;
+    let ap = *p - *a;
 
     let area_parallelogram_abc = ac.x * ab.y - ac.y * ab.x;
     let alpha = (pc.x * pb.y - pc.y * pb.x) / area_parallelogram_abc;
@@ -1,17 +1,19 @@
-#[derive(Debug, Copy, Clone)]
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
 pub struct Vec2 {
     pub x: f32,
     pub y: f32,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
 pub struct Vec3 {
     pub x: f32,
     pub y: f32,
     pub z: f32,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
 pub struct Vec4 {
     pub x: f32,
     pub y: f32,
@@ -116,6 +118,9 @@ pub fn vec3_dot(a: &Vec3, b: &Vec3) -> f32 {
     return a.x * b.x + a.y * b.y + a.z * b.z;
 }
 
+// the vec3_rotate functions turn counterclockwise when looking down the axis
+// towards the origin, the same as mat4_make_rotation_x/y/z and
+// quat_from_axis_angle, so a vector comes out the same whichever one is used
 pub fn vec3_rotate_z(v: &Vec3, angle: f32) -> Vec3 {
     Vec3 {
         x: v.x * angle.cos() - v.y * angle.sin(),
@@ -126,9 +131,9 @@ pub fn vec3_rotate_z(v: &Vec3, angle: f32) -> Vec3 {
 
 pub fn vec3_rotate_y(v: &Vec3, angle: f32) -> Vec3 {
     Vec3 {
-        x: v.x * angle.cos() - v.z * angle.sin(),
+        x: v.x * angle.cos() + v.z * angle.sin(),
         y: v.y,
-        z: v.x * angle.sin() + v.z * angle.cos(),
+        z: -v.x * angle.sin() + v.z * angle.cos(),
     }
 }
 
@@ -155,4 +160,236 @@ pub fn vec3_from_vec4(v: &Vec4) -> Vec3 {
         y: v.y,
         z: v.z
     }
-}
\ No newline at end of file
+}
+
+pub fn vec2_normalize_or_zero(v: &Vec2) -> Vec2 {
+    let length = vec2_length(v);
+    if length == 0.0 || !length.is_finite() {
+        return Vec2 { x: 0.0, y: 0.0 };
+    }
+    vec2_div(v, length)
+}
+
+// same as vec3_normalize, but returns a zero vector instead of dividing by
+// zero, e.g. for the normal of a degenerate face that has no area
+pub fn vec3_normalize_or_zero(v: &Vec3) -> Vec3 {
+    let length = vec3_length(v);
+    if length == 0.0 || !length.is_finite() {
+        return Vec3 { x: 0.0, y: 0.0, z: 0.0 };
+    }
+    vec3_div(v, length)
+}
+
+pub fn vec2_lerp(a: &Vec2, b: &Vec2, t: f32) -> Vec2 {
+    *a + (*b - *a) * t
+}
+
+pub fn vec3_lerp(a: &Vec3, b: &Vec3, t: f32) -> Vec3 {
+    *a + (*b - *a) * t
+}
+
+pub fn vec4_lerp(a: &Vec4, b: &Vec4, t: f32) -> Vec4 {
+    *a + (*b - *a) * t
+}
+
+pub fn vec2_distance(a: &Vec2, b: &Vec2) -> f32 {
+    vec2_length(&(*b - *a))
+}
+
+pub fn vec3_distance(a: &Vec3, b: &Vec3) -> f32 {
+    vec3_length(&(*b - *a))
+}
+
+// reflect the incoming direction off a surface with the given unit normal
+pub fn vec3_reflect(incident: &Vec3, normal: &Vec3) -> Vec3 {
+    *incident - *normal * (2.0 * vec3_dot(incident, normal))
+}
+
+pub fn vec2_min(a: &Vec2, b: &Vec2) -> Vec2 {
+    Vec2 { x: a.x.min(b.x), y: a.y.min(b.y) }
+}
+
+pub fn vec2_max(a: &Vec2, b: &Vec2) -> Vec2 {
+    Vec2 { x: a.x.max(b.x), y: a.y.max(b.y) }
+}
+
+pub fn vec3_min(a: &Vec3, b: &Vec3) -> Vec3 {
+    Vec3 { x: a.x.min(b.x), y: a.y.min(b.y), z: a.z.min(b.z) }
+}
+
+pub fn vec3_max(a: &Vec3, b: &Vec3) -> Vec3 {
+    Vec3 { x: a.x.max(b.x), y: a.y.max(b.y), z: a.z.max(b.z) }
+}
+
+pub fn vec4_min(a: &Vec4, b: &Vec4) -> Vec4 {
+    Vec4 { x: a.x.min(b.x), y: a.y.min(b.y), z: a.z.min(b.z), w: a.w.min(b.w) }
+}
+
+pub fn vec4_max(a: &Vec4, b: &Vec4) -> Vec4 {
+    Vec4 { x: a.x.max(b.x), y: a.y.max(b.y), z: a.z.max(b.z), w: a.w.max(b.w) }
+}
+
+pub fn vec2_from_vec3(v: &Vec3) -> Vec2 {
+    Vec2 {
+        x: v.x,
+        y: v.y
+    }
+}
+
+pub fn vec2_from_vec4(v: &Vec4) -> Vec2 {
+    Vec2 {
+        x: v.x,
+        y: v.y
+    }
+}
+
+// the operator versions of the functions above, so vector math can be written
+// as `b - a` instead of `vec3_sub(&b, &a)`. multiplying or dividing two
+// vectors works component by component
+macro_rules! impl_vector_ops {
+    ($vec:ident { $($field:ident),+ }) => {
+        impl Add for $vec {
+            type Output = $vec;
+            fn add(self, other: $vec) -> $vec {
+                $vec { $($field: self.$field + other.$field),+ }
+            }
+        }
+
+        impl Sub for $vec {
+            type Output = $vec;
+            fn sub(self, other: $vec) -> $vec {
+                $vec { $($field: self.$field - other.$field),+ }
+            }
+        }
+
+        impl Mul for $vec {
+            type Output = $vec;
+            fn mul(self, other: $vec) -> $vec {
+                $vec { $($field: self.$field * other.$field),+ }
+            }
+        }
+
+        impl Div for $vec {
+            type Output = $vec;
+            fn div(self, other: $vec) -> $vec {
+                $vec { $($field: self.$field / other.$field),+ }
+            }
+        }
+
+        impl Mul<f32> for $vec {
+            type Output = $vec;
+            fn mul(self, factor: f32) -> $vec {
+                $vec { $($field: self.$field * factor),+ }
+            }
+        }
+
+        impl Mul<$vec> for f32 {
+            type Output = $vec;
+            fn mul(self, v: $vec) -> $vec {
+                $vec { $($field: self * v.$field),+ }
+            }
+        }
+
+        impl Div<f32> for $vec {
+            type Output = $vec;
+            fn div(self, factor: f32) -> $vec {
+                $vec { $($field: self.$field / factor),+ }
+            }
+        }
+
+        impl Neg for $vec {
+            type Output = $vec;
+            fn neg(self) -> $vec {
+                $vec { $($field: -self.$field),+ }
+            }
+        }
+
+        impl AddAssign for $vec {
+            fn add_assign(&mut self, other: $vec) {
+                $(self.$field += other.$field;)+
+            }
+        }
+
+        impl SubAssign for $vec {
+            fn sub_assign(&mut self, other: $vec) {
+                $(self.$field -= other.$field;)+
+            }
+        }
+
+        impl MulAssign<f32> for $vec {
+            fn mul_assign(&mut self, factor: f32) {
+                $(self.$field *= factor;)+
+            }
+        }
+
+        impl DivAssign<f32> for $vec {
+            fn div_assign(&mut self, factor: f32) {
+                $(self.$field /= factor;)+
+            }
+        }
+    };
+}
+
+impl_vector_ops!(Vec2 { x, y });
+impl_vector_ops!(Vec3 { x, y, z });
+impl_vector_ops!(Vec4 { x, y, z, w });
+
+impl From<Vec3> for Vec4 {
+    fn from(v: Vec3) -> Vec4 {
+        vec4_from_vec3(&v)
+    }
+}
+
+impl From<Vec4> for Vec3 {
+    fn from(v: Vec4) -> Vec3 {
+        vec3_from_vec4(&v)
+    }
+}
+
+impl From<Vec3> for Vec2 {
+    fn from(v: Vec3) -> Vec2 {
+        vec2_from_vec3(&v)
+    }
+}
+
+impl From<Vec4> for Vec2 {
+    fn from(v: Vec4) -> Vec2 {
+        vec2_from_vec4(&v)
+    }
+}
+
+impl From<[f32; 2]> for Vec2 {
+    fn from(a: [f32; 2]) -> Vec2 {
+        Vec2 { x: a[0], y: a[1] }
+    }
+}
+
+impl From<[f32; 3]> for Vec3 {
+    fn from(a: [f32; 3]) -> Vec3 {
+        Vec3 { x: a[0], y: a[1], z: a[2] }
+    }
+}
+
+impl From<[f32; 4]> for Vec4 {
+    fn from(a: [f32; 4]) -> Vec4 {
+        Vec4 { x: a[0], y: a[1], z: a[2], w: a[3] }
+    }
+}
+
+impl From<Vec2> for [f32; 2] {
+    fn from(v: Vec2) -> [f32; 2] {
+        [v.x, v.y]
+    }
+}
+
+impl From<Vec3> for [f32; 3] {
+    fn from(v: Vec3) -> [f32; 3] {
+        [v.x, v.y, v.z]
+    }
+}
+
+impl From<Vec4> for [f32; 4] {
+    fn from(v: Vec4) -> [f32; 4] {
+        [v.x, v.y, v.z, v.w]
+    }
+}
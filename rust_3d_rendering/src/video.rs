@@ -0,0 +1,371 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+// records frames straight into a video file any player or encoder can read,
+// without needing an encoder of its own. a .y4m file is a YUV4MPEG2 stream of
+// raw 4:2:0 frames, which is what ffmpeg and most encoders take in, and an
+// .avi file holds uncompressed 24 bit frames that most players can open. both
+// are big, about 1.5 and 3 bytes a pixel for every frame
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum VideoFormat {
+    Y4m,
+    Avi,
+}
+
+// also the file extensions
+pub const VIDEO_FORMAT_NAMES: [(&str, VideoFormat); 2] = [
+    ("y4m", VideoFormat::Y4m),
+    ("avi", VideoFormat::Avi),
+];
+
+pub fn video_format_from_name(name: &str) -> Option<VideoFormat> {
+    VIDEO_FORMAT_NAMES.iter().find(|(n, _)| *n == name).map(|(_, format)| *format)
+}
+
+pub fn video_format_name(format: VideoFormat) -> &'static str {
+    VIDEO_FORMAT_NAMES.iter().find(|(_, f)| *f == format).map(|(name, _)| *name).unwrap()
+}
+
+// the format that goes with the path's extension
+pub fn video_format_from_path(path: &Path) -> Option<VideoFormat> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    video_format_from_name(&extension)
+}
+
+pub struct VideoRecorder {
+    writer: BufWriter<File>,
+    pub path: String,
+    format: VideoFormat,
+    pub width: usize,
+    pub height: usize,
+    pub fps: f32,
+    pub frames_written: usize,
+    // the frame converted to the file's layout, kept around so it isn't
+    // allocated again for every frame
+    frame: Vec<u8>,
+    // how many bytes of the avi's movi list are written so far, and where
+    // each frame chunk starts in it for the index
+    avi_movi_size: u64,
+    avi_chunk_offsets: Vec<u32>,
+}
+
+// where the avi's headers have the numbers that are only known at the end
+const AVI_RIFF_SIZE_OFFSET: u64 = 4;
+const AVI_TOTAL_FRAMES_OFFSET: u64 = 48;
+const AVI_STREAM_LENGTH_OFFSET: u64 = 140;
+const AVI_MOVI_SIZE_OFFSET: u64 = 216;
+const AVI_HEADER_SIZE: u64 = 224;
+
+// marks every frame as a key frame in the avi's index
+const AVI_KEYFRAME: u32 = 0x10;
+const AVI_HAS_INDEX: u32 = 0x10;
+
+// starts a video of the given size at `fps` frames a second, in the format
+// that goes with the path's extension
+pub fn video_recorder_new(path: &Path, width: usize, height: usize, fps: f32) -> Result<VideoRecorder, String> {
+    let format = match video_format_from_path(path) {
+        Some(format) => format,
+        None => {
+            let names: Vec<&str> = VIDEO_FORMAT_NAMES.iter().map(|(name, _)| *name).collect();
+            return Err(format!(
+                "can't tell what kind of video '{}' is, it should end in one of: .{}", path.display(), names.join(", .")));
+        }
+    };
+    if width == 0 || height == 0 {
+        return Err(format!("a video can't be {}x{}", width, height));
+    }
+    if !(fps > 0.0 && fps.is_finite()) {
+        return Err(format!("a video's frame rate must be above 0, got {}", fps));
+    }
+    let file = match File::create(path) {
+        Ok(file) => file,
+        Err(e) => return Err(format!("Failed to create video '{}': {}", path.display(), e)),
+    };
+    let mut recorder = VideoRecorder {
+        writer: BufWriter::new(file),
+        path: path.display().to_string(),
+        format,
+        width,
+        height,
+        fps,
+        frames_written: 0,
+        frame: Vec::new(),
+        avi_movi_size: 4,
+        avi_chunk_offsets: Vec::new(),
+    };
+    let header = match format {
+        VideoFormat::Y4m => y4m_header(width, height, fps),
+        VideoFormat::Avi => avi_header(width, height, fps),
+    };
+    write_bytes(&mut recorder, &header)?;
+    Ok(recorder)
+}
+
+// adds a frame of rgba pixels that's shown `time` seconds into the video.
+// it's written as many times as it takes to catch the video up to that
+// time, so frames that come slower than the video's frame rate are held for
+// longer and ones that come faster are skipped. frames `1 / fps` seconds
+// apart are each written once
+pub fn video_recorder_add_frame(recorder: &mut VideoRecorder, rgba: &[u8], time: f32) -> Result<(), String> {
+    if rgba.len() != recorder.width * recorder.height * 4 {
+        return Err(format!(
+            "a {}x{} video can't take a frame of {} bytes", recorder.width, recorder.height, rgba.len()));
+    }
+    let frames_due = (time * recorder.fps).round().max(0.0) as usize + 1;
+    if frames_due <= recorder.frames_written {
+        return Ok(());
+    }
+    video_recorder_convert_frame(recorder, rgba);
+    for _ in recorder.frames_written..frames_due {
+        video_recorder_write_frame(recorder)?;
+    }
+    Ok(())
+}
+
+// writes the index and fills in the frame counts, which the avi needs to be
+// readable. a y4m is fine without it, but it's flushed all the same
+pub fn video_recorder_finish(mut recorder: VideoRecorder) -> Result<(), String> {
+    if recorder.format == VideoFormat::Avi {
+        let mut index = Vec::with_capacity(8 + recorder.avi_chunk_offsets.len() * 16);
+        index.extend_from_slice(b"idx1");
+        push_u32(&mut index, (recorder.avi_chunk_offsets.len() * 16) as u32);
+        let frame_size = recorder.frame.len() as u32;
+        for &offset in &recorder.avi_chunk_offsets {
+            index.extend_from_slice(b"00db");
+            push_u32(&mut index, AVI_KEYFRAME);
+            push_u32(&mut index, offset);
+            push_u32(&mut index, frame_size);
+        }
+        write_bytes(&mut recorder, &index)?;
+
+        let riff_size = avi_riff_size(recorder.avi_movi_size, recorder.avi_chunk_offsets.len());
+        if riff_size > u32::MAX as u64 {
+            return Err(format!("video '{}' is too big for an avi, record a .y4m instead", recorder.path));
+        }
+        let frames = recorder.frames_written as u32;
+        let patches = [
+            (AVI_RIFF_SIZE_OFFSET, riff_size as u32),
+            (AVI_TOTAL_FRAMES_OFFSET, frames),
+            (AVI_STREAM_LENGTH_OFFSET, frames),
+            (AVI_MOVI_SIZE_OFFSET, recorder.avi_movi_size as u32),
+        ];
+        for (offset, value) in patches {
+            let result = recorder.writer.seek(SeekFrom::Start(offset))
+                .and_then(|_| recorder.writer.write_all(&value.to_le_bytes()));
+            if let Err(e) = result {
+                return Err(format!("Failed to write video '{}': {}", recorder.path, e));
+            }
+        }
+    }
+    match recorder.writer.flush() {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("Failed to write video '{}': {}", recorder.path, e)),
+    }
+}
+
+// turns the rgba pixels into the layout the file stores a frame in
+fn video_recorder_convert_frame(recorder: &mut VideoRecorder, rgba: &[u8]) {
+    let (width, height) = (recorder.width, recorder.height);
+    let frame = &mut recorder.frame;
+    frame.clear();
+    match recorder.format {
+        // the whole luma plane, then the blue and red difference planes at
+        // half the width and height, each sample the average of 2x2 pixels
+        VideoFormat::Y4m => {
+            for pixel in rgba.chunks_exact(4) {
+                frame.push(rgb_to_y(pixel[0] as f32, pixel[1] as f32, pixel[2] as f32));
+            }
+            let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+            let mut cr = Vec::with_capacity(chroma_width * chroma_height);
+            for chroma_y in 0..chroma_height {
+                for chroma_x in 0..chroma_width {
+                    let (mut r, mut g, mut b, mut count) = (0.0, 0.0, 0.0, 0.0);
+                    for y in chroma_y * 2..(chroma_y * 2 + 2).min(height) {
+                        for x in chroma_x * 2..(chroma_x * 2 + 2).min(width) {
+                            let i = (y * width + x) * 4;
+                            r += rgba[i] as f32;
+                            g += rgba[i + 1] as f32;
+                            b += rgba[i + 2] as f32;
+                            count += 1.0;
+                        }
+                    }
+                    let (cb_value, cr_value) = rgb_to_cb_cr(r / count, g / count, b / count);
+                    frame.push(cb_value);
+                    cr.push(cr_value);
+                }
+            }
+            frame.extend_from_slice(&cr);
+        },
+        // bgr rows from the bottom up, each padded to a multiple of 4 bytes
+        VideoFormat::Avi => {
+            let padding = avi_row_size(width) - width * 3;
+            for y in (0..height).rev() {
+                for pixel in rgba[y * width * 4..(y + 1) * width * 4].chunks_exact(4) {
+                    frame.extend_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+                }
+                frame.extend(std::iter::repeat_n(0, padding));
+            }
+        },
+    }
+}
+
+fn video_recorder_write_frame(recorder: &mut VideoRecorder) -> Result<(), String> {
+    let frame = std::mem::take(&mut recorder.frame);
+    let result = match recorder.format {
+        VideoFormat::Y4m => {
+            write_bytes(recorder, b"FRAME\n").and_then(|_| write_bytes(recorder, &frame))
+        },
+        VideoFormat::Avi => {
+            // leaving room for this frame's entry in the index written at the end
+            let chunk_size = 8 + frame.len() as u64;
+            let chunk_count = recorder.avi_chunk_offsets.len() + 1;
+            if avi_riff_size(recorder.avi_movi_size + chunk_size, chunk_count) > u32::MAX as u64 {
+                Err(format!("video '{}' is too big for an avi, record a .y4m instead", recorder.path))
+            } else {
+                // the index counts from the start of the movi list's type
+                recorder.avi_chunk_offsets.push(recorder.avi_movi_size as u32);
+                recorder.avi_movi_size += chunk_size;
+                let mut chunk_header = Vec::with_capacity(8);
+                chunk_header.extend_from_slice(b"00db");
+                push_u32(&mut chunk_header, frame.len() as u32);
+                write_bytes(recorder, &chunk_header).and_then(|_| write_bytes(recorder, &frame))
+            }
+        },
+    };
+    recorder.frame = frame;
+    if result.is_ok() {
+        recorder.frames_written += 1;
+    }
+    result
+}
+
+fn write_bytes(recorder: &mut VideoRecorder, bytes: &[u8]) -> Result<(), String> {
+    match recorder.writer.write_all(bytes) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("Failed to write video '{}': {}", recorder.path, e)),
+    }
+}
+
+fn y4m_header(width: usize, height: usize, fps: f32) -> Vec<u8> {
+    let (rate, scale) = frame_rate_fraction(fps);
+    format!("YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C420jpeg\n", width, height, rate, scale).into_bytes()
+}
+
+// a RIFF file with one uncompressed video stream. the sizes and frame counts
+// are left as 0 until video_recorder_finish
+fn avi_header(width: usize, height: usize, fps: f32) -> Vec<u8> {
+    let (rate, scale) = frame_rate_fraction(fps);
+    let frame_size = (avi_row_size(width) * height) as u32;
+    let mut header = Vec::with_capacity(AVI_HEADER_SIZE as usize);
+
+    header.extend_from_slice(b"RIFF");
+    push_u32(&mut header, 0);
+    header.extend_from_slice(b"AVI ");
+
+    header.extend_from_slice(b"LIST");
+    push_u32(&mut header, 192);
+    header.extend_from_slice(b"hdrl");
+
+    header.extend_from_slice(b"avih");
+    push_u32(&mut header, 56);
+    push_u32(&mut header, (1_000_000.0 / fps).round() as u32);
+    push_u32(&mut header, (frame_size as f32 * fps).min(u32::MAX as f32) as u32);
+    push_u32(&mut header, 0);
+    push_u32(&mut header, AVI_HAS_INDEX);
+    push_u32(&mut header, 0); // total frames
+    push_u32(&mut header, 0);
+    push_u32(&mut header, 1); // streams
+    push_u32(&mut header, frame_size);
+    push_u32(&mut header, width as u32);
+    push_u32(&mut header, height as u32);
+    header.extend_from_slice(&[0; 16]);
+
+    header.extend_from_slice(b"LIST");
+    push_u32(&mut header, 116);
+    header.extend_from_slice(b"strl");
+
+    header.extend_from_slice(b"strh");
+    push_u32(&mut header, 56);
+    header.extend_from_slice(b"vids");
+    header.extend_from_slice(b"DIB ");
+    push_u32(&mut header, 0); // flags
+    push_u32(&mut header, 0); // priority and language
+    push_u32(&mut header, 0); // initial frames
+    push_u32(&mut header, scale);
+    push_u32(&mut header, rate);
+    push_u32(&mut header, 0); // start
+    push_u32(&mut header, 0); // length in frames
+    push_u32(&mut header, frame_size);
+    push_u32(&mut header, u32::MAX); // default quality
+    push_u32(&mut header, frame_size);
+    header.extend_from_slice(&[0, 0, 0, 0]);
+    header.extend_from_slice(&(width as u16).to_le_bytes());
+    header.extend_from_slice(&(height as u16).to_le_bytes());
+
+    // a BITMAPINFOHEADER, with a positive height for bottom up rows
+    header.extend_from_slice(b"strf");
+    push_u32(&mut header, 40);
+    push_u32(&mut header, 40);
+    push_u32(&mut header, width as u32);
+    push_u32(&mut header, height as u32);
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&24u16.to_le_bytes());
+    push_u32(&mut header, 0); // uncompressed
+    push_u32(&mut header, frame_size);
+    header.extend_from_slice(&[0; 16]);
+
+    header.extend_from_slice(b"LIST");
+    push_u32(&mut header, 0);
+    header.extend_from_slice(b"movi");
+
+    debug_assert_eq!(header.len() as u64, AVI_HEADER_SIZE);
+    header
+}
+
+// the size the avi's riff header gives once the index is written, which is
+// everything after the first 8 bytes of the file. it has to fit in a u32
+pub fn avi_riff_size(movi_size: u64, chunk_count: usize) -> u64 {
+    let index_size = 8 + chunk_count as u64 * 16;
+    AVI_HEADER_SIZE - 8 + (movi_size - 4) + index_size
+}
+
+fn avi_row_size(width: usize) -> usize {
+    (width * 3).div_ceil(4) * 4
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+// the frame rate as a whole number of frames over a whole number of
+// seconds, to a thousandth of a frame, e.g. 29.97 is 2997 / 100
+pub fn frame_rate_fraction(fps: f32) -> (u32, u32) {
+    let mut rate = (fps as f64 * 1000.0).round().max(1.0) as u32;
+    let mut scale = 1000;
+    let divisor = greatest_common_divisor(rate, scale);
+    rate /= divisor;
+    scale /= divisor;
+    (rate, scale)
+}
+
+fn greatest_common_divisor(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+// BT.601 studio range, which is what players assume a y4m is in
+fn rgb_to_y(r: f32, g: f32, b: f32) -> u8 {
+    (16.0 + 0.257 * r + 0.504 * g + 0.098 * b).round() as u8
+}
+
+fn rgb_to_cb_cr(r: f32, g: f32, b: f32) -> (u8, u8) {
+    let cb = 128.0 - 0.148 * r - 0.291 * g + 0.439 * b;
+    let cr = 128.0 + 0.439 * r - 0.368 * g - 0.071 * b;
+    (cb.round() as u8, cr.round() as u8)
+}
@@ -0,0 +1,170 @@
+use rust_3d_rendering::{
+    antialias::{
+        ANTI_ALIASING_NAMES, AntiAliasing, MAX_SAMPLE_BUFFER_BYTES, anti_aliasing_from_name, anti_aliasing_name,
+        anti_aliasing_sample_count, downsample_box, downsample_multisampled, sample_offsets,
+    },
+    display::Display,
+    material::BlendMode,
+    triangle::draw_filled_triangle,
+    vector::Vec4,
+};
+use sdl2::pixels::Color;
+
+const WHITE: Color = Color::RGBA(255, 255, 255, 255);
+
+fn points(corners: [(f32, f32); 3], w: f32) -> [Vec4; 3] {
+    corners.map(|(x, y)| Vec4 { x, y, z: 0.0, w })
+}
+
+#[test]
+fn names_round_trip() {
+    for (name, anti_aliasing) in ANTI_ALIASING_NAMES {
+        assert_eq!(anti_aliasing_from_name(name), Some(anti_aliasing));
+        assert_eq!(anti_aliasing_name(anti_aliasing), name);
+    }
+    assert_eq!(anti_aliasing_from_name("msaa16"), None);
+}
+
+// every pattern stays inside its pixel, and no two samples share a row or a
+// column so nearly straight edges still get a sample at every step
+#[test]
+fn sample_patterns_are_rotated_grids() {
+    for count in [2, 4, 8] {
+        let offsets = sample_offsets(count);
+        assert_eq!(offsets.len(), count);
+        for (i, a) in offsets.iter().enumerate() {
+            assert!(a.0.abs() < 0.5 && a.1.abs() < 0.5);
+            for b in &offsets[i + 1..] {
+                assert!(a.0 != b.0 && a.1 != b.1, "{:?} and {:?} line up", a, b);
+            }
+        }
+    }
+    assert_eq!(sample_offsets(1), &[(0.0, 0.0)]);
+}
+
+#[test]
+fn downsampling_averages_samples() {
+    // a 2 x 1 screen supersampled 2 times, the left pixel half white
+    let mut samples = vec![0u8; 4 * 2 * 4];
+    for index in [0, 1] {
+        samples[index * 4..index * 4 + 4].copy_from_slice(&[255, 255, 255, 255]);
+    }
+    let mut pixels = vec![0u8; 2 * 4];
+    downsample_box(&samples, &mut pixels, 2, 2);
+    assert_eq!(pixels, [128, 128, 128, 128, 0, 0, 0, 0]);
+
+    // the same with the samples of each pixel one after another
+    let mut samples = vec![0u8; 2 * 4 * 4];
+    samples[4..8].copy_from_slice(&[200, 100, 0, 255]);
+    let mut pixels = vec![0u8; 2 * 4];
+    downsample_multisampled(&samples, &mut pixels, 4);
+    assert_eq!(pixels, [50, 25, 0, 64, 0, 0, 0, 0]);
+}
+
+// the shades of grey a white triangle on black leaves along its edges
+fn edge_shades(anti_aliasing: AntiAliasing) -> Vec<u8> {
+    let mut display = Display::new_headless(32, 32);
+    display.set_anti_aliasing(anti_aliasing).unwrap();
+    display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+    display.clear_depth_buffer();
+    let mut tile = display.full_frame();
+    // the samples are stored at the supersampled size
+    let scale = match anti_aliasing {
+        AntiAliasing::Supersample(factor) => factor as i32,
+        _ => 1,
+    };
+    let point = |x: f32| x * scale as f32;
+    let corners = [(point(2.0), point(3.0)), (point(29.0), point(9.0)), (point(7.0), point(28.0))];
+    draw_filled_triangle(&mut tile, &points(corners, 5.0), WHITE, BlendMode::Opaque);
+    display.resolve_samples();
+
+    let mut shades: Vec<u8> = display.color_buffer_rgba().chunks_exact(4).map(|pixel| pixel[0]).collect();
+    shades.sort();
+    shades.dedup();
+    shades
+}
+
+#[test]
+fn edges_get_in_between_shades() {
+    assert_eq!(edge_shades(AntiAliasing::Off), [0, 255]);
+    for anti_aliasing in [AntiAliasing::Multisample(4), AntiAliasing::Multisample(8), AntiAliasing::Supersample(3)] {
+        let shades = edge_shades(anti_aliasing);
+        assert!(shades.len() > 3, "{:?} only gave {:?}", anti_aliasing, shades);
+        assert_eq!(shades.first(), Some(&0));
+        assert_eq!(shades.last(), Some(&255));
+    }
+}
+
+// with multisampling a triangle behind another one still shows through
+// where the front one only covers some of a pixel's samples
+#[test]
+fn multisampled_depth_is_tested_per_sample() {
+    let red = Color::RGBA(255, 0, 0, 255);
+    let mut display = Display::new_headless(8, 8);
+    display.set_anti_aliasing(AntiAliasing::Multisample(4)).unwrap();
+    display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+    display.clear_depth_buffer();
+    let mut tile = display.full_frame();
+    // a red square behind, and a white triangle in front cutting it diagonally
+    draw_filled_triangle(&mut tile, &points([(-4.0, -4.0), (12.0, -4.0), (12.0, 12.0)], 9.0), red, BlendMode::Opaque);
+    draw_filled_triangle(&mut tile, &points([(-4.0, -4.0), (12.0, 12.0), (-4.0, 12.0)], 9.0), red, BlendMode::Opaque);
+    draw_filled_triangle(&mut tile, &points([(0.0, 0.0), (8.0, 8.0), (0.0, 8.0)], 2.0), WHITE, BlendMode::Opaque);
+    display.resolve_samples();
+
+    let rgba = display.color_buffer_rgba();
+    let pixel = |x: usize, y: usize| &rgba[(y * 8 + x) * 4..(y * 8 + x) * 4 + 3];
+    assert_eq!(pixel(1, 6), [255, 255, 255]);
+    assert_eq!(pixel(6, 1), [255, 0, 0]);
+    // on the diagonal it's part white and part red, never black
+    let [r, g, b] = [pixel(4, 4)[0], pixel(4, 4)[1], pixel(4, 4)[2]];
+    assert_eq!(r, 255);
+    assert!(g > 0 && g < 255 && g == b, "got {} {} {}", r, g, b);
+}
+
+fn shifted_triangle(anti_aliasing: AntiAliasing, shift: f32) -> Vec<u8> {
+    let mut display = Display::new_headless(16, 16);
+    display.set_anti_aliasing(anti_aliasing).unwrap();
+    display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+    display.clear_depth_buffer();
+    let mut tile = display.full_frame();
+    let corners = [(2.0 + shift, 1.0), (14.0 + shift, 5.0), (4.0 + shift, 15.0)];
+    draw_filled_triangle(&mut tile, &points(corners, 5.0), WHITE, BlendMode::Opaque);
+    display.resolve_samples();
+    display.color_buffer_rgba()
+}
+
+// multisampling covers the samples by where the edges really are, so moving
+// a triangle by a fraction of a pixel changes its edge shades. without it
+// the corners are snapped to whole pixels
+#[test]
+fn multisampled_edges_follow_fractions_of_a_pixel() {
+    assert!(shifted_triangle(AntiAliasing::Off, 0.0) == shifted_triangle(AntiAliasing::Off, 0.4));
+    assert!(shifted_triangle(AntiAliasing::Multisample(4), 0.0) != shifted_triangle(AntiAliasing::Multisample(4), 0.4));
+}
+
+// a pixel only partly covered by a triangle reports the triangle's depth,
+// so something behind it fails a test against the pixel
+#[test]
+fn a_pixel_depth_is_its_nearest_sample() {
+    let mut display = Display::new_headless(8, 8);
+    display.set_anti_aliasing(AntiAliasing::Multisample(4)).unwrap();
+    display.clear_depth_buffer();
+    let mut tile = display.full_frame();
+    draw_filled_triangle(&mut tile, &points([(0.0, 0.0), (8.0, 8.0), (0.0, 8.0)], 2.0), WHITE, BlendMode::Opaque);
+    assert_eq!(tile.depth_at(4, 4), 0.5);
+    assert_eq!(tile.depth_at(6, 1), 1.0);
+}
+
+#[test]
+fn sample_buffers_are_kept_in_budget() {
+    assert_eq!(anti_aliasing_sample_count(AntiAliasing::Off, 800, 600), Ok(0));
+    assert_eq!(anti_aliasing_sample_count(AntiAliasing::Supersample(3), 800, 600), Ok(800 * 600 * 9));
+    assert_eq!(anti_aliasing_sample_count(AntiAliasing::Multisample(4), 800, 600), Ok(800 * 600 * 4));
+
+    // a colour and a depth of 4 bytes each for every sample, right up to the budget
+    let side = 1 << 13;
+    let most_samples = MAX_SAMPLE_BUFFER_BYTES / 8;
+    assert_eq!(anti_aliasing_sample_count(AntiAliasing::Supersample(2), side, side), Ok(most_samples));
+    assert!(anti_aliasing_sample_count(AntiAliasing::Supersample(4), side, side).is_err());
+    assert!(anti_aliasing_sample_count(AntiAliasing::Multisample(8), usize::MAX, 2).is_err());
+}
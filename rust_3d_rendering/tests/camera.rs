@@ -0,0 +1,90 @@
+mod common;
+
+use std::{f32::consts::PI, path::Path};
+
+use common::*;
+use rust_3d_rendering::{
+    camera::{CameraKey, camera_path_duration, camera_path_sample},
+    display::render_settings_default,
+    quaternion::{quat_from_axis_angle, quat_identity, quat_rotate_vec3},
+    scene_file::{parse_scene, scene_file_to_string},
+    vector::Vec3,
+};
+
+const Y_AXIS: Vec3 = Vec3 { x: 0.0, y: 1.0, z: 0.0 };
+const Z_AXIS: Vec3 = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+
+fn path() -> Vec<CameraKey> {
+    vec![
+        CameraKey { time: 1.0, position: Vec3 { x: 0.0, y: 0.0, z: 0.0 }, rotation: quat_identity() },
+        CameraKey {
+            time: 3.0,
+            position: Vec3 { x: 4.0, y: 2.0, z: -2.0 },
+            rotation: quat_from_axis_angle(&Y_AXIS, PI / 2.0),
+        },
+    ]
+}
+
+#[test]
+fn the_camera_moves_evenly_between_keys() {
+    let (position, rotation) = camera_path_sample(&path(), 2.0).unwrap();
+    assert_vec3_close(&position, &Vec3 { x: 2.0, y: 1.0, z: -1.0 }, 1e-5);
+    let forward = quat_rotate_vec3(&rotation, &Z_AXIS);
+    let half_turn = (PI / 4.0).sin();
+    assert_vec3_close(&forward, &Vec3 { x: half_turn, y: 0.0, z: half_turn }, 1e-5);
+}
+
+#[test]
+fn the_camera_stays_at_the_ends_of_the_path() {
+    let keys = path();
+    let (before, _) = camera_path_sample(&keys, 0.0).unwrap();
+    assert_vec3_close(&before, &keys[0].position, 1e-6);
+    let (after, _) = camera_path_sample(&keys, 10.0).unwrap();
+    assert_vec3_close(&after, &keys[1].position, 1e-6);
+    assert_f32_close(camera_path_duration(&keys), 3.0, 1e-6);
+
+    assert!(camera_path_sample(&[], 1.0).is_none());
+    assert_f32_close(camera_path_duration(&[]), 0.0, 1e-6);
+}
+
+#[test]
+fn camera_keys_load_and_save() {
+    let text = "
+[[camera_key]]
+time = 0
+position = [0, 0, -5]
+
+[[camera_key]]
+time = 2.5
+position = [1, 2, 3]
+rotation = [0, 90, 0]
+";
+    let (scene, _) = parse_scene(text, "path.toml", Path::new("")).unwrap();
+    assert_eq!(scene.camera_path.len(), 2);
+    assert_f32_close(scene.camera_path[1].time, 2.5, 1e-6);
+    assert_vec3_close(&scene.camera_path[1].position, &Vec3 { x: 1.0, y: 2.0, z: 3.0 }, 1e-6);
+
+    let saved = scene_file_to_string(&scene, &render_settings_default(), Path::new(""));
+    let (reloaded, _) = parse_scene(&saved, "saved.toml", Path::new("")).unwrap();
+    assert_eq!(reloaded.camera_path.len(), 2);
+    for (a, b) in scene.camera_path.iter().zip(&reloaded.camera_path) {
+        assert_f32_close(a.time, b.time, 1e-6);
+        assert_vec3_close(&a.position, &b.position, 1e-5);
+        assert_vec3_close(&quat_rotate_vec3(&a.rotation, &Z_AXIS), &quat_rotate_vec3(&b.rotation, &Z_AXIS), 1e-4);
+    }
+}
+
+#[test]
+fn camera_keys_must_go_forward_in_time() {
+    let text = "
+[[camera_key]]
+time = 2
+position = [0, 0, 0]
+
+[[camera_key]]
+time = 1
+position = [0, 0, 1]
+";
+    let error = parse_scene(text, "path.toml", Path::new("")).err().unwrap();
+    assert!(error.starts_with("path.toml:7:"), "{}", error);
+}
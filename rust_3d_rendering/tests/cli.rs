@@ -0,0 +1,147 @@
+use rust_3d_rendering::{
+    antialias::{AntiAliasing, anti_aliasing_from_name},
+    cli::{CliOptions, DEFAULT_FPS, MAX_RESOLUTION, parse_args},
+    clock::FrameLimit,
+    display::{RenderMode, SCREEN_HEIGHT, SCREEN_WIDTH, TransparencyMode},
+    line::{HiddenLines, LineEdges},
+};
+
+fn parse(line: &str) -> Result<CliOptions, String> {
+    let args: Vec<String> = line.split_whitespace().map(|arg| arg.to_string()).collect();
+    parse_args(&args)
+}
+
+fn parse_error(line: &str) -> String {
+    match parse(line) {
+        Ok(_) => panic!("'{}' should have been an error", line),
+        Err(error) => error,
+    }
+}
+
+#[test]
+fn no_arguments_gives_the_defaults() {
+    let options = parse("").unwrap();
+    assert_eq!((options.width, options.height), (SCREEN_WIDTH, SCREEN_HEIGHT));
+    assert_eq!(options.render_mode, None);
+    assert_eq!(options.fps, DEFAULT_FPS);
+    assert_eq!(options.frame_limit, FrameLimit::TargetFps(DEFAULT_FPS));
+    assert!(!options.headless && !options.hud && !options.svg && !options.show_help);
+    assert_eq!(options.out_dir, ".");
+}
+
+#[test]
+fn every_option_is_read() {
+    let options = parse(
+        "--model m.obj --texture t.png --resolution 320x200 --mode hidden_line --culling off \
+         --transparency a_buffer --a-buffer-fragments 8 --aa msaa4 --line-width 2.5 --smooth-lines on \
+         --hidden-lines dashed --line-edges outline --fps 60 --frame-limit vsync --hud --stats-csv s.csv \
+         --headless --frames 12 --out frames --svg --record clip.y4m --record-fps 25").unwrap();
+    assert_eq!(options.model_path.as_deref(), Some("m.obj"));
+    assert_eq!(options.texture_path.as_deref(), Some("t.png"));
+    assert_eq!((options.width, options.height), (320, 200));
+    assert_eq!(options.render_mode, Some(RenderMode::HiddenLine));
+    assert_eq!(options.backface_culling_enabled, Some(false));
+    assert_eq!(options.transparency, Some(TransparencyMode::ABuffer));
+    assert_eq!(options.a_buffer_fragments, 8);
+    assert_eq!(options.anti_aliasing, Some(AntiAliasing::Multisample(4)));
+    assert_eq!(options.line_width, Some(2.5));
+    assert_eq!(options.smooth_lines, Some(true));
+    assert_eq!(options.hidden_lines, Some(HiddenLines::Dashed));
+    assert_eq!(options.line_edges, Some(LineEdges::Outline));
+    assert_eq!(options.fps, 60.0);
+    assert_eq!(options.frame_limit, FrameLimit::Vsync);
+    assert!(options.hud && options.headless && options.svg);
+    assert_eq!(options.stats_csv_path.as_deref(), Some("s.csv"));
+    assert_eq!(options.frame_count, Some(12));
+    assert_eq!(options.out_dir, "frames");
+    assert_eq!(options.record_path.as_deref(), Some("clip.y4m"));
+    assert_eq!(options.record_fps, Some(25.0));
+
+    let options = parse("--scene a.toml --frame-limit uncapped").unwrap();
+    assert_eq!(options.scene_path.as_deref(), Some("a.toml"));
+    assert_eq!(options.frame_limit, FrameLimit::Uncapped);
+}
+
+#[test]
+fn values_can_follow_an_equals_sign() {
+    let options = parse("--fps=24 --resolution=64x48 --mode=wireframe").unwrap();
+    assert_eq!(options.fps, 24.0);
+    assert_eq!(options.frame_limit, FrameLimit::TargetFps(24.0));
+    assert_eq!((options.width, options.height), (64, 48));
+    assert_eq!(options.render_mode, Some(RenderMode::Wireframe));
+}
+
+#[test]
+fn help_skips_the_other_checks() {
+    assert!(parse("-h").unwrap().show_help);
+    assert!(parse("--svg --help").unwrap().show_help);
+    // but not when it's the value of another option
+    assert_eq!(parse("--model -h").unwrap().model_path.as_deref(), Some("-h"));
+}
+
+#[test]
+fn bad_values_are_errors() {
+    let cases = [
+        ("--resolution 800", "--resolution should look like 800x600"),
+        ("--resolution 0x600", "--resolution should look like 800x600"),
+        ("--resolution 100000x600", "--resolution can be at most"),
+        ("--mode solid", "unknown render mode 'solid'"),
+        ("--culling maybe", "--culling should be 'on' or 'off'"),
+        ("--transparency glass", "unknown transparency mode 'glass'"),
+        ("--a-buffer-fragments 0", "--a-buffer-fragments should be a whole number from 1 to 64"),
+        ("--a-buffer-fragments 65", "--a-buffer-fragments should be a whole number from 1 to 64"),
+        ("--aa fxaa", "unknown anti-aliasing 'fxaa'"),
+        ("--line-width -1", "--line-width should be a number above 0"),
+        ("--line-width inf", "--line-width should be a number above 0"),
+        ("--smooth-lines yes", "--smooth-lines should be 'on' or 'off'"),
+        ("--hidden-lines ghost", "unknown hidden lines 'ghost'"),
+        ("--line-edges some", "unknown line edges 'some'"),
+        ("--fps 0", "--fps should be a number above 0"),
+        ("--frame-limit fast", "--frame-limit should be 'fps', 'vsync' or 'uncapped'"),
+        ("--headless --frames 0", "--frames should be a whole number above 0"),
+        ("--record clip.mp4", "--record should end in one of: .y4m, .avi"),
+        ("--record clip.avi --record-fps nan", "--record-fps should be a number above 0"),
+    ];
+    for (line, expected) in cases {
+        let error = parse_error(line);
+        assert!(error.starts_with(expected), "'{}' gave {}", line, error);
+    }
+}
+
+#[test]
+fn bad_arguments_are_errors() {
+    assert_eq!(parse_error("--fps"), "--fps needs a value");
+    assert_eq!(parse_error("--hud=yes"), "--hud doesn't take a value");
+    assert_eq!(parse_error("model.obj"), "unexpected argument 'model.obj'");
+    assert_eq!(parse_error("--zoom 2"), "unknown option '--zoom'");
+}
+
+#[test]
+fn options_that_need_each_other() {
+    assert!(parse_error("--scene a.toml --model m.obj").starts_with("--scene can't be used together"));
+    assert!(parse_error("--scene a.toml --texture t.png").starts_with("--scene can't be used together"));
+    assert!(parse_error("--frames 3").starts_with("--frames and --out only work with --headless"));
+    assert!(parse_error("--out frames").starts_with("--frames and --out only work with --headless"));
+    assert!(parse_error("--record-fps 30").starts_with("--record-fps only works with --record"));
+    assert!(parse_error("--svg").starts_with("--svg only works with --headless"));
+}
+
+#[test]
+fn the_largest_resolution_is_allowed() {
+    let options = parse(&format!("--resolution {}x{}", MAX_RESOLUTION, MAX_RESOLUTION)).unwrap();
+    assert_eq!((options.width, options.height), (MAX_RESOLUTION, MAX_RESOLUTION));
+}
+
+#[test]
+fn the_largest_resolution_only_takes_the_lighter_anti_aliasing() {
+    let size = format!("--resolution {}x{}", MAX_RESOLUTION, MAX_RESOLUTION);
+    for aa in ["ssaa2", "msaa4"] {
+        let options = parse(&format!("{} --aa {}", size, aa)).unwrap();
+        assert_eq!(options.anti_aliasing, anti_aliasing_from_name(aa));
+    }
+    for aa in ["ssaa3", "ssaa4", "msaa8"] {
+        assert!(parse_error(&format!("{} --aa {}", size, aa)).contains("MiB allowed for the samples"));
+    }
+    // a smaller frame can have them all
+    assert!(parse("--resolution 1920x1080 --aa ssaa4").is_ok());
+}
@@ -0,0 +1,50 @@
+use rust_3d_rendering::clock::{
+    MAX_FRAME_TIME, clock_new, clock_tick, fixed_timestep_advance, fixed_timestep_alpha, fixed_timestep_new,
+};
+
+#[test]
+fn steps_match_elapsed_time() {
+    let mut timestep = fixed_timestep_new(60.0);
+    let mut steps = 0;
+    // a second of frames at 144 fps, which doesn't divide into 60
+    for _ in 0..144 {
+        steps += fixed_timestep_advance(&mut timestep, 1.0 / 144.0);
+    }
+    assert!((59..=60).contains(&steps), "took {} steps", steps);
+    assert!(timestep.accumulator < timestep.step);
+}
+
+#[test]
+fn slow_frames_take_several_steps() {
+    let mut timestep = fixed_timestep_new(60.0);
+    assert_eq!(fixed_timestep_advance(&mut timestep, 3.5 / 60.0), 3);
+    assert!((fixed_timestep_alpha(&timestep) - 0.5).abs() < 1e-3);
+}
+
+#[test]
+fn fast_frames_carry_over() {
+    let mut timestep = fixed_timestep_new(60.0);
+    assert_eq!(fixed_timestep_advance(&mut timestep, 0.4 / 60.0), 0);
+    assert!((fixed_timestep_alpha(&timestep) - 0.4).abs() < 1e-3);
+    assert_eq!(fixed_timestep_advance(&mut timestep, 0.4 / 60.0), 0);
+    assert_eq!(fixed_timestep_advance(&mut timestep, 0.4 / 60.0), 1);
+    assert!((fixed_timestep_alpha(&timestep) - 0.2).abs() < 1e-3);
+}
+
+// a long stall shouldn't make the simulation run seconds worth of steps
+#[test]
+fn long_frames_are_capped() {
+    let mut timestep = fixed_timestep_new(60.0);
+    let steps = fixed_timestep_advance(&mut timestep, 10.0);
+    assert_eq!(steps, (MAX_FRAME_TIME * 60.0).round() as usize);
+    assert_eq!(fixed_timestep_advance(&mut timestep, -1.0), 0);
+}
+
+#[test]
+fn clock_measures_real_time() {
+    let mut clock = clock_new();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    let delta_time = clock_tick(&mut clock);
+    assert!((0.02..=MAX_FRAME_TIME).contains(&delta_time), "delta time was {}", delta_time);
+    assert!(clock.elapsed_time >= 0.02);
+}
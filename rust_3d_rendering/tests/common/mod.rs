@@ -0,0 +1,91 @@
+// helpers shared by the math tests. the property tests draw their inputs
+// from a seeded rng so a failure always comes back the same way
+
+#![allow(dead_code)]
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rust_3d_rendering::{
+    matrix::{Mat3, Mat4},
+    vector::{Vec2, Vec3, Vec4},
+};
+
+// how many random cases each property test tries
+pub const PROPERTY_CASES: usize = 1000;
+
+pub const EPSILON: f32 = 1e-5;
+
+pub fn test_rng() -> StdRng {
+    StdRng::seed_from_u64(0x3d_3d_3d)
+}
+
+pub fn random_f32(rng: &mut StdRng) -> f32 {
+    rng.gen_range(-10.0..10.0)
+}
+
+pub fn random_angle(rng: &mut StdRng) -> f32 {
+    rng.gen_range(-2.0 * std::f32::consts::PI..2.0 * std::f32::consts::PI)
+}
+
+pub fn random_vec2(rng: &mut StdRng) -> Vec2 {
+    Vec2 { x: random_f32(rng), y: random_f32(rng) }
+}
+
+pub fn random_vec3(rng: &mut StdRng) -> Vec3 {
+    Vec3 { x: random_f32(rng), y: random_f32(rng), z: random_f32(rng) }
+}
+
+pub fn random_vec4(rng: &mut StdRng) -> Vec4 {
+    Vec4 { x: random_f32(rng), y: random_f32(rng), z: random_f32(rng), w: random_f32(rng) }
+}
+
+pub fn random_mat4(rng: &mut StdRng) -> Mat4 {
+    let mut m = Mat4 { m: [[0.0; 4]; 4] };
+    for row in m.m.iter_mut() {
+        for value in row.iter_mut() {
+            *value = random_f32(rng);
+        }
+    }
+    m
+}
+
+// a tolerance that grows with the size of the numbers being compared, since
+// f32 only keeps about 7 significant digits
+pub fn close(a: f32, b: f32, epsilon: f32) -> bool {
+    (a - b).abs() <= epsilon * a.abs().max(b.abs()).max(1.0)
+}
+
+pub fn assert_f32_close(a: f32, b: f32, epsilon: f32) {
+    assert!(close(a, b, epsilon), "{} != {} (epsilon {})", a, b, epsilon);
+}
+
+pub fn assert_vec2_close(a: &Vec2, b: &Vec2, epsilon: f32) {
+    assert!(close(a.x, b.x, epsilon) && close(a.y, b.y, epsilon), "{:?} != {:?}", a, b);
+}
+
+pub fn assert_vec3_close(a: &Vec3, b: &Vec3, epsilon: f32) {
+    assert!(
+        close(a.x, b.x, epsilon) && close(a.y, b.y, epsilon) && close(a.z, b.z, epsilon),
+        "{:?} != {:?}", a, b);
+}
+
+pub fn assert_vec4_close(a: &Vec4, b: &Vec4, epsilon: f32) {
+    assert!(
+        close(a.x, b.x, epsilon) && close(a.y, b.y, epsilon) && close(a.z, b.z, epsilon) && close(a.w, b.w, epsilon),
+        "{:?} != {:?}", a, b);
+}
+
+pub fn assert_mat4_close(a: &Mat4, b: &Mat4, epsilon: f32) {
+    for i in 0..4 {
+        for j in 0..4 {
+            assert!(close(a.m[i][j], b.m[i][j], epsilon), "{:?}\n!=\n{:?}\n(differ at [{}][{}])", a, b, i, j);
+        }
+    }
+}
+
+pub fn assert_mat3_close(a: &Mat3, b: &Mat3, epsilon: f32) {
+    for i in 0..3 {
+        for j in 0..3 {
+            assert!(close(a.m[i][j], b.m[i][j], epsilon), "{:?}\n!=\n{:?}\n(differ at [{}][{}])", a, b, i, j);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use rust_3d_rendering::{display::Display, vector::Vec2};
+use sdl2::pixels::Color;
+
+const RED: Color = Color::RGBA(255, 0, 0, 255);
+
+fn red_pixels(display: &Display) -> Vec<(usize, usize)> {
+    let width = display.width;
+    display
+        .color_buffer_rgba()
+        .chunks_exact(4)
+        .enumerate()
+        .filter(|(_, pixel)| pixel[0] == 255)
+        .map(|(i, _)| (i % width, i / width))
+        .collect()
+}
+
+#[test]
+fn rects_are_clipped_to_the_screen() {
+    let mut display = Display::new_headless(8, 8);
+    display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+    // height 5 and width 4 from (-2, -3) only leaves two rows and columns
+    display.draw_rect(-2, -3, 5, 4, RED);
+    display.draw_rect(6, 7, 4, 4, RED);
+    display.draw_rect(-100, 2, 2, 50, RED);
+    display.draw_rect(20, 20, 4, 4, RED);
+    display.draw_rect(-20, -20, 4, 4, RED);
+    let expected = [(0, 0), (1, 0), (0, 1), (1, 1), (6, 7), (7, 7)];
+    let red = red_pixels(&display);
+    for pixel in &expected {
+        assert!(red.contains(pixel), "missing {:?}", pixel);
+    }
+    assert_eq!(red.len(), expected.len());
+}
+
+#[test]
+fn lines_off_the_screen_draw_nothing() {
+    let mut display = Display::new_headless(8, 8);
+    display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+    display.draw_line(-50.0, -1.0, 50.0, -1.0, RED);
+    display.draw_line(9.0, -1.0e9, 9.0, 1.0e9, RED);
+    assert!(red_pixels(&display).is_empty());
+
+    display.draw_line(-50.0, 3.0, 50.0, 3.0, RED);
+    assert_eq!(red_pixels(&display), (0..8).map(|x| (x, 3)).collect::<Vec<_>>());
+}
+
+#[test]
+fn triangle_outline() {
+    let mut display = Display::new_headless(8, 8);
+    display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+    display.draw_triangle(&[Vec2 { x: 1.0, y: 1.0 }, Vec2 { x: 6.0, y: 1.0 }, Vec2 { x: 1.0, y: 6.0 }], RED);
+    let red = red_pixels(&display);
+    for i in 1..=6 {
+        assert!(red.contains(&(i, 1)) && red.contains(&(1, i)), "missing a pixel of the sides at {}", i);
+    }
+    // the long side, and nothing inside
+    assert!(red.contains(&(3, 4)) && red.contains(&(4, 3)));
+    assert!(!red.contains(&(2, 2)) && !red.contains(&(3, 3)));
+}
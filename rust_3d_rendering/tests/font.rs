@@ -0,0 +1,79 @@
+use rust_3d_rendering::{
+    display::Display,
+    font::{CHAR_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH, LINE_ADVANCE, glyph_rows, text_size},
+};
+use sdl2::pixels::Color;
+
+#[test]
+fn text_size_covers_the_longest_line() {
+    assert_eq!(text_size("", 1), (0, 0));
+    assert_eq!(text_size("a", 1), (GLYPH_WIDTH, GLYPH_HEIGHT));
+    assert_eq!(text_size("abc\nd", 1), (3 * CHAR_ADVANCE - 1, LINE_ADVANCE + GLYPH_HEIGHT));
+    assert_eq!(text_size("abc\nd", 2), (2 * (3 * CHAR_ADVANCE - 1), 2 * (LINE_ADVANCE + GLYPH_HEIGHT)));
+}
+
+#[test]
+fn unknown_characters_draw_as_question_marks() {
+    assert_eq!(glyph_rows('é'), glyph_rows('?'));
+    assert_eq!(glyph_rows('\t'), glyph_rows('?'));
+    assert_ne!(glyph_rows('A'), glyph_rows('?'));
+    assert!(glyph_rows(' ').iter().all(|row| *row == 0));
+}
+
+// the text should land exactly inside the area text_size gives, and run off
+// the edge of the screen without panicking
+#[test]
+fn draw_text_stays_inside_its_size() {
+    let text = "Hello,\nworld!";
+    let (width, height) = text_size(text, 2);
+    let mut display = Display::new_headless(100, 40);
+    display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+    display.draw_text(3, 5, text, 2, Color::RGBA(255, 255, 255, 255));
+    display.draw_text(95, 38, text, 2, Color::RGBA(0, 0, 0, 255));
+
+    let rgba = display.color_buffer_rgba();
+    let mut lit_pixels = 0;
+    for y in 0..40 {
+        for x in 0..100 {
+            if rgba[(y * 100 + x) * 4] == 255 {
+                lit_pixels += 1;
+                assert!((3..3 + width).contains(&x) && (5..5 + height).contains(&y), "pixel {}, {} is lit", x, y);
+            }
+        }
+    }
+    assert!(lit_pixels > 0);
+}
+
+// text starting above and left of the screen draws the part that's on it
+#[test]
+fn draw_text_is_clipped_off_the_top_left() {
+    let text = "Hello,\nworld!";
+    let white = Color::RGBA(255, 255, 255, 255);
+    let mut whole = Display::new_headless(100, 40);
+    whole.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+    whole.draw_text(10, 10, text, 2, white);
+    let mut clipped = Display::new_headless(100, 40);
+    clipped.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+    clipped.draw_text(-4, -3, text, 2, white);
+
+    let (whole, clipped) = (whole.color_buffer_rgba(), clipped.color_buffer_rgba());
+    let mut lit_pixels = 0;
+    for y in 0..27 {
+        for x in 0..86 {
+            let shade = clipped[(y * 100 + x) * 4];
+            assert_eq!(shade, whole[((y + 13) * 100 + x + 14) * 4], "pixel {}, {}", x, y);
+            lit_pixels += (shade == 255) as usize;
+        }
+    }
+    assert!(lit_pixels > 0);
+}
+
+#[test]
+fn text_far_off_the_screen_draws_nothing() {
+    let mut display = Display::new_headless(10, 10);
+    display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+    for (x, y) in [(i32::MIN, i32::MAX), (i32::MAX, i32::MIN), (i32::MIN, i32::MIN), (-200, 0), (0, -200)] {
+        display.draw_text(x, y, "far away\nand further", 3, Color::RGBA(255, 255, 255, 255));
+    }
+    assert!(display.color_buffer_rgba().chunks_exact(4).all(|pixel| pixel[0] == 0));
+}
@@ -0,0 +1,150 @@
+use rust_3d_rendering::{
+    antialias::AntiAliasing,
+    display::Display,
+    fragment::{
+        MAX_FRAGMENTS_PER_PIXEL, fragment_buffer_clamp, fragment_buffer_new, fragment_buffer_split, fragment_rows_clear,
+        fragment_rows_push, fragment_rows_sorted,
+    },
+    material::{BlendMode, blend_colors},
+};
+use sdl2::pixels::Color;
+
+const RED: Color = Color::RGBA(255, 0, 0, 128);
+const GREEN: Color = Color::RGBA(0, 255, 0, 128);
+const BLUE: Color = Color::RGBA(0, 0, 255, 128);
+
+#[test]
+fn fragments_come_out_farthest_first() {
+    let mut buffer = fragment_buffer_new(4, 2, 2).unwrap();
+    let mut rows = fragment_buffer_split(&mut buffer, 2).pop().unwrap();
+    fragment_rows_clear(&mut rows);
+    assert!(fragment_rows_push(&mut rows, 1, 1, RED, 0.5, BlendMode::Alpha, 1));
+    assert!(fragment_rows_push(&mut rows, 1, 1, GREEN, 0.9, BlendMode::Alpha, 1));
+    assert!(fragment_rows_push(&mut rows, 1, 1, BLUE, 0.7, BlendMode::Additive, 1));
+    // the same depth as red but drawn after it, so it stays after it
+    assert!(fragment_rows_push(&mut rows, 1, 1, RED, 0.5, BlendMode::Multiply, 1));
+
+    let mut sorted = Vec::new();
+    fragment_rows_sorted(&rows, 1, 1, &mut sorted);
+    let order: Vec<(f32, BlendMode)> = sorted.iter().map(|fragment| (fragment.depth, fragment.blend_mode)).collect();
+    assert_eq!(order, [
+        (0.9, BlendMode::Alpha), (0.7, BlendMode::Additive), (0.5, BlendMode::Alpha), (0.5, BlendMode::Multiply)]);
+
+    fragment_rows_sorted(&rows, 0, 1, &mut sorted);
+    assert!(sorted.is_empty());
+}
+
+// each row only gets its share of the pool, however the rows are split
+#[test]
+fn full_rows_refuse_fragments() {
+    let mut buffer = fragment_buffer_new(2, 4, 1).unwrap();
+    let mut parts = fragment_buffer_split(&mut buffer, 3);
+    assert_eq!(parts.len(), 2);
+    let rows = &mut parts[0];
+    fragment_rows_clear(rows);
+    assert!(fragment_rows_push(rows, 0, 1, RED, 0.5, BlendMode::Alpha, 1));
+    assert!(fragment_rows_push(rows, 0, 1, RED, 0.5, BlendMode::Alpha, 1));
+    assert!(!fragment_rows_push(rows, 1, 1, RED, 0.5, BlendMode::Alpha, 1));
+    assert!(fragment_rows_push(rows, 1, 2, RED, 0.5, BlendMode::Alpha, 1));
+
+    fragment_rows_clear(rows);
+    assert!(fragment_rows_push(rows, 1, 1, RED, 0.5, BlendMode::Alpha, 1));
+}
+
+// pixels drawn in the wrong order come out the same as drawing them back to front
+#[test]
+fn resolve_blends_in_depth_order() {
+    let background = Color::RGBA(40, 40, 40, 255);
+    let expected = [(GREEN, BlendMode::Alpha), (RED, BlendMode::Additive), (BLUE, BlendMode::Alpha)]
+        .iter()
+        .fold(background, |color, (source, blend_mode)| blend_colors(source, &color, *blend_mode).unwrap());
+
+    let mut display = Display::new_headless(4, 4);
+    display.enable_a_buffer(4).unwrap();
+    display.clear_color_buffer(background);
+    let mut tile = display.full_frame();
+    tile.clear_fragments();
+    tile.draw_transparent_pixel(2, 3, BLUE, 0.2, BlendMode::Alpha);
+    tile.draw_transparent_pixel(2, 3, GREEN, 0.8, BlendMode::Alpha);
+    tile.draw_transparent_pixel(2, 3, RED, 0.5, BlendMode::Additive);
+    assert_eq!(tile.pixel_at(2, 3), background);
+    assert_eq!(tile.fragments_stored(), 3);
+
+    // an opaque pixel drawn afterwards still hides the fragments behind it
+    tile.set_depth(2, 3, 0.6);
+    tile.resolve_fragments();
+    let expected_in_front = blend_colors(
+        &BLUE, &blend_colors(&RED, &background, BlendMode::Additive).unwrap(), BlendMode::Alpha).unwrap();
+    assert_eq!(tile.pixel_at(2, 3), expected_in_front);
+
+    tile.clear_fragments();
+    tile.set_depth(2, 3, 1.0);
+    tile.draw_pixel(2, 3, background);
+    tile.draw_transparent_pixel(2, 3, BLUE, 0.2, BlendMode::Alpha);
+    tile.draw_transparent_pixel(2, 3, GREEN, 0.8, BlendMode::Alpha);
+    tile.draw_transparent_pixel(2, 3, RED, 0.5, BlendMode::Additive);
+    tile.resolve_fragments();
+    assert_eq!(tile.pixel_at(2, 3), expected);
+    assert_eq!(tile.fragments_overflowed, 0);
+}
+
+// once a row's out of room, the rest are blended straight away
+#[test]
+fn overflow_falls_back_to_blending_straight_away() {
+    let background = Color::RGBA(0, 0, 0, 255);
+    let mut display = Display::new_headless(1, 1);
+    display.enable_a_buffer(1).unwrap();
+    display.clear_color_buffer(background);
+    let mut tile = display.full_frame();
+    tile.clear_fragments();
+    tile.draw_transparent_pixel(0, 0, RED, 0.2, BlendMode::Alpha);
+    tile.draw_transparent_pixel(0, 0, GREEN, 0.8, BlendMode::Alpha);
+    assert_eq!(tile.fragments_stored(), 1);
+    assert_eq!(tile.fragments_overflowed, 1);
+    assert_eq!(tile.pixel_at(0, 0), blend_colors(&GREEN, &background, BlendMode::Alpha).unwrap());
+
+    tile.resolve_fragments();
+    let after_green = blend_colors(&GREEN, &background, BlendMode::Alpha).unwrap();
+    assert_eq!(tile.pixel_at(0, 0), blend_colors(&RED, &after_green, BlendMode::Alpha).unwrap());
+}
+
+// without the a-buffer see through pixels are blended as they're drawn
+#[test]
+fn without_the_a_buffer_pixels_blend_straight_away() {
+    let background = Color::RGBA(0, 0, 0, 255);
+    let mut display = Display::new_headless(1, 1);
+    display.enable_a_buffer(2).unwrap();
+    display.disable_a_buffer();
+    assert!(!display.a_buffer_enabled());
+    display.clear_color_buffer(background);
+    let mut tile = display.full_frame();
+    tile.clear_fragments();
+    tile.draw_transparent_pixel(0, 0, RED, 0.2, BlendMode::Alpha);
+    assert_eq!(tile.pixel_at(0, 0), blend_colors(&RED, &background, BlendMode::Alpha).unwrap());
+    assert_eq!(tile.fragments_stored(), 0);
+    assert_eq!(tile.fragments_overflowed, 0);
+}
+
+#[test]
+fn fragment_counts_are_kept_in_range() {
+    assert_eq!(fragment_buffer_clamp(800, 0), 1);
+    assert_eq!(fragment_buffer_clamp(800, 5), 5);
+    assert_eq!(fragment_buffer_clamp(800, 1000), MAX_FRAGMENTS_PER_PIXEL);
+}
+
+// supersampling multiplies the pixels the a-buffer covers, so a size that
+// fits at first can be too big once it's turned on
+#[test]
+fn a_buffer_too_big_is_turned_off() {
+    assert!(fragment_buffer_new(16384, 16384, MAX_FRAGMENTS_PER_PIXEL).is_err());
+
+    let mut display = Display::new_headless(250, 250);
+    display.enable_a_buffer(MAX_FRAGMENTS_PER_PIXEL).unwrap();
+    display.set_anti_aliasing(AntiAliasing::Supersample(4)).unwrap();
+    assert!(!display.a_buffer_enabled());
+    let error = display.enable_a_buffer(MAX_FRAGMENTS_PER_PIXEL).err().unwrap();
+    assert!(error.contains("1000x1000 pixels"), "{}", error);
+    assert!(!display.a_buffer_enabled());
+    display.enable_a_buffer(4).unwrap();
+    assert!(display.a_buffer_enabled());
+}
@@ -0,0 +1,536 @@
+// golden image tests. each test renders a small reference scene headlessly
+// and compares it against the png of the same name in tests/golden. a pixel
+// only counts as different when one of its channels is off by more than
+// CHANNEL_TOLERANCE, which leaves room for the odd rounding difference
+// between machines without hiding real changes.
+//
+// when a test fails the rendered image, the golden and a diff image with the
+// changed pixels in red are written to target/tmp/golden so you can see what
+// moved. if the change was on purpose, regenerate the goldens with
+//
+//     UPDATE_GOLDENS=1 cargo test --test golden
+//
+// and commit the new pngs along with the change
+
+use std::{env, fs, path::PathBuf};
+
+use image::{ImageBuffer, Rgba, RgbaImage};
+use rust_3d_rendering::{
+    antialias::AntiAliasing,
+    display::{Display, RenderMode, RenderSettings, TransparencyMode, render_settings_default},
+    fragment::DEFAULT_FRAGMENTS_PER_PIXEL,
+    light::Light,
+    line::{HiddenLines, LineEdges, LineStyle, line_style_default},
+    material::BlendMode,
+    mesh::{Mesh, get_cube_mesh, get_sphere_mesh},
+    pipeline::{assemble_scene_triangles, sort_triangles_by_depth, vertex_cache_new},
+    quaternion::{EulerOrder, quat_from_euler},
+    raster::render_triangles,
+    scene::{Scene, scene_add_mesh, scene_add_node, scene_add_texture, scene_new, scene_node_new, scene_update_world_matrices},
+    texture::{MeshTexture, Tex2},
+    triangle::Face,
+    vector::Vec3,
+};
+use sdl2::pixels::Color;
+
+const WIDTH: usize = 160;
+const HEIGHT: usize = 120;
+
+const CHANNEL_TOLERANCE: u8 = 2;
+
+// the share of pixels that can differ before a test fails, this many are
+// allowed for pixels right on a triangle edge that can land either way
+const MAX_DIFFERENT_PIXELS: f32 = 0.001;
+
+// the tiled renderer has to give exactly the same image as drawing on one
+// thread, so every scene is drawn both ways
+const THREAD_COUNTS: [usize; 2] = [1, 4];
+
+fn render(scene: &mut Scene, render_mode: RenderMode, backface_culling_enabled: bool) -> Vec<u8> {
+    let settings = RenderSettings { render_mode, backface_culling_enabled, ..render_settings_default() };
+    render_with_settings(scene, &settings)
+}
+
+fn render_with_settings(scene: &mut Scene, settings: &RenderSettings) -> Vec<u8> {
+    scene_update_world_matrices(scene);
+
+    let vertex_count = scene.meshes.iter().map(|mesh| mesh.vertices.len()).max().unwrap_or(0);
+    let mut cache = vertex_cache_new(vertex_count);
+    let mut triangles = Vec::new();
+    assemble_scene_triangles(&mut triangles, scene, &mut cache, WIDTH, HEIGHT, settings.backface_culling_enabled);
+    sort_triangles_by_depth(&mut triangles);
+
+    let mut images = Vec::new();
+    let mut raster_stats = Vec::new();
+    for thread_count in THREAD_COUNTS {
+        let mut display = Display::new_headless(WIDTH, HEIGHT);
+        display.set_anti_aliasing(settings.anti_aliasing).unwrap();
+        display.set_line_style(settings.line_style);
+        if settings.transparency == TransparencyMode::ABuffer {
+            display.enable_a_buffer(DEFAULT_FRAGMENTS_PER_PIXEL).unwrap();
+        }
+        display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+        display.clear_depth_buffer();
+        raster_stats.push(
+            render_triangles(&mut display, &triangles, settings.render_mode, &scene.textures, thread_count));
+        images.push(display.color_buffer_rgba());
+    }
+    for image in &images[1..] {
+        assert!(*image == images[0], "drawing on several threads gave a different image to one thread");
+    }
+    for stats in &raster_stats[1..] {
+        assert_eq!(*stats, raster_stats[0], "drawing on several threads wrote a different number of pixels");
+    }
+    images.remove(0)
+}
+
+fn check_golden(name: &str, rgba: Vec<u8>) {
+    let actual: RgbaImage = ImageBuffer::from_raw(WIDTH as u32, HEIGHT as u32, rgba).unwrap();
+    let golden_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", name));
+
+    if env::var_os("UPDATE_GOLDENS").is_some() {
+        fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+        actual.save(&golden_path).unwrap();
+        return;
+    }
+
+    let expected = match image::open(&golden_path) {
+        Ok(image) => image.to_rgba8(),
+        Err(e) => panic!(
+            "couldn't open the golden image {}: {}\nrun `UPDATE_GOLDENS=1 cargo test --test golden` to create it",
+            golden_path.display(), e),
+    };
+    assert_eq!(
+        expected.dimensions(), actual.dimensions(),
+        "{} is a different size to the golden image", name);
+
+    let mut diff: RgbaImage = ImageBuffer::new(WIDTH as u32, HEIGHT as u32);
+    let mut different_pixels = 0;
+    let mut largest_difference = 0;
+    for (x, y, expected_pixel) in expected.enumerate_pixels() {
+        let actual_pixel = actual.get_pixel(x, y);
+        let difference = (0..3)
+            .map(|channel| (expected_pixel[channel] as i32 - actual_pixel[channel] as i32).unsigned_abs() as u8)
+            .max()
+            .unwrap();
+        largest_difference = largest_difference.max(difference);
+
+        if difference > CHANNEL_TOLERANCE {
+            different_pixels += 1;
+            diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else {
+            // a dimmed copy of the golden, so the red stands out against it
+            let grey = ((expected_pixel[0] as u32 + expected_pixel[1] as u32 + expected_pixel[2] as u32) / 9) as u8;
+            diff.put_pixel(x, y, Rgba([grey, grey, grey, 255]));
+        }
+    }
+
+    let allowed = (MAX_DIFFERENT_PIXELS * (WIDTH * HEIGHT) as f32) as usize;
+    if different_pixels > allowed {
+        let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        fs::create_dir_all(&out_dir).unwrap();
+        actual.save(out_dir.join(format!("{}_actual.png", name))).unwrap();
+        expected.save(out_dir.join(format!("{}_expected.png", name))).unwrap();
+        diff.save(out_dir.join(format!("{}_diff.png", name))).unwrap();
+        panic!(
+            "{} has {} pixels different from the golden image (up to {} allowed, largest channel difference {}), \
+             see the images in {}",
+            name, different_pixels, allowed, largest_difference, out_dir.display());
+    }
+}
+
+// an 8 x 8 checkerboard with a different colour in each corner, so a flipped
+// or rotated texture shows up
+fn checker_texture() -> MeshTexture {
+    let size = 16;
+    let mut pixels = Vec::with_capacity(size * size);
+    for y in 0..size {
+        for x in 0..size {
+            let color = if (x / 2 + y / 2) % 2 == 0 {
+                match (x < size / 2, y < size / 2) {
+                    (true, true) => Color::RGBA(255, 64, 64, 255),
+                    (false, true) => Color::RGBA(64, 255, 64, 255),
+                    (true, false) => Color::RGBA(64, 64, 255, 255),
+                    (false, false) => Color::RGBA(255, 255, 64, 255),
+                }
+            } else {
+                Color::RGBA(240, 240, 240, 255)
+            };
+            pixels.push(color);
+        }
+    }
+    MeshTexture { width: size, height: size, pixels }
+}
+
+fn quad_mesh(half_width: f32, half_height: f32) -> Mesh {
+    let white = Color::RGBA(255, 255, 255, 255);
+    Mesh {
+        vertices: vec![
+            Vec3 { x: -half_width, y: -half_height, z: 0.0 },
+            Vec3 { x: -half_width, y: half_height, z: 0.0 },
+            Vec3 { x: half_width, y: half_height, z: 0.0 },
+            Vec3 { x: half_width, y: -half_height, z: 0.0 },
+        ],
+        faces: vec![
+            Face { a: 1, b: 2, c: 3, a_uv: Tex2 { u: 0.0, v: 0.0 }, b_uv: Tex2 { u: 0.0, v: 1.0 }, c_uv: Tex2 { u: 1.0, v: 1.0 }, color: white },
+            Face { a: 1, b: 3, c: 4, a_uv: Tex2 { u: 0.0, v: 0.0 }, b_uv: Tex2 { u: 1.0, v: 1.0 }, c_uv: Tex2 { u: 1.0, v: 0.0 }, color: white },
+        ],
+    }
+}
+
+fn degrees(x: f32, y: f32, z: f32) -> rust_3d_rendering::quaternion::Quat {
+    quat_from_euler(x.to_radians(), y.to_radians(), z.to_radians(), EulerOrder::XYZ)
+}
+
+// the textured cube from the viewer, turned so three of its faces show
+fn cube_scene() -> Scene {
+    let mut scene = scene_new();
+    let mesh = scene_add_mesh(&mut scene, "cube", "builtin:cube", get_cube_mesh());
+    let texture = scene_add_texture(&mut scene, "checker", "", checker_texture());
+
+    let mut cube = scene_node_new("cube");
+    cube.mesh = Some(mesh);
+    cube.material.texture = Some(texture);
+    cube.transform.translation.z = 5.0;
+    cube.transform.rotation = degrees(30.0, 40.0, 0.0);
+    scene_add_node(&mut scene, None, cube);
+
+    scene.lights.push(Light { direction: Vec3 { x: 0.3, y: -0.5, z: 0.8 } });
+    scene
+}
+
+fn cube_test(name: &str, render_mode: RenderMode) {
+    let mut scene = cube_scene();
+    check_golden(name, render(&mut scene, render_mode, true));
+}
+
+#[test]
+fn cube_wireframe_dot() {
+    cube_test("cube_wireframe_dot", RenderMode::WireframeWithDot);
+}
+
+#[test]
+fn cube_wireframe() {
+    cube_test("cube_wireframe", RenderMode::Wireframe);
+}
+
+#[test]
+fn cube_filled() {
+    cube_test("cube_filled", RenderMode::FilledTriangles);
+}
+
+#[test]
+fn cube_filled_wireframe() {
+    cube_test("cube_filled_wireframe", RenderMode::FilledTrianglesAndWireframe);
+}
+
+#[test]
+fn cube_textured() {
+    cube_test("cube_textured", RenderMode::Textured);
+}
+
+#[test]
+fn cube_textured_wireframe() {
+    cube_test("cube_textured_wireframe", RenderMode::TexturedAndWireframe);
+}
+
+#[test]
+fn cube_depth() {
+    cube_test("cube_depth", RenderMode::Depth);
+}
+
+#[test]
+fn cube_normals() {
+    cube_test("cube_normals", RenderMode::Normals);
+}
+
+#[test]
+fn cube_uvs() {
+    cube_test("cube_uvs", RenderMode::Uvs);
+}
+
+#[test]
+fn cube_triangle_index() {
+    cube_test("cube_triangle_index", RenderMode::TriangleIndex);
+}
+
+// without culling every pixel of the cube is covered by a front and a back face
+#[test]
+fn cube_overdraw() {
+    let mut scene = cube_scene();
+    check_golden("cube_overdraw", render(&mut scene, RenderMode::Overdraw, false));
+}
+
+fn cube_anti_aliased_test(name: &str, render_mode: RenderMode, anti_aliasing: AntiAliasing) {
+    let mut scene = cube_scene();
+    let settings = RenderSettings { render_mode, anti_aliasing, ..render_settings_default() };
+    check_golden(name, render_with_settings(&mut scene, &settings));
+}
+
+#[test]
+fn cube_ssaa() {
+    cube_anti_aliased_test("cube_ssaa", RenderMode::Textured, AntiAliasing::Supersample(3));
+}
+
+// the edges come out smooth like supersampling, but the texture inside the
+// faces is the same as without anti-aliasing
+#[test]
+fn cube_msaa() {
+    cube_anti_aliased_test("cube_msaa", RenderMode::Textured, AntiAliasing::Multisample(4));
+}
+
+#[test]
+fn cube_msaa_wireframe() {
+    cube_anti_aliased_test("cube_msaa_wireframe", RenderMode::FilledTrianglesAndWireframe, AntiAliasing::Multisample(8));
+}
+
+// the back faces only show up with culling turned off
+#[test]
+fn cube_wireframe_no_culling() {
+    let mut scene = cube_scene();
+    check_golden("cube_wireframe_no_culling", render(&mut scene, RenderMode::Wireframe, false));
+}
+
+fn cube_line_style_test(name: &str, render_mode: RenderMode, backface_culling_enabled: bool, line_style: LineStyle) {
+    let mut scene = cube_scene();
+    let settings = RenderSettings { render_mode, backface_culling_enabled, line_style, ..render_settings_default() };
+    check_golden(name, render_with_settings(&mut scene, &settings));
+}
+
+#[test]
+fn cube_wireframe_smooth() {
+    let line_style = LineStyle { smooth: true, ..line_style_default() };
+    cube_line_style_test("cube_wireframe_smooth", RenderMode::Wireframe, true, line_style);
+}
+
+// wide lines meet in round corners
+#[test]
+fn cube_wireframe_wide() {
+    let line_style = LineStyle { width: 3.5, smooth: true, ..line_style_default() };
+    cube_line_style_test("cube_wireframe_wide", RenderMode::Wireframe, true, line_style);
+}
+
+// with culling off every edge is there, but the ones round the back are
+// faint or gone
+#[test]
+fn cube_wireframe_hidden_lines() {
+    let dim = LineStyle { hidden: HiddenLines::Dim, ..line_style_default() };
+    cube_line_style_test("cube_wireframe_hidden_dim", RenderMode::Wireframe, false, dim);
+    let hide = LineStyle { width: 2.0, smooth: true, hidden: HiddenLines::Hide, ..line_style_default() };
+    cube_line_style_test("cube_wireframe_hidden_hide", RenderMode::WireframeWithDot, false, hide);
+}
+
+// a face's own edges lie right on it, so hiding the lines behind the faces
+// mustn't take away any of them. a smooth line's pixels reach a little way
+// onto the face, and on a face leaning far away the depth there is a long way
+// in front of the line's, which used to hide bits of it
+#[test]
+fn edges_are_not_hidden_by_their_own_faces() {
+    for rotation in [degrees(0.0, 75.0, 0.0), degrees(-70.0, 0.0, 20.0), degrees(40.0, 60.0, 0.0)] {
+        let mut scene = scene_new();
+        let mesh = scene_add_mesh(&mut scene, "quad", "", quad_mesh(1.5, 1.5));
+        let mut quad = scene_node_new("quad");
+        quad.mesh = Some(mesh);
+        quad.transform.translation.z = 4.0;
+        quad.transform.rotation = rotation;
+        scene_add_node(&mut scene, None, quad);
+
+        let show = LineStyle { width: 1.0, smooth: true, ..line_style_default() };
+        let hide = LineStyle { hidden: HiddenLines::Hide, ..show };
+        let render_lines = |scene: &mut Scene, line_style: LineStyle| {
+            let settings =
+                RenderSettings { render_mode: RenderMode::Wireframe, line_style, ..render_settings_default() };
+            render_with_settings(scene, &settings)
+        };
+        let shown = render_lines(&mut scene, show);
+        let hidden = render_lines(&mut scene, hide);
+        let missing = shown.chunks_exact(4).zip(hidden.chunks_exact(4)).filter(|(a, b)| a != b).count();
+        assert_eq!(missing, 0, "{} pixels of the edges were hidden by their own faces", missing);
+    }
+}
+
+// the faces are all drawn before the lines, so the edges of the back faces
+// don't end up on top of the front ones
+#[test]
+fn cube_filled_wireframe_hidden_lines() {
+    let line_style = LineStyle { width: 2.0, smooth: true, hidden: HiddenLines::Hide, ..line_style_default() };
+    cube_line_style_test("cube_filled_wireframe_hidden_hide", RenderMode::FilledTrianglesAndWireframe, false, line_style);
+}
+
+// the hidden line mode takes out the edges behind the faces without having
+// to ask for it, and can dash them instead
+#[test]
+fn cube_hidden_line() {
+    cube_line_style_test("cube_hidden_line", RenderMode::HiddenLine, false, line_style_default());
+    let dashed = LineStyle { hidden: HiddenLines::Dashed, ..line_style_default() };
+    cube_line_style_test("cube_hidden_line_dashed", RenderMode::HiddenLine, false, dashed);
+}
+
+// just the square sides of the cube without the diagonals across them
+#[test]
+fn cube_wireframe_outline() {
+    let line_style = LineStyle { edges: LineEdges::Outline, ..line_style_default() };
+    cube_line_style_test("cube_wireframe_outline", RenderMode::Wireframe, true, line_style);
+}
+
+// a smooth sphere has no creases, so its outline is just the silhouette,
+// found from the back faces even with culling off
+#[test]
+fn sphere_hidden_line_outline() {
+    let mut scene = scene_new();
+    let mesh = scene_add_mesh(&mut scene, "sphere", "builtin:sphere", get_sphere_mesh(12, 16));
+    let mut sphere = scene_node_new("sphere");
+    sphere.mesh = Some(mesh);
+    sphere.transform.translation.z = 4.0;
+    sphere.transform.rotation = degrees(20.0, 0.0, 0.0);
+    scene_add_node(&mut scene, None, sphere);
+
+    let line_style = LineStyle { edges: LineEdges::Outline, ..line_style_default() };
+    let settings = RenderSettings {
+        render_mode: RenderMode::HiddenLine,
+        backface_culling_enabled: false,
+        line_style,
+        ..render_settings_default()
+    };
+    check_golden("sphere_hidden_line_outline", render_with_settings(&mut scene, &settings));
+}
+
+// a quad leaning away from the camera. the texture coordinates are
+// interpolated straight across the screen rather than perspective correct, so
+// the checks don't shrink into the distance, and this pins that down
+#[test]
+fn textured_quad() {
+    let mut scene = scene_new();
+    let mesh = scene_add_mesh(&mut scene, "quad", "", quad_mesh(1.5, 1.5));
+    let texture = scene_add_texture(&mut scene, "checker", "", checker_texture());
+
+    let mut quad = scene_node_new("quad");
+    quad.mesh = Some(mesh);
+    quad.material.texture = Some(texture);
+    quad.transform.translation.z = 4.0;
+    quad.transform.rotation = degrees(-50.0, 0.0, 15.0);
+    scene_add_node(&mut scene, None, quad);
+
+    check_golden("textured_quad", render(&mut scene, RenderMode::Textured, false));
+}
+
+// quads much bigger than the screen hanging off every edge, so the
+// rasterizer has to clip their rows and columns to the screen
+#[test]
+fn clipped_triangles() {
+    let mut scene = scene_new();
+    let mesh = scene_add_mesh(&mut scene, "quad", "", quad_mesh(3.0, 1.0));
+    let texture = scene_add_texture(&mut scene, "checker", "", checker_texture());
+
+    let placements = [
+        (Vec3 { x: -2.5, y: 1.5, z: 3.0 }, degrees(0.0, 0.0, 20.0)),
+        (Vec3 { x: 2.5, y: -1.5, z: 3.0 }, degrees(0.0, 0.0, -30.0)),
+        (Vec3 { x: 0.0, y: 0.0, z: 6.0 }, degrees(0.0, 0.0, 90.0)),
+    ];
+    for (i, (translation, rotation)) in placements.iter().enumerate() {
+        let mut quad = scene_node_new(&format!("quad {}", i));
+        quad.mesh = Some(mesh);
+        quad.material.texture = Some(texture);
+        quad.transform.translation = *translation;
+        quad.transform.rotation = *rotation;
+        scene_add_node(&mut scene, None, quad);
+    }
+
+    check_golden("clipped_triangles_filled", render(&mut scene, RenderMode::FilledTrianglesAndWireframe, false));
+    check_golden("clipped_triangles_textured", render(&mut scene, RenderMode::Textured, false));
+}
+
+// orange with every other square nearly see through, for the cutout
+fn cutout_texture() -> MeshTexture {
+    let size = 16;
+    let mut pixels = Vec::with_capacity(size * size);
+    for y in 0..size {
+        for x in 0..size {
+            let alpha = if (x / 4 + y / 4) % 2 == 0 { 255 } else { 40 };
+            pixels.push(Color::RGBA(255, 140, 0, alpha));
+        }
+    }
+    MeshTexture { width: size, height: size, pixels }
+}
+
+// a cutout quad and see through quads in each blend mode over a textured
+// wall. the alpha and multiply quads overlap so their order matters, and the
+// additive one pokes out behind the cutout to check it was depth tested
+#[test]
+fn blend_modes() {
+    let mut scene = scene_new();
+    let mesh = scene_add_mesh(&mut scene, "quad", "", quad_mesh(1.0, 1.0));
+    let checker = scene_add_texture(&mut scene, "checker", "", checker_texture());
+    let cutout = scene_add_texture(&mut scene, "cutout", "", cutout_texture());
+
+    let quads = [
+        ("wall", Vec3 { x: 0.0, y: 0.0, z: 6.0 }, 2.2, Color::RGBA(255, 255, 255, 255), Some(checker), BlendMode::Opaque, 1.0),
+        ("cutout", Vec3 { x: 0.9, y: 0.6, z: 4.0 }, 0.8, Color::RGBA(255, 255, 255, 255), Some(cutout), BlendMode::Cutout(0.5), 1.0),
+        ("glass", Vec3 { x: -0.6, y: 0.3, z: 4.5 }, 0.9, Color::RGBA(255, 60, 60, 255), None, BlendMode::Alpha, 0.5),
+        ("tint", Vec3 { x: -0.2, y: -0.5, z: 4.2 }, 0.9, Color::RGBA(80, 80, 255, 255), None, BlendMode::Multiply, 1.0),
+        ("glow", Vec3 { x: 1.0, y: 0.3, z: 4.8 }, 0.7, Color::RGBA(40, 200, 40, 255), None, BlendMode::Additive, 0.8),
+    ];
+    for (name, translation, size, color, texture, blend_mode, opacity) in quads {
+        let mut quad = scene_node_new(name);
+        quad.mesh = Some(mesh);
+        quad.material.color = color;
+        quad.material.texture = texture;
+        quad.material.blend_mode = blend_mode;
+        quad.material.opacity = opacity;
+        quad.transform.translation = translation;
+        quad.transform.scale = Vec3 { x: size, y: size, z: 1.0 };
+        scene_add_node(&mut scene, None, quad);
+    }
+
+    check_golden("blend_modes", render(&mut scene, RenderMode::Textured, false));
+}
+
+
+// two see through quads crossing in an x over a wall. sorting the triangles
+// by their average depth puts one whole quad in front of the other, the
+// a-buffer sorts each pixel so each quad shows in front where it's closer
+#[test]
+fn crossing_glass() {
+    let mut scene = scene_new();
+    let mesh = scene_add_mesh(&mut scene, "quad", "", quad_mesh(1.0, 1.0));
+    let checker = scene_add_texture(&mut scene, "checker", "", checker_texture());
+
+    let quads = [
+        ("wall", Vec3 { x: 0.0, y: 0.0, z: 6.0 }, degrees(0.0, 0.0, 0.0), 2.2, Color::RGBA(255, 255, 255, 255),
+            Some(checker), BlendMode::Opaque, 1.0),
+        ("red", Vec3 { x: 0.0, y: 0.0, z: 4.0 }, degrees(0.0, 50.0, 0.0), 1.2, Color::RGBA(255, 40, 40, 255),
+            None, BlendMode::Alpha, 0.7),
+        ("blue", Vec3 { x: 0.0, y: 0.2, z: 4.0 }, degrees(0.0, -50.0, 0.0), 1.2, Color::RGBA(40, 80, 255, 255),
+            None, BlendMode::Alpha, 0.7),
+    ];
+    for (name, translation, rotation, size, color, texture, blend_mode, opacity) in quads {
+        let mut quad = scene_node_new(name);
+        quad.mesh = Some(mesh);
+        quad.material.color = color;
+        quad.material.texture = texture;
+        quad.material.blend_mode = blend_mode;
+        quad.material.opacity = opacity;
+        quad.transform.translation = translation;
+        quad.transform.rotation = rotation;
+        quad.transform.scale = Vec3 { x: size, y: size, z: 1.0 };
+        scene_add_node(&mut scene, None, quad);
+    }
+
+    let mut settings = RenderSettings {
+        render_mode: RenderMode::Textured,
+        backface_culling_enabled: false,
+        ..render_settings_default()
+    };
+    let sorted = render_with_settings(&mut scene, &settings);
+    settings.transparency = TransparencyMode::ABuffer;
+    let a_buffer = render_with_settings(&mut scene, &settings);
+    assert!(sorted != a_buffer, "the a-buffer should fix the order where the quads cross");
+
+    check_golden("crossing_glass_sorted", sorted);
+    check_golden("crossing_glass_a_buffer", a_buffer);
+
+    // the fragments keep which samples they covered, so the edges of the
+    // glass are smoothed as well
+    settings.anti_aliasing = AntiAliasing::Multisample(4);
+    check_golden("crossing_glass_a_buffer_msaa", render_with_settings(&mut scene, &settings));
+}
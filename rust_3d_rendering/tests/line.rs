@@ -0,0 +1,161 @@
+use rust_3d_rendering::{
+    display::Display,
+    line::{
+        HIDDEN_LINES_NAMES, HiddenLines, LINE_EDGES_NAMES, LinePoint, LineStyle, clip_line, draw_polyline,
+        hidden_lines_from_name, hidden_lines_name, line_edges_from_name, line_edges_name, line_is_hidden,
+        line_style_default,
+    },
+    triangle::draw_depth_triangle,
+    vector::Vec4,
+};
+use sdl2::pixels::Color;
+
+const WHITE: Color = Color::RGBA(255, 255, 255, 255);
+
+fn point(x: f32, y: f32, w: f32) -> LinePoint {
+    LinePoint { x, y, w }
+}
+
+// a triangle well past every side of the screen, at depth w
+fn wall(w: f32) -> [Vec4; 3] {
+    [(-20.0, -20.0), (60.0, -20.0), (-20.0, 60.0)].map(|(x, y)| Vec4 { x, y, z: 0.0, w })
+}
+
+// the red channel of every pixel after drawing the lines on black
+fn draw_lines(points: &[LinePoint], closed: bool, color: Color, style: &LineStyle) -> Vec<u8> {
+    let mut display = Display::new_headless(32, 32);
+    display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+    display.clear_depth_buffer();
+    draw_polyline(&mut display.full_frame(), points, closed, color, style);
+    display.color_buffer_rgba().chunks_exact(4).map(|pixel| pixel[0]).collect()
+}
+
+fn distinct(shades: &[u8]) -> Vec<u8> {
+    let mut shades = shades.to_vec();
+    shades.sort();
+    shades.dedup();
+    shades
+}
+
+#[test]
+fn names_round_trip() {
+    for (name, hidden) in HIDDEN_LINES_NAMES {
+        assert_eq!(hidden_lines_from_name(name), Some(hidden));
+        assert_eq!(hidden_lines_name(hidden), name);
+    }
+    assert_eq!(hidden_lines_from_name("dotted"), None);
+    for (name, edges) in LINE_EDGES_NAMES {
+        assert_eq!(line_edges_from_name(name), Some(edges));
+        assert_eq!(line_edges_name(edges), name);
+    }
+    assert_eq!(line_edges_from_name("silhouette"), None);
+}
+
+#[test]
+fn smooth_lines_get_in_between_shades() {
+    let points = [point(2.0, 3.0, 1.0), point(29.0, 17.0, 1.0)];
+    let stepped = draw_lines(&points, false, WHITE, &line_style_default());
+    assert_eq!(distinct(&stepped), [0, 255]);
+
+    let smooth = draw_lines(&points, false, WHITE, &LineStyle { smooth: true, ..line_style_default() });
+    assert!(distinct(&smooth).len() > 4, "only got {:?}", distinct(&smooth));
+    // every column the line crosses gets about a pixel's worth of it
+    for x in 4..28 {
+        let column: u32 = (0..32).map(|y| smooth[y * 32 + x] as u32).sum();
+        assert!((240..=270).contains(&column), "column {} adds up to {}", x, column);
+    }
+}
+
+// the corners of wide see through lines aren't any darker than the rest,
+// each pixel is only drawn once however many of the lines reach it
+#[test]
+fn wide_lines_join_without_overlapping() {
+    let half_red = Color::RGBA(255, 0, 0, 128);
+    let points = [point(4.0, 4.0, 1.0), point(27.0, 6.0, 1.0), point(10.0, 26.0, 1.0)];
+    let style = LineStyle { width: 4.0, ..line_style_default() };
+    let shades = draw_lines(&points, true, half_red, &style);
+    assert_eq!(distinct(&shades), [0, 128]);
+    // the corner is round, so it reaches out half the width to the side of
+    // the point but not diagonally
+    assert_eq!(shades[4 * 32 + 4], 128);
+    assert_eq!(shades[4 * 32 + 2], 128);
+    assert_eq!(shades[32 + 1], 0);
+}
+
+#[test]
+fn lines_behind_the_depth_buffer_are_dimmed_or_hidden() {
+    assert!(!line_is_hidden(1.0, 0.01));
+
+    let draw = |w: f32, hidden: HiddenLines| draw_behind_wall(w, hidden)[8];
+    // lying on the wall counts as in front of it
+    assert_eq!(draw(4.0, HiddenLines::Hide), 255);
+    assert_eq!(draw(3.0, HiddenLines::Hide), 255);
+    assert_eq!(draw(6.0, HiddenLines::Show), 255);
+    assert_eq!(draw(6.0, HiddenLines::Hide), 0);
+    let dimmed = draw(6.0, HiddenLines::Dim);
+    assert!(dimmed > 0 && dimmed < 128, "dimmed to {}", dimmed);
+}
+
+// the red channel along row 8 after drawing a line across it at depth w,
+// behind a wall at w = 4 over the whole screen
+fn draw_behind_wall(w: f32, hidden: HiddenLines) -> Vec<u8> {
+    let mut display = Display::new_headless(16, 16);
+    display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+    display.clear_depth_buffer();
+    let mut tile = display.full_frame();
+    draw_depth_triangle(&mut tile, &wall(4.0));
+    draw_polyline(&mut tile, &[point(1.0, 8.0, w), point(14.0, 8.0, w)], false, WHITE, &LineStyle {
+        hidden,
+        ..line_style_default()
+    });
+    (0..16).map(|x| tile.pixel_at(x, 8).r).collect()
+}
+
+#[test]
+fn hidden_lines_can_be_dashed() {
+    // the dashes and gaps are 4 pixels each, measured from the left end
+    let dashed = draw_behind_wall(6.0, HiddenLines::Dashed);
+    assert_eq!(dashed, [0, 255, 255, 255, 255, 0, 0, 0, 0, 255, 255, 255, 255, 0, 0, 0]);
+    // the part in front isn't dashed
+    let in_front = draw_behind_wall(3.0, HiddenLines::Dashed);
+    assert_eq!(in_front[1..15], [255; 14]);
+
+    // drawn the other way round the dashes land in the same places
+    let mut display = Display::new_headless(16, 16);
+    display.clear_color_buffer(Color::RGBA(0, 0, 0, 255));
+    display.clear_depth_buffer();
+    let mut tile = display.full_frame();
+    draw_depth_triangle(&mut tile, &wall(4.0));
+    let style = LineStyle { hidden: HiddenLines::Dashed, ..line_style_default() };
+    draw_polyline(&mut tile, &[point(14.0, 8.0, 6.0), point(1.0, 8.0, 6.0)], false, WHITE, &style);
+    let reversed: Vec<u8> = (0..16).map(|x| tile.pixel_at(x, 8).r).collect();
+    assert_eq!(reversed, dashed);
+}
+
+#[test]
+fn lines_are_clipped_to_the_rectangle() {
+    let (min, max) = ((0.0, 0.0), (10.0, 10.0));
+    assert_eq!(clip_line((1.0, 1.0), (9.0, 5.0), min, max), Some((0.0, 1.0)));
+    assert_eq!(clip_line((-10.0, 5.0), (20.0, 5.0), min, max), Some((1.0 / 3.0, 2.0 / 3.0)));
+    assert_eq!(clip_line((5.0, -10.0), (5.0, 5.0), min, max), Some((10.0 / 15.0, 1.0)));
+    assert_eq!(clip_line((-5.0, -5.0), (-1.0, 20.0), min, max), None);
+    assert_eq!(clip_line((12.0, 0.0), (0.0, -12.0), min, max), None);
+    assert_eq!(clip_line((1.0, 1.0), (f32::NAN, 5.0), min, max), None);
+    assert_eq!(clip_line((1.0, 1.0), (f32::INFINITY, 5.0), min, max), None);
+}
+
+// a line with ends far off the screen only steps over the part on it, and
+// the part it does draw lands where the unclipped line would
+#[test]
+fn huge_lines_only_draw_what_is_on_screen() {
+    let points = [point(-1.0e9, 10.0, 1.0), point(1.0e9, 10.0, 1.0)];
+    for smooth in [false, true] {
+        let shades = draw_lines(&points, false, WHITE, &LineStyle { smooth, ..line_style_default() });
+        for x in 0..32 {
+            assert_eq!(shades[10 * 32 + x], 255, "pixel {} with smooth {}", x, smooth);
+        }
+        assert_eq!(shades.iter().filter(|&&shade| shade > 0).count(), 32);
+    }
+    let missing = [point(-1.0e9, -5.0, 1.0), point(1.0e9, -5.0, 1.0)];
+    assert_eq!(distinct(&draw_lines(&missing, false, WHITE, &line_style_default())), [0]);
+}
@@ -0,0 +1,56 @@
+use rust_3d_rendering::material::{
+    BLEND_MODE_NAMES, BlendMode, blend_colors, blend_mode_from_name, blend_mode_is_transparent, blend_mode_name,
+};
+use sdl2::pixels::Color;
+
+const GREY: Color = Color::RGBA(100, 100, 100, 255);
+
+#[test]
+fn opaque_replaces_the_pixel() {
+    let source = Color::RGBA(10, 20, 30, 0);
+    assert_eq!(blend_colors(&source, &GREY, BlendMode::Opaque), Some(source));
+}
+
+#[test]
+fn cutout_skips_pixels_below_the_cutoff() {
+    let source = Color::RGBA(200, 0, 0, 100);
+    assert_eq!(blend_colors(&source, &GREY, BlendMode::Cutout(0.5)), None);
+    assert_eq!(blend_colors(&source, &GREY, BlendMode::Cutout(0.3)), Some(Color::RGBA(200, 0, 0, 255)));
+}
+
+#[test]
+fn alpha_mixes_by_alpha() {
+    let white = Color::RGBA(255, 255, 255, 255);
+    assert_eq!(blend_colors(&Color::RGBA(200, 0, 50, 0), &white, BlendMode::Alpha), Some(white));
+    assert_eq!(blend_colors(&Color::RGBA(200, 0, 50, 255), &white, BlendMode::Alpha), Some(Color::RGBA(200, 0, 50, 255)));
+    assert_eq!(blend_colors(&Color::RGBA(0, 0, 0, 128), &white, BlendMode::Alpha), Some(Color::RGBA(127, 127, 127, 255)));
+}
+
+#[test]
+fn additive_adds_and_saturates() {
+    let source = Color::RGBA(200, 50, 0, 255);
+    assert_eq!(blend_colors(&source, &GREY, BlendMode::Additive), Some(Color::RGBA(255, 150, 100, 255)));
+    let half = Color::RGBA(200, 50, 0, 128);
+    assert_eq!(blend_colors(&half, &GREY, BlendMode::Additive), Some(Color::RGBA(200, 125, 100, 255)));
+}
+
+#[test]
+fn multiply_darkens() {
+    let source = Color::RGBA(255, 128, 0, 255);
+    assert_eq!(blend_colors(&source, &GREY, BlendMode::Multiply), Some(Color::RGBA(100, 50, 0, 255)));
+    // with no alpha the colour has no effect
+    let clear = Color::RGBA(0, 0, 0, 0);
+    assert_eq!(blend_colors(&clear, &GREY, BlendMode::Multiply), Some(GREY));
+}
+
+#[test]
+fn blend_mode_names_round_trip() {
+    for name in BLEND_MODE_NAMES {
+        let blend_mode = blend_mode_from_name(name).unwrap();
+        assert_eq!(blend_mode_name(blend_mode), name);
+    }
+    assert_eq!(blend_mode_from_name("glass"), None);
+    assert!(!blend_mode_is_transparent(BlendMode::Opaque));
+    assert!(!blend_mode_is_transparent(BlendMode::Cutout(0.5)));
+    assert!(blend_mode_is_transparent(BlendMode::Alpha));
+}
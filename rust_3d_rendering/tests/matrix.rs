@@ -0,0 +1,362 @@
+mod common;
+
+use std::f32::consts::PI;
+
+use common::*;
+use rand::{Rng, rngs::StdRng};
+use rust_3d_rendering::{
+    matrix::*,
+    quaternion::{EulerOrder, quat_from_axis_angle, quat_from_euler, quat_to_mat4},
+    transform::{Transform, transform_to_mat4},
+    vector::{Vec3, Vec4, vec3_cross, vec3_dot, vec3_length, vec3_normalize_or_zero, vec4_from_vec3},
+};
+
+// a matrix made of a random scale, rotation and translation, like the world
+// matrix of a scene node. the scale is kept away from zero so it always has
+// an inverse
+fn random_affine(rng: &mut StdRng) -> Mat4 {
+    let mut random_scale = || {
+        let size = rng.gen_range(0.2..5.0);
+        if rng.gen_bool(0.5) { size } else { -size }
+    };
+    let scale = Vec3 { x: random_scale(), y: random_scale(), z: random_scale() };
+    let axis = vec3_normalize_or_zero(&random_vec3(rng));
+    let transform = Transform {
+        translation: random_vec3(rng),
+        rotation: quat_from_axis_angle(&axis, random_angle(rng)),
+        scale,
+    };
+    transform_to_mat4(&transform)
+}
+
+fn point(v: &Vec3) -> Vec4 {
+    vec4_from_vec3(v)
+}
+
+#[test]
+fn identity_leaves_vectors_alone() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let v = random_vec4(&mut rng);
+        assert_eq!(mat4_mul_vec4(&mat4_identity(), &v), v);
+        let m = random_mat4(&mut rng);
+        assert_mat4_close(&mat4_mul_mat4(&m, &mat4_identity()), &m, 0.0);
+        assert_mat4_close(&mat4_mul_mat4(&mat4_identity(), &m), &m, 0.0);
+    }
+}
+
+#[test]
+fn scale_and_translation() {
+    let v = point(&Vec3 { x: 1.0, y: 2.0, z: 3.0 });
+    assert_eq!(
+        mat4_mul_vec4(&mat4_make_scale(2.0, -1.0, 0.5), &v),
+        Vec4 { x: 2.0, y: -2.0, z: 1.5, w: 1.0 });
+    assert_eq!(
+        mat4_mul_vec4(&mat4_make_translation(1.0, -2.0, 3.0), &v),
+        Vec4 { x: 2.0, y: 0.0, z: 6.0, w: 1.0 });
+
+    // directions have w = 0 and so aren't moved by a translation
+    let direction = Vec4 { x: 1.0, y: 2.0, z: 3.0, w: 0.0 };
+    assert_eq!(mat4_mul_vec4(&mat4_make_translation(1.0, -2.0, 3.0), &direction), direction);
+}
+
+#[test]
+fn rotation_quarter_turns() {
+    let x = point(&Vec3 { x: 1.0, y: 0.0, z: 0.0 });
+    let y = point(&Vec3 { x: 0.0, y: 1.0, z: 0.0 });
+    let z = point(&Vec3 { x: 0.0, y: 0.0, z: 1.0 });
+    assert_vec4_close(&mat4_mul_vec4(&mat4_make_rotation_x(PI / 2.0), &y), &z, EPSILON);
+    assert_vec4_close(&mat4_mul_vec4(&mat4_make_rotation_y(PI / 2.0), &z), &x, EPSILON);
+    assert_vec4_close(&mat4_mul_vec4(&mat4_make_rotation_z(PI / 2.0), &x), &y, EPSILON);
+}
+
+#[test]
+fn rotation_preserves_length_and_is_orthonormal() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let angle = random_angle(&mut rng);
+        let v = random_vec3(&mut rng);
+        for rotation in [mat4_make_rotation_x(angle), mat4_make_rotation_y(angle), mat4_make_rotation_z(angle)] {
+            let rotated = mat4_mul_vec4(&rotation, &point(&v));
+            assert_f32_close(vec3_length(&Vec3::from(rotated)), vec3_length(&v), EPSILON);
+            assert_f32_close(mat4_determinant(&rotation), 1.0, EPSILON);
+            // the inverse of a rotation is its transpose
+            assert_mat4_close(&mat4_mul_mat4(&rotation, &mat4_transpose(&rotation)), &mat4_identity(), EPSILON);
+        }
+    }
+}
+
+// the rotation matrices and the quaternions have to turn the same way, and
+// quat_from_euler has to put the rotations together in the order it says
+#[test]
+fn rotation_matches_quaternion() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let (x, y, z) = (random_angle(&mut rng), random_angle(&mut rng), random_angle(&mut rng));
+        let axis_x = Vec3 { x: 1.0, y: 0.0, z: 0.0 };
+        let axis_y = Vec3 { x: 0.0, y: 1.0, z: 0.0 };
+        let axis_z = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+        assert_mat4_close(&quat_to_mat4(&quat_from_axis_angle(&axis_x, x)), &mat4_make_rotation_x(x), 1e-5);
+        assert_mat4_close(&quat_to_mat4(&quat_from_axis_angle(&axis_y, y)), &mat4_make_rotation_y(y), 1e-5);
+        assert_mat4_close(&quat_to_mat4(&quat_from_axis_angle(&axis_z, z)), &mat4_make_rotation_z(z), 1e-5);
+
+        let xyz = mat4_mul_mat4(
+            &mat4_make_rotation_z(z),
+            &mat4_mul_mat4(&mat4_make_rotation_y(y), &mat4_make_rotation_x(x)));
+        assert_mat4_close(&quat_to_mat4(&quat_from_euler(x, y, z, EulerOrder::XYZ)), &xyz, 1e-5);
+    }
+}
+
+#[test]
+fn mul_mat4_matches_mul_vec4() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let a = random_mat4(&mut rng);
+        let b = random_mat4(&mut rng);
+        let v = random_vec4(&mut rng);
+        // (a * b) * v = a * (b * v)
+        let together = mat4_mul_vec4(&mat4_mul_mat4(&a, &b), &v);
+        let one_by_one = mat4_mul_vec4(&a, &mat4_mul_vec4(&b, &v));
+        // a component near zero can be the difference of two big numbers, so
+        // the rounding error goes with the size of the whole vector
+        let size = [one_by_one.x, one_by_one.y, one_by_one.z, one_by_one.w]
+            .iter()
+            .fold(1.0f32, |size, component| size.max(component.abs()));
+        let difference = [
+            together.x - one_by_one.x,
+            together.y - one_by_one.y,
+            together.z - one_by_one.z,
+            together.w - one_by_one.w,
+        ];
+        for component in difference {
+            assert!(component.abs() <= 1e-5 * size, "{:?} != {:?}", together, one_by_one);
+        }
+    }
+}
+
+#[test]
+fn transform_points_matches_mul_vec4() {
+    let mut rng = test_rng();
+    let m = random_mat4(&mut rng);
+    let points: Vec<Vec3> = (0..PROPERTY_CASES).map(|_| random_vec3(&mut rng)).collect();
+
+    // a buffer that already has something in it, which has to be cleared
+    let mut out = vec![Vec4::default(); 3];
+    mat4_transform_points(&m, &points, &mut out);
+    assert_eq!(out.len(), points.len());
+    for (p, transformed) in points.iter().zip(&out) {
+        // the SSE version adds up in the same order, so it matches exactly
+        assert_eq!(*transformed, mat4_mul_vec4(&m, &point(p)));
+    }
+
+    mat4_transform_points(&m, &[], &mut out);
+    assert!(out.is_empty());
+}
+
+// the scalar version is what runs on everything but x86_64, and it has to
+// give exactly what the SSE one does
+#[test]
+fn scalar_transform_points_matches_sse() {
+    let mut rng = test_rng();
+    let m = random_mat4(&mut rng);
+    let points: Vec<Vec3> = (0..PROPERTY_CASES).map(|_| random_vec3(&mut rng)).collect();
+
+    let mut fast = Vec::new();
+    mat4_transform_points(&m, &points, &mut fast);
+    let mut scalar = vec![Vec4::default(); 3];
+    mat4_transform_points_scalar(&m, &points, &mut scalar);
+    assert_eq!(scalar, fast);
+    for (p, transformed) in points.iter().zip(&scalar) {
+        assert_eq!(*transformed, mat4_mul_vec4(&m, &point(p)));
+    }
+}
+
+#[test]
+fn perspective_projection() {
+    let fov = PI / 2.0;
+    let aspect = 0.75;
+    let (znear, zfar) = (0.5, 100.0);
+    let projection = mat4_make_perspective(fov, aspect, znear, zfar);
+
+    // the near and far planes land on 0 and 1 after the divide
+    let near = mat4_mul_vec4_project(&projection, &Vec4 { x: 0.0, y: 0.0, z: znear, w: 1.0 });
+    let far = mat4_mul_vec4_project(&projection, &Vec4 { x: 0.0, y: 0.0, z: zfar, w: 1.0 });
+    assert_f32_close(near.z, 0.0, EPSILON);
+    assert_f32_close(far.z, 1.0, EPSILON);
+    // w keeps the view space z for the depth buffer and texture mapping
+    assert_eq!(near.w, znear);
+    assert_eq!(far.w, zfar);
+
+    // a point on the top edge of a 90 degree fov goes to y = 1, and x is
+    // squashed by the aspect ratio
+    let corner = mat4_mul_vec4_project(&projection, &Vec4 { x: 2.0, y: 2.0, z: 2.0, w: 1.0 });
+    assert_f32_close(corner.y, 1.0, EPSILON);
+    assert_f32_close(corner.x, aspect, EPSILON);
+
+    // nothing to divide by leaves the point as it is
+    let at_eye = mat4_mul_vec4_project(&projection, &Vec4 { x: 1.0, y: 1.0, z: 0.0, w: 0.0 });
+    assert_eq!(at_eye.w, 0.0);
+}
+
+#[test]
+fn transpose() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let a = random_mat4(&mut rng);
+        let b = random_mat4(&mut rng);
+        let t = mat4_transpose(&a);
+        for i in 0..4 {
+            for j in 0..4 {
+                assert_eq!(t.m[i][j], a.m[j][i]);
+            }
+        }
+        // (a * b)^T = b^T * a^T
+        assert_mat4_close(
+            &mat4_transpose(&mat4_mul_mat4(&a, &b)),
+            &mat4_mul_mat4(&mat4_transpose(&b), &mat4_transpose(&a)),
+            1e-5);
+        assert_f32_close(mat4_determinant(&t), mat4_determinant(&a), 1e-3);
+    }
+}
+
+#[test]
+fn determinant() {
+    assert_eq!(mat4_determinant(&mat4_identity()), 1.0);
+    assert_eq!(mat4_determinant(&mat4_make_scale(2.0, 3.0, 4.0)), 24.0);
+    assert_eq!(mat4_determinant(&mat4_make_translation(5.0, -1.0, 2.0)), 1.0);
+    assert_eq!(mat3_determinant(&mat3_identity()), 1.0);
+
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        // det(a * b) = det(a) * det(b)
+        let a = random_affine(&mut rng);
+        let b = random_affine(&mut rng);
+        assert_f32_close(
+            mat4_determinant(&mat4_mul_mat4(&a, &b)),
+            mat4_determinant(&a) * mat4_determinant(&b),
+            1e-3);
+        assert_f32_close(mat3_determinant(&mat3_from_mat4(&a)), mat4_determinant(&a), 1e-4);
+    }
+}
+
+#[test]
+fn inverse_undoes_the_matrix() {
+    let mut rng = test_rng();
+    let mut inverted = 0;
+    for _ in 0..PROPERTY_CASES {
+        let m = random_mat4(&mut rng);
+        // random matrices can be close enough to singular that f32 can't
+        // invert them accurately, so only check the well conditioned ones
+        if mat4_determinant(&m).abs() < 1.0 {
+            continue;
+        }
+        let inverse = mat4_inverse(&m).unwrap();
+        assert_mat4_close(&mat4_mul_mat4(&m, &inverse), &mat4_identity(), 1e-3);
+        assert_mat4_close(&mat4_mul_mat4(&inverse, &m), &mat4_identity(), 1e-3);
+        inverted += 1;
+    }
+    assert!(inverted > PROPERTY_CASES / 2);
+}
+
+#[test]
+fn inverse_of_singular_is_none() {
+    let flat = mat4_make_scale(1.0, 0.0, 1.0);
+    assert!(mat4_inverse(&flat).is_none());
+    assert!(mat4_inverse_affine(&flat).is_none());
+    assert!(mat4_normal_matrix(&flat).is_none());
+    assert!(mat3_inverse(&mat3_from_mat4(&flat)).is_none());
+    assert!(mat4_inverse(&Mat4 { m: [[0.0; 4]; 4] }).is_none());
+}
+
+// singular is judged by the shape of the matrix rather than its size, so a
+// tiny scale or a big one far from the origin still inverts, but rows that
+// are nearly parallel don't
+#[test]
+fn inverse_of_small_and_large_scales() {
+    let mut rng = test_rng();
+    for scale in [1e-3, 1e-2, 1e3] {
+        for _ in 0..PROPERTY_CASES {
+            let m = mat4_mul_mat4(&random_affine(&mut rng), &mat4_make_scale(scale, scale, scale));
+            let translated = mat4_mul_mat4(&mat4_make_translation(500.0, -200.0, 1000.0), &m);
+            for m in [m, translated] {
+                let inverse = mat4_inverse(&m).unwrap();
+                assert_mat4_close(&mat4_mul_mat4(&m, &inverse), &mat4_identity(), 1e-3);
+                let affine = mat4_inverse_affine(&m).unwrap();
+                assert_mat4_close(&mat4_mul_mat4(&m, &affine), &mat4_identity(), 1e-3);
+                assert!(mat4_normal_matrix(&m).is_some());
+            }
+        }
+    }
+
+    let mut nearly_flat = mat4_make_scale(1e-3, 1e-3, 1e-3);
+    nearly_flat.m[0] = [1e-3, 1e-3, 0.0, 0.0];
+    nearly_flat.m[1] = [1e-3, 1e-3 + 1e-10, 0.0, 0.0];
+    assert!(mat4_inverse(&nearly_flat).is_none());
+    assert!(mat3_inverse(&mat3_from_mat4(&nearly_flat)).is_none());
+    assert!(mat4_inverse(&mat4_make_scale(1e3, 0.0, 1e3)).is_none());
+}
+
+#[test]
+fn affine_inverse_matches_general_inverse() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let m = random_affine(&mut rng);
+        let affine = mat4_inverse_affine(&m).unwrap();
+        let general = mat4_inverse(&m).unwrap();
+        assert_mat4_close(&affine, &general, 1e-4);
+        assert_mat4_close(&mat4_mul_mat4(&m, &affine), &mat4_identity(), 1e-4);
+
+        // moving a point there and back gets it back where it started
+        let p = random_vec3(&mut rng);
+        let there_and_back = mat4_mul_vec4(&affine, &mat4_mul_vec4(&m, &point(&p)));
+        assert_vec4_close(&there_and_back, &point(&p), 1e-3);
+    }
+}
+
+#[test]
+fn mat3_inverse_and_mul() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let m = mat3_from_mat4(&random_affine(&mut rng));
+        let inverse = mat3_inverse(&m).unwrap();
+        assert_mat3_close(&mat3_mul_mat3(&m, &inverse), &mat3_identity(), 1e-4);
+
+        let v = random_vec3(&mut rng);
+        let back = mat3_mul_vec3(&inverse, &mat3_mul_vec3(&m, &v));
+        assert_vec3_close(&back, &v, 1e-3);
+        assert_mat3_close(&mat3_transpose(&mat3_transpose(&m)), &m, 0.0);
+    }
+}
+
+#[test]
+fn mat3_from_mat4_drops_translation() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let m = random_affine(&mut rng);
+        let v = random_vec3(&mut rng);
+        // the upper 3x3 part transforms directions, which have w = 0
+        let direction = Vec4 { x: v.x, y: v.y, z: v.z, w: 0.0 };
+        let expected = Vec3::from(mat4_mul_vec4(&m, &direction));
+        assert_vec3_close(&mat3_mul_vec3(&mat3_from_mat4(&m), &v), &expected, EPSILON);
+    }
+}
+
+// a normal moved by the normal matrix has to stay perpendicular to the
+// surface moved by the matrix itself, even with a non uniform scale
+#[test]
+fn normal_matrix_keeps_normals_perpendicular() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let m = random_affine(&mut rng);
+        let normal_matrix = mat4_normal_matrix(&m).unwrap();
+        let upper = mat3_from_mat4(&m);
+
+        let normal = vec3_normalize_or_zero(&random_vec3(&mut rng));
+        // any direction along the surface is perpendicular to the normal
+        let along_surface = vec3_normalize_or_zero(&vec3_cross(&normal, &random_vec3(&mut rng)));
+
+        let moved_normal = vec3_normalize_or_zero(&mat3_mul_vec3(&normal_matrix, &normal));
+        let moved_surface = vec3_normalize_or_zero(&mat3_mul_vec3(&upper, &along_surface));
+        assert!(vec3_dot(&moved_normal, &moved_surface).abs() < 1e-4);
+    }
+}
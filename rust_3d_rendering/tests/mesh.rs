@@ -0,0 +1,60 @@
+use rust_3d_rendering::{
+    mesh::{Mesh, get_cube_mesh, get_sphere_mesh, mesh_edges_new},
+    texture::Tex2,
+    triangle::Face,
+    vector::Vec3,
+};
+use sdl2::pixels::Color;
+
+fn face(a: usize, b: usize, c: usize) -> Face {
+    let uv = Tex2 { u: 0.0, v: 0.0 };
+    Face { a, b, c, a_uv: uv, b_uv: uv, c_uv: uv, color: Color::RGBA(255, 255, 255, 255) }
+}
+
+// every edge of a closed mesh has a face on the other side, which has this
+// face on the other side of the same edge
+fn assert_closed(mesh: &Mesh) {
+    let edges = mesh_edges_new(mesh);
+    for (face_index, neighbors) in edges.neighbors.iter().enumerate() {
+        for neighbor in neighbors {
+            let neighbor = neighbor.expect("an edge without a neighbour");
+            assert_ne!(neighbor, face_index);
+            assert!(edges.neighbors[neighbor].contains(&Some(face_index)));
+        }
+    }
+}
+
+#[test]
+fn cube_creases_are_its_corners_not_its_diagonals() {
+    let cube = get_cube_mesh();
+    assert_closed(&cube);
+    let edges = mesh_edges_new(&cube);
+    for creases in &edges.creases {
+        // each triangle is half of a square side, with the diagonal flat
+        assert_eq!(creases.iter().filter(|&&crease| crease).count(), 2);
+    }
+}
+
+#[test]
+fn smooth_sphere_has_no_creases() {
+    let sphere = get_sphere_mesh(12, 16);
+    assert_closed(&sphere);
+    let edges = mesh_edges_new(&sphere);
+    assert!(edges.creases.iter().all(|creases| creases == &[false; 3]));
+}
+
+#[test]
+fn open_borders_have_no_neighbours() {
+    let quad = Mesh {
+        vertices: vec![
+            Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+            Vec3 { x: 1.0, y: 0.0, z: 0.0 },
+            Vec3 { x: 1.0, y: 1.0, z: 0.0 },
+            Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+        ],
+        faces: vec![face(1, 2, 3), face(1, 3, 4)],
+    };
+    let edges = mesh_edges_new(&quad);
+    assert_eq!(edges.neighbors, [[None, None, Some(1)], [Some(0), None, None]]);
+    assert_eq!(edges.creases, [[false; 3]; 2]);
+}
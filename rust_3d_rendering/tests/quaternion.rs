@@ -0,0 +1,122 @@
+mod common;
+
+use std::f32::consts::PI;
+
+use common::*;
+use rand::{Rng, rngs::StdRng};
+use rust_3d_rendering::{
+    quaternion::{
+        EulerOrder, Quat, quat_dot, quat_from_axis_angle, quat_from_euler, quat_identity, quat_length, quat_slerp,
+        quat_to_euler_xyz, quat_to_mat4,
+    },
+    vector::Vec3,
+};
+
+const Z_AXIS: Vec3 = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+
+fn negate(q: &Quat) -> Quat {
+    Quat { x: -q.x, y: -q.y, z: -q.z, w: -q.w }
+}
+
+// q and -q are the same rotation, so compare what they do rather than their
+// numbers
+fn assert_same_rotation(a: &Quat, b: &Quat, epsilon: f32) {
+    assert_mat4_close(&quat_to_mat4(a), &quat_to_mat4(b), epsilon);
+}
+
+fn random_rotation(rng: &mut StdRng) -> Quat {
+    quat_from_euler(random_angle(rng), random_angle(rng), random_angle(rng), EulerOrder::XYZ)
+}
+
+#[test]
+fn euler_angles_round_trip() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        // inside the range quat_to_euler_xyz gives back, away from the wrap
+        // around at +-180 degrees and the gimbal lock at +-90
+        let angles = Vec3 { x: rng.gen_range(-3.0..3.0), y: rng.gen_range(-1.5..1.5), z: rng.gen_range(-3.0..3.0) };
+        let q = quat_from_euler(angles.x, angles.y, angles.z, EulerOrder::XYZ);
+        assert_vec3_close(&quat_to_euler_xyz(&q), &angles, 1e-3);
+    }
+}
+
+#[test]
+fn euler_angles_at_gimbal_lock_give_the_same_rotation() {
+    let mut rng = test_rng();
+    for y in [PI / 2.0, -PI / 2.0] {
+        // with no z there's only one way to write it
+        let q = quat_from_euler(0.4, y, 0.0, EulerOrder::XYZ);
+        assert_vec3_close(&quat_to_euler_xyz(&q), &Vec3 { x: 0.4, y, z: 0.0 }, 1e-3);
+
+        // otherwise x and z turn about the same axis, so all of it ends up
+        // in x but the rotation is the same
+        for _ in 0..PROPERTY_CASES {
+            let (x, z) = (rng.gen_range(-1.5..1.5), rng.gen_range(-1.5..1.5));
+            let q = quat_from_euler(x, y, z, EulerOrder::XYZ);
+            let angles = quat_to_euler_xyz(&q);
+            assert_f32_close(angles.y, y, 1e-3);
+            assert_eq!(angles.z, 0.0);
+            assert_same_rotation(&quat_from_euler(angles.x, angles.y, angles.z, EulerOrder::XYZ), &q, 1e-3);
+        }
+    }
+}
+
+#[test]
+fn slerp_starts_and_ends_at_its_rotations() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let (a, b) = (random_rotation(&mut rng), random_rotation(&mut rng));
+        assert_same_rotation(&quat_slerp(&a, &b, 0.0), &a, 1e-4);
+        assert_same_rotation(&quat_slerp(&a, &b, 1.0), &b, 1e-4);
+        let t = rng.gen_range(0.0..1.0);
+        assert_f32_close(quat_length(&quat_slerp(&a, &b, t)), 1.0, 1e-4);
+    }
+}
+
+#[test]
+fn slerp_halfway_is_half_the_angle() {
+    let a = quat_identity();
+    let b = quat_from_axis_angle(&Z_AXIS, PI / 2.0);
+    assert_same_rotation(&quat_slerp(&a, &b, 0.5), &quat_from_axis_angle(&Z_AXIS, PI / 4.0), EPSILON);
+    assert_same_rotation(&quat_slerp(&a, &b, 0.25), &quat_from_axis_angle(&Z_AXIS, PI / 8.0), EPSILON);
+}
+
+// -b is the same rotation as b, so slerp takes the short way round either way
+#[test]
+fn slerp_takes_the_shortest_path() {
+    let a = quat_identity();
+    let b = negate(&quat_from_axis_angle(&Z_AXIS, PI / 2.0));
+    assert!(quat_dot(&a, &b) < 0.0);
+    assert_same_rotation(&quat_slerp(&a, &b, 0.5), &quat_from_axis_angle(&Z_AXIS, PI / 4.0), EPSILON);
+
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let (a, b) = (random_rotation(&mut rng), random_rotation(&mut rng));
+        let t = rng.gen_range(0.0..1.0);
+        assert_same_rotation(&quat_slerp(&a, &negate(&b), t), &quat_slerp(&a, &b, t), 1e-4);
+    }
+}
+
+#[test]
+fn slerp_between_q_and_minus_q_stays_put() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let q = random_rotation(&mut rng);
+        let t = rng.gen_range(0.0..1.0);
+        assert_same_rotation(&quat_slerp(&q, &negate(&q), t), &q, 1e-4);
+    }
+}
+
+// rotations too close together for the sin in slerp go through the linear
+// fallback, which still has to land in between and stay a unit quaternion
+#[test]
+fn slerp_of_nearly_equal_rotations_is_still_in_between() {
+    let a = quat_from_axis_angle(&Z_AXIS, 0.5);
+    let b = quat_from_axis_angle(&Z_AXIS, 0.51);
+    assert!(quat_dot(&a, &b) > 0.9995);
+    for t in [0.0, 0.25, 0.5, 1.0] {
+        let q = quat_slerp(&a, &b, t);
+        assert_f32_close(quat_length(&q), 1.0, EPSILON);
+        assert_same_rotation(&q, &quat_from_axis_angle(&Z_AXIS, 0.5 + 0.01 * t), EPSILON);
+    }
+}
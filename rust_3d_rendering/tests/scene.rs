@@ -0,0 +1,106 @@
+mod common;
+
+use common::*;
+use rust_3d_rendering::{
+    matrix::{Mat4, mat4_identity, mat4_mul_mat4, mat4_mul_vec4},
+    quaternion::{EulerOrder, quat_from_euler},
+    scene::{Scene, scene_add_node, scene_new, scene_node_new, scene_update_world_matrices},
+    transform::transform_to_mat4,
+    vector::{Vec3, Vec4},
+};
+
+// a body with an arm with a hand on the end of it, and a lamp that's a root
+// of its own. returns the scene and the indexes of the body, arm and hand
+fn arm_scene() -> (Scene, [usize; 3]) {
+    let mut scene = scene_new();
+    let mut body = scene_node_new("body");
+    body.transform.translation = Vec3 { x: 1.0, y: 2.0, z: 3.0 };
+    body.transform.rotation = quat_from_euler(0.0, 0.5, 0.0, EulerOrder::XYZ);
+    body.transform.scale = Vec3 { x: 2.0, y: 2.0, z: 2.0 };
+    let body = scene_add_node(&mut scene, None, body);
+
+    let mut arm = scene_node_new("arm");
+    arm.transform.translation = Vec3 { x: 1.0, y: 0.0, z: 0.0 };
+    arm.transform.rotation = quat_from_euler(0.0, 0.0, 0.8, EulerOrder::XYZ);
+    let arm = scene_add_node(&mut scene, Some(body), arm);
+
+    let mut hand = scene_node_new("hand");
+    hand.transform.translation = Vec3 { x: 0.5, y: 0.0, z: 0.0 };
+    hand.transform.scale = Vec3 { x: 0.5, y: 1.0, z: 1.0 };
+    let hand = scene_add_node(&mut scene, Some(arm), hand);
+
+    let mut lamp = scene_node_new("lamp");
+    lamp.transform.translation = Vec3 { x: -4.0, y: 0.0, z: 0.0 };
+    scene_add_node(&mut scene, None, lamp);
+
+    scene_update_world_matrices(&mut scene);
+    (scene, [body, arm, hand])
+}
+
+#[test]
+fn world_matrices_are_the_parent_times_the_local_transform() {
+    let (scene, [body, arm, hand]) = arm_scene();
+    let local = |index: usize| transform_to_mat4(&scene.nodes[index].transform);
+    let world = |index: usize| scene.nodes[index].world_matrix;
+
+    assert_mat4_close(&world(body), &local(body), EPSILON);
+    assert_mat4_close(&world(arm), &mat4_mul_mat4(&world(body), &local(arm)), EPSILON);
+    assert_mat4_close(&world(hand), &mat4_mul_mat4(&world(arm), &local(hand)), EPSILON);
+    let all_the_way_down = mat4_mul_mat4(&local(body), &mat4_mul_mat4(&local(arm), &local(hand)));
+    assert_mat4_close(&world(hand), &all_the_way_down, EPSILON);
+
+    // the other root isn't moved by the body
+    let lamp = &scene.nodes[3];
+    assert_mat4_close(&lamp.world_matrix, &transform_to_mat4(&lamp.transform), EPSILON);
+}
+
+#[test]
+fn the_hand_is_where_the_arm_puts_it() {
+    let (scene, [_, _, hand]) = arm_scene();
+    let origin = Vec4 { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+    let world = mat4_mul_vec4(&scene.nodes[hand].world_matrix, &origin);
+
+    // the hand sits 0.5 along the arm, which is turned 0.8 about z and starts
+    // 1 along the body, which is scaled by 2, turned 0.5 about y and moved
+    let (sin_z, cos_z) = 0.8f32.sin_cos();
+    let in_body = Vec3 { x: 1.0 + 0.5 * cos_z, y: 0.5 * sin_z, z: 0.0 };
+    let (sin_y, cos_y) = 0.5f32.sin_cos();
+    let expected = Vec3 {
+        x: 1.0 + 2.0 * (in_body.x * cos_y + in_body.z * sin_y),
+        y: 2.0 + 2.0 * in_body.y,
+        z: 3.0 + 2.0 * (-in_body.x * sin_y + in_body.z * cos_y),
+    };
+    assert_vec3_close(&Vec3 { x: world.x, y: world.y, z: world.z }, &expected, 1e-4);
+}
+
+#[test]
+fn moving_a_parent_moves_its_children() {
+    let (mut scene, [body, arm, hand]) = arm_scene();
+    let before: Vec<Mat4> = scene.nodes.iter().map(|node| node.world_matrix).collect();
+
+    scene.nodes[body].transform.translation.y += 10.0;
+    scene_update_world_matrices(&mut scene);
+
+    // only the translation changes, by the same amount all the way down
+    for index in [body, arm, hand] {
+        let mut moved = before[index];
+        moved.m[1][3] += 10.0;
+        assert_mat4_close(&scene.nodes[index].world_matrix, &moved, 1e-4);
+    }
+    assert_mat4_close(&scene.nodes[3].world_matrix, &before[3], EPSILON);
+
+    // turning the arm turns the hand but not the body
+    let body_before = scene.nodes[body].world_matrix;
+    scene.nodes[arm].transform.rotation = quat_from_euler(0.0, 0.0, -0.8, EulerOrder::XYZ);
+    scene_update_world_matrices(&mut scene);
+    assert_mat4_close(&scene.nodes[body].world_matrix, &body_before, EPSILON);
+    let local_hand = transform_to_mat4(&scene.nodes[hand].transform);
+    assert_mat4_close(
+        &scene.nodes[hand].world_matrix, &mat4_mul_mat4(&scene.nodes[arm].world_matrix, &local_hand), EPSILON);
+    assert!((scene.nodes[hand].world_matrix.m[1][3] - 10.0 - before[hand].m[1][3]).abs() > 0.5);
+
+    // and a node put back at the origin with nothing above it is the identity
+    scene.nodes[3].transform.translation.x = 0.0;
+    scene_update_world_matrices(&mut scene);
+    assert_mat4_close(&scene.nodes[3].world_matrix, &mat4_identity(), EPSILON);
+}
@@ -0,0 +1,147 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use rust_3d_rendering::{
+    display::RenderMode,
+    material::BlendMode,
+    scene::scene_find_node,
+    scene_file::{load_scene_file, parse_scene, save_scene_file, saved_scene_path, scene_file_to_string},
+};
+
+const SCENE: &str = r#"
+# everything the file can hold, apart from assets on disk
+[render]
+mode = "filled_wireframe"
+backface_culling = false
+line_width = 2.5
+
+[camera]
+position = [0, 1, -5]
+rotation = [10, 0, 0]
+fov = 75
+
+[[light]]
+direction = [0, 0, 1]
+
+[[mesh]]
+name = "cube"
+path = "builtin:cube"
+
+# a child can come before its parent
+[[node]]
+name = "lid"
+parent = "box"
+mesh = "cube"
+blend = "cutout"
+alpha_cutoff = 0.25
+translation = [0, 1, 0]
+
+[[node]]
+name = "box"
+mesh = "cube"
+color = [255, 128, 0, 200]
+rotation = [0, 45, 0]
+scale = [1, 2, 1]
+spin = true
+"#;
+
+fn out_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("scene_file").join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn saved_scenes_load_back_the_same() {
+    let (scene, settings) = parse_scene(SCENE, "scene.toml", Path::new("")).unwrap();
+    assert_eq!(settings.render_mode, RenderMode::FilledTrianglesAndWireframe);
+    assert!(!settings.backface_culling_enabled);
+    let lid = scene_find_node(&scene, "lid").unwrap();
+    assert_eq!(scene.nodes[lid].material.blend_mode, BlendMode::Cutout(0.25));
+
+    let saved = scene_file_to_string(&scene, &settings, Path::new(""));
+    let (reloaded, reloaded_settings) = parse_scene(&saved, "saved.toml", Path::new("")).unwrap();
+    assert_eq!(reloaded_settings.render_mode, settings.render_mode);
+    assert_eq!(reloaded_settings.line_style, settings.line_style);
+    assert_eq!(reloaded.nodes.len(), scene.nodes.len());
+    let reloaded_lid = scene_find_node(&reloaded, "lid").unwrap();
+    assert_eq!(reloaded.nodes[reloaded_lid].material.blend_mode, BlendMode::Cutout(0.25));
+    // only the nodes marked to spin do, whatever they're called
+    let spinning: Vec<&str> = reloaded.nodes.iter().filter(|node| node.spin).map(|node| node.name.as_str()).collect();
+    assert_eq!(spinning, ["box"]);
+    // saving it again gives exactly the same file
+    assert_eq!(scene_file_to_string(&reloaded, &reloaded_settings, Path::new("")), saved);
+}
+
+#[test]
+fn errors_give_the_line_they_are_on() {
+    let cases = [
+        ("[render]\nmode = \"filled\"\nline_width = \"wide\"\n", "a.toml:3: 'line_width' should be a number"),
+        ("\n\n[render\n", "a.toml:3: section header"),
+        ("[camera]\nposition = [0, 0]\n", "a.toml:2: 'position' should be a list of 3 numbers"),
+        ("[[node]]\nname = \"a\"\ncolor = [300, 0, 0]\n", "a.toml:3: colour values"),
+        ("[[node]]\nname = \"a\"\nparent = \"nobody\"\n", "a.toml:3: node 'a' has parent 'nobody'"),
+        ("[render]\nmode = \"filled\"\nmode = \"textured\"\n", "a.toml:3: 'mode' is set more than once"),
+    ];
+    for (text, expected) in cases {
+        let error = parse_scene(text, "a.toml", Path::new("")).err().unwrap();
+        assert!(error.starts_with(expected), "{:?} gave {}", text, error);
+    }
+}
+
+#[test]
+fn unknown_keys_and_sections_are_errors() {
+    let error = parse_scene("[render]\nmode = \"filled\"\nshadows = true\n", "a.toml", Path::new("")).err().unwrap();
+    assert!(error.starts_with("a.toml:3: unknown key 'shadows' in [render], expected one of:"), "{}", error);
+
+    let error = parse_scene("[[node]]\nname = \"a\"\nsize = 2\n", "a.toml", Path::new("")).err().unwrap();
+    assert!(error.starts_with("a.toml:3: unknown key 'size' in [[node]]"), "{}", error);
+
+    let error = parse_scene("\n[lights]\n", "a.toml", Path::new("")).err().unwrap();
+    assert!(error.starts_with("a.toml:2: unknown section [lights]"), "{}", error);
+}
+
+// asset paths are relative to the scene file they're in, so saving the scene
+// somewhere else has to rewrite them
+#[test]
+fn asset_paths_are_saved_relative_to_the_new_file() {
+    let dir = out_dir("asset_paths");
+    fs::create_dir_all(dir.join("models")).unwrap();
+    fs::create_dir_all(dir.join("scenes")).unwrap();
+    fs::create_dir_all(dir.join("saved/deeper")).unwrap();
+    fs::write(dir.join("models/triangle.obj"), "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+    image::RgbaImage::new(2, 2).save(dir.join("models/red.png")).unwrap();
+    fs::write(dir.join("scenes/scene.toml"), "\
+[[texture]]
+name = \"red\"
+path = \"../models/red.png\"
+
+[[mesh]]
+name = \"triangle\"
+path = \"../models/triangle.obj\"
+
+[[node]]
+name = \"triangle\"
+mesh = \"triangle\"
+texture = \"red\"
+").unwrap();
+
+    let scene_path = dir.join("scenes/scene.toml").to_string_lossy().into_owned();
+    let (scene, settings) = load_scene_file(&scene_path).unwrap();
+    let saved_path = dir.join("saved/deeper/scene.toml").to_string_lossy().into_owned();
+    save_scene_file(&saved_path, &scene, &settings).unwrap();
+
+    let saved = fs::read_to_string(&saved_path).unwrap();
+    assert!(saved.contains("path = \"../../models/triangle.obj\""), "{}", saved);
+    assert!(saved.contains("path = \"../../models/red.png\""), "{}", saved);
+    let (reloaded, _) = load_scene_file(&saved_path).unwrap();
+    assert_eq!(reloaded.meshes[0].faces.len(), 1);
+    assert_eq!(reloaded.textures.len(), 1);
+}
+
+#[test]
+fn saving_does_not_overwrite_the_loaded_file() {
+    assert_eq!(saved_scene_path("scenes/crate.toml"), "scenes/crate_saved.toml");
+    assert_eq!(saved_scene_path("crate"), "crate_saved.toml");
+    assert_ne!(saved_scene_path("scene.toml"), "scene.toml");
+}
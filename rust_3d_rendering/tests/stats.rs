@@ -0,0 +1,167 @@
+use std::collections::{HashMap, HashSet};
+
+use rust_3d_rendering::{
+    display::{Display, RenderMode},
+    mesh::get_cube_mesh,
+    pipeline::{assemble_scene_triangles, sort_triangles_by_depth, vertex_cache_new},
+    quaternion::{EulerOrder, quat_from_euler},
+    raster::render_triangles,
+    scene::{Scene, scene_add_mesh, scene_add_node, scene_new, scene_node_new, scene_update_world_matrices},
+    stats::{FRAME_STATS_CSV_HEADER, frame_stats_csv_row, frame_stats_new, overdraw},
+    vector::Vec3,
+};
+
+const WIDTH: usize = 160;
+const HEIGHT: usize = 120;
+
+fn cube_scene(z: f32) -> Scene {
+    let mut scene = scene_new();
+    let mesh = scene_add_mesh(&mut scene, "cube", "builtin:cube", get_cube_mesh());
+    let mut cube = scene_node_new("cube");
+    cube.mesh = Some(mesh);
+    cube.transform.translation.z = z;
+    cube.transform.rotation = quat_from_euler(0.5, 0.7, 0.0, EulerOrder::XYZ);
+    scene_add_node(&mut scene, None, cube);
+    scene_update_world_matrices(&mut scene);
+    scene
+}
+
+#[test]
+fn csv_rows_match_the_header() {
+    let row = frame_stats_csv_row(&frame_stats_new());
+    assert_eq!(row.split(',').count(), FRAME_STATS_CSV_HEADER.split(',').count());
+}
+
+#[test]
+fn triangle_counts_add_up() {
+    let scene = cube_scene(5.0);
+    let mut cache = vertex_cache_new(8);
+    let mut triangles = Vec::new();
+
+    let stats = assemble_scene_triangles(&mut triangles, &scene, &mut cache, WIDTH, HEIGHT, true);
+    assert_eq!(stats.triangles_submitted, 12);
+    assert_eq!(stats.triangles_drawn, triangles.len());
+    assert_eq!(stats.triangles_culled + stats.triangles_drawn, 12);
+    // a cube only ever shows up to three of its six sides
+    assert!(stats.triangles_culled >= 6);
+    assert_eq!(stats.triangles_offscreen, 0);
+
+    let stats = assemble_scene_triangles(&mut triangles, &scene, &mut cache, WIDTH, HEIGHT, false);
+    assert_eq!(stats.triangles_culled, 0);
+    assert_eq!(stats.triangles_drawn, 12);
+}
+
+#[test]
+fn triangles_past_the_screen_edge_are_counted_offscreen() {
+    // close enough that the cube covers more than the whole screen
+    let scene = cube_scene(1.8);
+    let mut cache = vertex_cache_new(8);
+    let mut triangles = Vec::new();
+    let stats = assemble_scene_triangles(&mut triangles, &scene, &mut cache, WIDTH, HEIGHT, true);
+    assert!(stats.triangles_offscreen > 0);
+    assert!(stats.triangles_offscreen <= stats.triangles_drawn);
+}
+
+// with culling the visible faces of a cube don't overlap, so every pixel is
+// written once. without it the back faces get drawn too
+#[test]
+fn overdraw_counts_pixels_drawn_over() {
+    let scene = cube_scene(5.0);
+    let mut cache = vertex_cache_new(8);
+    let mut triangles = Vec::new();
+    let mut pixels_written = Vec::new();
+    for backface_culling_enabled in [true, false] {
+        assemble_scene_triangles(&mut triangles, &scene, &mut cache, WIDTH, HEIGHT, backface_culling_enabled);
+        sort_triangles_by_depth(&mut triangles);
+        let mut display = Display::new_headless(WIDTH, HEIGHT);
+        pixels_written.push(
+            render_triangles(&mut display, &triangles, RenderMode::FilledTriangles, &scene.textures, 1).pixels_written);
+    }
+    assert!(pixels_written[0] > 0);
+    assert!(pixels_written[1] > pixels_written[0]);
+    assert!(overdraw(pixels_written[0], WIDTH, HEIGHT) < 1.0);
+    assert_eq!(overdraw(WIDTH * HEIGHT * 2, WIDTH, HEIGHT), 2.0);
+}
+
+// the triangle index view colours each triangle by the face it came from, so
+// the colours don't change as the depth sort moves the triangles around
+#[test]
+fn triangles_keep_their_face_index_through_the_sort() {
+    let scene = cube_scene(5.0);
+    let mut cache = vertex_cache_new(8);
+    let mut triangles = Vec::new();
+    assemble_scene_triangles(&mut triangles, &scene, &mut cache, WIDTH, HEIGHT, false);
+    let face_indexes: Vec<usize> = triangles.iter().map(|triangle| triangle.face_index).collect();
+    assert_eq!(face_indexes, (0..12).collect::<Vec<usize>>());
+
+    let assembled = triangles.clone();
+    sort_triangles_by_depth(&mut triangles);
+    assert!(triangles.iter().enumerate().any(|(index, triangle)| triangle.face_index != index));
+    for triangle in &triangles {
+        assert_eq!(triangle.points, assembled[triangle.face_index].points);
+    }
+}
+
+// two copies of the cube have the same face indexes, but the triangle index
+// view still gives each of them its own colours
+#[test]
+fn the_same_face_of_two_nodes_gets_different_colours() {
+    let mut scene = scene_new();
+    let mesh = scene_add_mesh(&mut scene, "cube", "builtin:cube", get_cube_mesh());
+    for x in [-2.5, 2.5] {
+        let mut cube = scene_node_new("cube");
+        cube.mesh = Some(mesh);
+        cube.transform.translation = Vec3 { x, y: 0.0, z: 9.0 };
+        cube.transform.rotation = quat_from_euler(0.5, 0.7, 0.0, EulerOrder::XYZ);
+        scene_add_node(&mut scene, None, cube);
+    }
+    scene_update_world_matrices(&mut scene);
+
+    let mut cache = vertex_cache_new(8);
+    let mut triangles = Vec::new();
+    assemble_scene_triangles(&mut triangles, &scene, &mut cache, WIDTH, HEIGHT, true);
+    let node_indexes: HashSet<usize> = triangles.iter().map(|triangle| triangle.node_index).collect();
+    assert_eq!(node_indexes, HashSet::from([0, 1]));
+
+    let mut display = Display::new_headless(WIDTH, HEIGHT);
+    render_triangles(&mut display, &triangles, RenderMode::TriangleIndex, &scene.textures, 1);
+    let rgba = display.color_buffer_rgba();
+    let colors_between = |from_x: usize, to_x: usize| {
+        let mut colors = HashSet::new();
+        for y in 0..HEIGHT {
+            for x in from_x..to_x {
+                let pixel = &rgba[(y * WIDTH + x) * 4..(y * WIDTH + x) * 4 + 3];
+                if pixel != [0, 0, 0] {
+                    colors.insert(pixel.to_vec());
+                }
+            }
+        }
+        colors
+    };
+    let left = colors_between(0, WIDTH / 2);
+    let right = colors_between(WIDTH / 2, WIDTH);
+    assert!(left.len() >= 3 && right.len() >= 3);
+    assert!(left.is_disjoint(&right));
+}
+
+// every edge between two faces that are drawn belongs to just one of them,
+// so the wireframe modes don't draw it twice
+#[test]
+fn each_drawn_edge_has_one_owner() {
+    let scene = cube_scene(5.0);
+    let mut cache = vertex_cache_new(8);
+    let mut triangles = Vec::new();
+    for backface_culling_enabled in [true, false] {
+        assemble_scene_triangles(&mut triangles, &scene, &mut cache, WIDTH, HEIGHT, backface_culling_enabled);
+        let mut owners: HashMap<[(u32, u32); 2], usize> = HashMap::new();
+        for triangle in &triangles {
+            for edge in 0..3 {
+                let [from, to] = [edge, (edge + 1) % 3]
+                    .map(|corner| (triangle.points[corner].x.to_bits(), triangle.points[corner].y.to_bits()));
+                let owned = triangle.owned_edges[edge] as usize;
+                *owners.entry([from.min(to), from.max(to)]).or_default() += owned;
+            }
+        }
+        assert!(owners.values().all(|&count| count == 1), "{:?}", owners);
+    }
+}
@@ -0,0 +1,158 @@
+use rust_3d_rendering::{
+    display::RenderMode,
+    line::{HiddenLines, LineEdges, LineStyle, line_style_default},
+    material::BlendMode,
+    svg::svg_from_triangles,
+    texture::Tex2,
+    triangle::Triangle,
+    vector::{Vec3, Vec4},
+};
+use sdl2::pixels::Color;
+
+const RED: Color = Color::RGBA(255, 0, 0, 255);
+const GREEN: Color = Color::RGBA(0, 255, 0, 255);
+
+fn triangle(points: [(f32, f32); 3], w: f32, color: Color) -> Triangle {
+    Triangle {
+        points: points.map(|(x, y)| Vec4 { x, y, z: 0.0, w }),
+        texcoords: [Tex2 { u: 0.0, v: 0.0 }; 3],
+        color,
+        normal: Vec3 { x: 0.0, y: 0.0, z: -1.0 },
+        avg_depth: w,
+        texture: None,
+        blend_mode: BlendMode::Opaque,
+        outline_edges: [true; 3],
+        owned_edges: [true; 3],
+        face_index: 0,
+        node_index: 0,
+    }
+}
+
+// a far red triangle with a near green one partly over it, back to front
+// like sort_triangles_by_depth leaves them
+fn two_triangles() -> Vec<Triangle> {
+    vec![
+        triangle([(10.0, 10.0), (90.0, 10.0), (10.0, 90.0)], 4.0, RED),
+        triangle([(30.0, 30.0), (95.5, 30.0), (30.0, 95.25)], 2.0, GREEN),
+    ]
+}
+
+fn render(triangles: &[Triangle], render_mode: RenderMode, line_style: &LineStyle) -> String {
+    svg_from_triangles(triangles, 100, 100, render_mode, line_style).unwrap()
+}
+
+#[test]
+fn filled_triangles_are_polygons_in_painters_order() {
+    let svg = render(&two_triangles(), RenderMode::FilledTriangles, &line_style_default());
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.contains("width=\"100\" height=\"100\""));
+    assert_eq!(svg.matches("<polygon").count(), 2);
+    assert_eq!(svg.matches("<path").count(), 0);
+    let red = svg.find("fill=\"#ff0000\"").unwrap();
+    let green = svg.find("fill=\"#00ff00\"").unwrap();
+    assert!(red < green, "the far triangle should be painted first");
+    // trailing zeros are left off
+    assert!(svg.contains("95.5,30 30,95.25"), "{}", svg);
+}
+
+#[test]
+fn wireframes_are_strokes_without_fills() {
+    let style = LineStyle { width: 2.5, ..line_style_default() };
+    let svg = render(&two_triangles(), RenderMode::Wireframe, &style);
+    assert_eq!(svg.matches("<polygon").count(), 0);
+    assert_eq!(svg.matches("<path").count(), 2);
+    assert!(svg.contains("stroke-width=\"2.5\""));
+
+    let svg = render(&two_triangles(), RenderMode::FilledTrianglesAndWireframe, &style);
+    assert_eq!(svg.matches("<polygon").count(), 2);
+    assert_eq!(svg.matches("<path").count(), 2);
+}
+
+#[test]
+fn hidden_lines_are_covered_by_the_background() {
+    let hide = LineStyle { hidden: HiddenLines::Hide, ..line_style_default() };
+    let svg = render(&two_triangles(), RenderMode::Wireframe, &hide);
+    assert_eq!(svg.matches("<polygon").count(), 2);
+    assert_eq!(svg.matches("fill=\"#000000\" stroke=\"none\"").count(), 2);
+    // the hidden line mode hides them even when the style shows them
+    let svg = render(&two_triangles(), RenderMode::HiddenLine, &line_style_default());
+    assert_eq!(svg.matches("<polygon").count(), 2);
+
+    // dimmed and dashed ones are drawn again over the polygons that hid them
+    let dashed = LineStyle { hidden: HiddenLines::Dashed, dash_length: 6.0, ..line_style_default() };
+    let svg = render(&two_triangles(), RenderMode::Wireframe, &dashed);
+    assert_eq!(svg.matches("<path").count(), 4);
+    let dashes = svg.find("stroke-dasharray=\"6\"").unwrap();
+    assert!(dashes > svg.rfind("<polygon").unwrap());
+
+    // see through triangles don't hide anything
+    let mut triangles = two_triangles();
+    triangles[1].blend_mode = BlendMode::Alpha;
+    let svg = render(&triangles, RenderMode::Wireframe, &hide);
+    assert_eq!(svg.matches("<polygon").count(), 1);
+}
+
+// in the filled modes the faces would bury the hidden lines, so they go after
+// every fill. an edge two triangles share is only drawn there once
+#[test]
+fn hidden_lines_go_over_the_fills() {
+    for render_mode in [RenderMode::FilledTrianglesAndWireframe, RenderMode::TexturedAndWireframe] {
+        let dim = LineStyle { hidden: HiddenLines::Dim, ..line_style_default() };
+        let mut triangles = two_triangles();
+        triangles[1].owned_edges = [true, false, true];
+        let svg = render(&triangles, render_mode, &dim);
+        let dimmed = svg.find("stroke-opacity=\"0.25\"").unwrap();
+        assert!(dimmed > svg.rfind("<polygon").unwrap(), "{}", svg);
+        let hidden_pass = &svg[dimmed..];
+        assert_eq!(hidden_pass.matches("<path").count(), 2);
+        // the whole of the first triangle, and two separate edges of the second
+        assert_eq!(hidden_pass.matches('L').count(), 2 + 2);
+    }
+}
+
+#[test]
+fn outline_edges_leave_out_the_inner_ones() {
+    let mut triangles = two_triangles();
+    triangles[0].outline_edges = [true, false, false];
+    triangles[1].outline_edges = [false; 3];
+    let style = LineStyle { edges: LineEdges::Outline, ..line_style_default() };
+    let svg = render(&triangles, RenderMode::Wireframe, &style);
+    assert_eq!(svg.matches("<path").count(), 1);
+    assert_eq!(svg.matches('L').count(), 1);
+}
+
+#[test]
+fn see_through_fills_keep_their_opacity() {
+    let mut triangles = two_triangles();
+    triangles[1].color = Color::RGBA(0, 255, 0, 128);
+    triangles[1].blend_mode = BlendMode::Alpha;
+    let svg = render(&triangles, RenderMode::FilledTriangles, &line_style_default());
+    assert!(svg.contains("fill=\"#00ff00\" fill-opacity=\"0.5\""), "{}", svg);
+
+    triangles[1].blend_mode = BlendMode::Cutout(0.75);
+    let svg = render(&triangles, RenderMode::FilledTriangles, &line_style_default());
+    assert!(!svg.contains("#00ff00"));
+}
+
+#[test]
+fn vertex_dots_go_on_top() {
+    let svg = render(&two_triangles(), RenderMode::WireframeWithDot, &line_style_default());
+    assert_eq!(svg.matches("<rect").count(), 1 + 6);
+    assert!(svg.rfind("<path").unwrap() < svg.rfind("<rect").unwrap());
+}
+
+#[test]
+fn debug_views_cant_be_saved() {
+    for render_mode in [RenderMode::Depth, RenderMode::Normals, RenderMode::Overdraw] {
+        assert!(svg_from_triangles(&two_triangles(), 100, 100, render_mode, &line_style_default()).is_err());
+    }
+}
+
+#[test]
+fn points_that_went_off_to_infinity_are_left_out() {
+    let mut triangles = two_triangles();
+    triangles[0].points[1].x = f32::INFINITY;
+    let svg = render(&triangles, RenderMode::FilledTriangles, &line_style_default());
+    assert_eq!(svg.matches("<polygon").count(), 1);
+    assert!(!svg.contains("inf"));
+}
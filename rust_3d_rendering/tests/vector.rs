@@ -0,0 +1,291 @@
+mod common;
+
+use std::f32::consts::PI;
+
+use common::*;
+use rust_3d_rendering::{
+    matrix::{mat4_make_rotation_x, mat4_make_rotation_y, mat4_make_rotation_z, mat4_mul_vec4},
+    quaternion::{quat_from_axis_angle, quat_rotate_vec3},
+    vector::*,
+};
+
+const X_AXIS: Vec3 = Vec3 { x: 1.0, y: 0.0, z: 0.0 };
+const Y_AXIS: Vec3 = Vec3 { x: 0.0, y: 1.0, z: 0.0 };
+const Z_AXIS: Vec3 = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+
+#[test]
+fn length() {
+    assert_eq!(vec2_length(&Vec2 { x: 3.0, y: 4.0 }), 5.0);
+    assert_eq!(vec3_length(&Vec3 { x: 2.0, y: 3.0, z: 6.0 }), 7.0);
+    assert_eq!(vec3_length(&Vec3::default()), 0.0);
+}
+
+#[test]
+fn add_sub_mul_div() {
+    let a2 = Vec2 { x: 1.0, y: 2.0 };
+    let b2 = Vec2 { x: 4.0, y: -3.0 };
+    assert_eq!(vec2_add(&a2, &b2), Vec2 { x: 5.0, y: -1.0 });
+    assert_eq!(vec2_sub(&a2, &b2), Vec2 { x: -3.0, y: 5.0 });
+    assert_eq!(vec2_mul(&a2, 3.0), Vec2 { x: 3.0, y: 6.0 });
+    assert_eq!(vec2_div(&a2, 2.0), Vec2 { x: 0.5, y: 1.0 });
+
+    let a3 = Vec3 { x: 1.0, y: 2.0, z: 3.0 };
+    let b3 = Vec3 { x: -1.0, y: 0.5, z: 4.0 };
+    assert_eq!(vec3_add(&a3, &b3), Vec3 { x: 0.0, y: 2.5, z: 7.0 });
+    assert_eq!(vec3_sub(&a3, &b3), Vec3 { x: 2.0, y: 1.5, z: -1.0 });
+    assert_eq!(vec3_mul(&a3, -2.0), Vec3 { x: -2.0, y: -4.0, z: -6.0 });
+    assert_eq!(vec3_div(&a3, 4.0), Vec3 { x: 0.25, y: 0.5, z: 0.75 });
+}
+
+#[test]
+fn operators_match_functions() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let (a2, b2) = (random_vec2(&mut rng), random_vec2(&mut rng));
+        let (a3, b3) = (random_vec3(&mut rng), random_vec3(&mut rng));
+        let (a4, b4) = (random_vec4(&mut rng), random_vec4(&mut rng));
+        let factor = random_f32(&mut rng);
+
+        assert_eq!(a2 + b2, vec2_add(&a2, &b2));
+        assert_eq!(a2 - b2, vec2_sub(&a2, &b2));
+        assert_eq!(a2 * factor, vec2_mul(&a2, factor));
+        assert_eq!(factor * a2, vec2_mul(&a2, factor));
+        assert_eq!(a2 / factor, vec2_div(&a2, factor));
+
+        assert_eq!(a3 + b3, vec3_add(&a3, &b3));
+        assert_eq!(a3 - b3, vec3_sub(&a3, &b3));
+        assert_eq!(a3 * factor, vec3_mul(&a3, factor));
+        assert_eq!(factor * a3, vec3_mul(&a3, factor));
+        assert_eq!(a3 / factor, vec3_div(&a3, factor));
+        assert_eq!(-a3, vec3_mul(&a3, -1.0));
+        assert_eq!(a3 * b3, Vec3 { x: a3.x * b3.x, y: a3.y * b3.y, z: a3.z * b3.z });
+        assert_eq!(a3 / b3, Vec3 { x: a3.x / b3.x, y: a3.y / b3.y, z: a3.z / b3.z });
+
+        assert_eq!(a4 + b4, Vec4 { x: a4.x + b4.x, y: a4.y + b4.y, z: a4.z + b4.z, w: a4.w + b4.w });
+        assert_eq!(a4 - b4, Vec4 { x: a4.x - b4.x, y: a4.y - b4.y, z: a4.z - b4.z, w: a4.w - b4.w });
+        assert_eq!(-a4, Vec4 { x: -a4.x, y: -a4.y, z: -a4.z, w: -a4.w });
+    }
+}
+
+#[test]
+fn assign_operators() {
+    let mut v = Vec3 { x: 1.0, y: 2.0, z: 3.0 };
+    v += Vec3 { x: 1.0, y: 1.0, z: 1.0 };
+    assert_eq!(v, Vec3 { x: 2.0, y: 3.0, z: 4.0 });
+    v -= Vec3 { x: 2.0, y: 0.0, z: 1.0 };
+    assert_eq!(v, Vec3 { x: 0.0, y: 3.0, z: 3.0 });
+    v *= 2.0;
+    assert_eq!(v, Vec3 { x: 0.0, y: 6.0, z: 6.0 });
+    v /= 3.0;
+    assert_eq!(v, Vec3 { x: 0.0, y: 2.0, z: 2.0 });
+
+    let mut w = Vec4 { x: 1.0, y: -1.0, z: 0.0, w: 2.0 };
+    w -= Vec4 { x: 3.0, y: 2.0, z: 1.0, w: 0.5 };
+    assert_eq!(w, Vec4 { x: -2.0, y: -3.0, z: -1.0, w: 1.5 });
+}
+
+#[test]
+fn normalize() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let mut v2 = random_vec2(&mut rng);
+        let mut v3 = random_vec3(&mut rng);
+        let expected2 = vec2_normalize_or_zero(&v2);
+        let expected3 = vec3_normalize_or_zero(&v3);
+        vec2_normalize(&mut v2);
+        vec3_normalize(&mut v3);
+
+        assert_f32_close(vec2_length(&v2), 1.0, EPSILON);
+        assert_f32_close(vec3_length(&v3), 1.0, EPSILON);
+        assert_eq!(v2, expected2);
+        assert_eq!(v3, expected3);
+    }
+}
+
+#[test]
+fn normalize_or_zero_of_zero() {
+    assert_eq!(vec2_normalize_or_zero(&Vec2::default()), Vec2::default());
+    assert_eq!(vec3_normalize_or_zero(&Vec3::default()), Vec3::default());
+    let infinite = Vec3 { x: f32::INFINITY, y: 0.0, z: 0.0 };
+    assert_eq!(vec3_normalize_or_zero(&infinite), Vec3::default());
+}
+
+#[test]
+fn dot() {
+    assert_eq!(vec2_dot(&Vec2 { x: 1.0, y: 2.0 }, &Vec2 { x: 3.0, y: -4.0 }), -5.0);
+    assert_eq!(vec3_dot(&Vec3 { x: 1.0, y: 2.0, z: 3.0 }, &Vec3 { x: 4.0, y: 5.0, z: 6.0 }), 32.0);
+    assert_eq!(vec3_dot(&X_AXIS, &Y_AXIS), 0.0);
+}
+
+#[test]
+fn dot_is_length_squared() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let v = random_vec3(&mut rng);
+        assert_f32_close(vec3_dot(&v, &v), vec3_length(&v).powi(2), EPSILON);
+    }
+}
+
+#[test]
+fn cross_of_axes() {
+    assert_eq!(vec3_cross(&X_AXIS, &Y_AXIS), Z_AXIS);
+    assert_eq!(vec3_cross(&Y_AXIS, &Z_AXIS), X_AXIS);
+    assert_eq!(vec3_cross(&Z_AXIS, &X_AXIS), Y_AXIS);
+    assert_eq!(vec3_cross(&Y_AXIS, &X_AXIS), -Z_AXIS);
+}
+
+#[test]
+fn cross_is_orthogonal_and_anticommutative() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let a = random_vec3(&mut rng);
+        let b = random_vec3(&mut rng);
+        let cross = vec3_cross(&a, &b);
+        let scale = vec3_length(&a) * vec3_length(&b) * vec3_length(&cross);
+
+        assert!(vec3_dot(&cross, &a).abs() <= EPSILON * scale.max(1.0));
+        assert!(vec3_dot(&cross, &b).abs() <= EPSILON * scale.max(1.0));
+        assert_eq!(vec3_cross(&b, &a), -cross);
+        assert_eq!(vec3_cross(&a, &a), Vec3::default());
+    }
+}
+
+#[test]
+fn rotate_quarter_turns() {
+    // counterclockwise looking down each axis towards the origin
+    assert_vec3_close(&vec3_rotate_x(&Y_AXIS, PI / 2.0), &Z_AXIS, EPSILON);
+    assert_vec3_close(&vec3_rotate_y(&Z_AXIS, PI / 2.0), &X_AXIS, EPSILON);
+    assert_vec3_close(&vec3_rotate_z(&X_AXIS, PI / 2.0), &Y_AXIS, EPSILON);
+}
+
+#[test]
+fn rotate_preserves_length_and_axis() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let v = random_vec3(&mut rng);
+        let angle = random_angle(&mut rng);
+        let length = vec3_length(&v);
+
+        let rotated_x = vec3_rotate_x(&v, angle);
+        let rotated_y = vec3_rotate_y(&v, angle);
+        let rotated_z = vec3_rotate_z(&v, angle);
+        assert_f32_close(vec3_length(&rotated_x), length, EPSILON);
+        assert_f32_close(vec3_length(&rotated_y), length, EPSILON);
+        assert_f32_close(vec3_length(&rotated_z), length, EPSILON);
+        assert_eq!(rotated_x.x, v.x);
+        assert_eq!(rotated_y.y, v.y);
+        assert_eq!(rotated_z.z, v.z);
+
+        // turning back by the same angle undoes it
+        assert_vec3_close(&vec3_rotate_y(&rotated_y, -angle), &v, 1e-4);
+    }
+}
+
+// the direct rotations, the rotation matrices and the quaternions all have
+// to agree on which way is positive
+#[test]
+fn rotate_matches_matrix_and_quaternion() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let v = random_vec3(&mut rng);
+        let angle = random_angle(&mut rng);
+        let v4 = vec4_from_vec3(&v);
+
+        let cases = [
+            (vec3_rotate_x(&v, angle), mat4_make_rotation_x(angle), X_AXIS),
+            (vec3_rotate_y(&v, angle), mat4_make_rotation_y(angle), Y_AXIS),
+            (vec3_rotate_z(&v, angle), mat4_make_rotation_z(angle), Z_AXIS),
+        ];
+        for (direct, matrix, axis) in cases {
+            let by_matrix = vec3_from_vec4(&mat4_mul_vec4(&matrix, &v4));
+            let by_quaternion = quat_rotate_vec3(&quat_from_axis_angle(&axis, angle), &v);
+            assert_vec3_close(&direct, &by_matrix, 1e-4);
+            assert_vec3_close(&direct, &by_quaternion, 1e-4);
+        }
+    }
+}
+
+#[test]
+fn conversions() {
+    let v3 = Vec3 { x: 1.0, y: 2.0, z: 3.0 };
+    let v4 = vec4_from_vec3(&v3);
+    assert_eq!(v4, Vec4 { x: 1.0, y: 2.0, z: 3.0, w: 1.0 });
+    assert_eq!(vec3_from_vec4(&Vec4 { x: 1.0, y: 2.0, z: 3.0, w: 7.0 }), v3);
+    assert_eq!(vec2_from_vec3(&v3), Vec2 { x: 1.0, y: 2.0 });
+    assert_eq!(vec2_from_vec4(&v4), Vec2 { x: 1.0, y: 2.0 });
+
+    assert_eq!(Vec4::from(v3), v4);
+    assert_eq!(Vec3::from(v4), v3);
+    assert_eq!(Vec2::from(v3), Vec2 { x: 1.0, y: 2.0 });
+    assert_eq!(Vec2::from(v4), Vec2 { x: 1.0, y: 2.0 });
+    assert_eq!(Vec2::from([5.0, 6.0]), Vec2 { x: 5.0, y: 6.0 });
+    assert_eq!(Vec3::from([1.0, 2.0, 3.0]), v3);
+    assert_eq!(Vec4::from([1.0, 2.0, 3.0, 1.0]), v4);
+    assert_eq!(<[f32; 2]>::from(Vec2 { x: 5.0, y: 6.0 }), [5.0, 6.0]);
+    assert_eq!(<[f32; 3]>::from(v3), [1.0, 2.0, 3.0]);
+    assert_eq!(<[f32; 4]>::from(v4), [1.0, 2.0, 3.0, 1.0]);
+}
+
+#[test]
+fn lerp() {
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let (a2, b2) = (random_vec2(&mut rng), random_vec2(&mut rng));
+        let (a3, b3) = (random_vec3(&mut rng), random_vec3(&mut rng));
+        let (a4, b4) = (random_vec4(&mut rng), random_vec4(&mut rng));
+
+        assert_eq!(vec2_lerp(&a2, &b2, 0.0), a2);
+        assert_vec2_close(&vec2_lerp(&a2, &b2, 1.0), &b2, EPSILON);
+        assert_eq!(vec3_lerp(&a3, &b3, 0.0), a3);
+        assert_vec3_close(&vec3_lerp(&a3, &b3, 1.0), &b3, EPSILON);
+        assert_vec3_close(&vec3_lerp(&a3, &b3, 0.5), &((a3 + b3) * 0.5), EPSILON);
+        assert_eq!(vec4_lerp(&a4, &b4, 0.0), a4);
+        assert_vec4_close(&vec4_lerp(&a4, &b4, 1.0), &b4, EPSILON);
+    }
+}
+
+#[test]
+fn distance() {
+    assert_eq!(vec2_distance(&Vec2 { x: 1.0, y: 1.0 }, &Vec2 { x: 4.0, y: 5.0 }), 5.0);
+    assert_eq!(vec3_distance(&Vec3 { x: 1.0, y: 1.0, z: 1.0 }, &Vec3 { x: 3.0, y: 4.0, z: 7.0 }), 7.0);
+
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let (a, b) = (random_vec3(&mut rng), random_vec3(&mut rng));
+        assert_eq!(vec3_distance(&a, &b), vec3_distance(&b, &a));
+    }
+}
+
+#[test]
+fn reflect() {
+    let incident = Vec3 { x: 1.0, y: -1.0, z: 0.0 };
+    assert_eq!(vec3_reflect(&incident, &Y_AXIS), Vec3 { x: 1.0, y: 1.0, z: 0.0 });
+
+    // reflecting keeps the length and flips the part along the normal
+    let mut rng = test_rng();
+    for _ in 0..PROPERTY_CASES {
+        let incident = random_vec3(&mut rng);
+        let normal = vec3_normalize_or_zero(&random_vec3(&mut rng));
+        let reflected = vec3_reflect(&incident, &normal);
+        assert_f32_close(vec3_length(&reflected), vec3_length(&incident), 1e-4);
+        assert_f32_close(vec3_dot(&reflected, &normal), -vec3_dot(&incident, &normal), 1e-4);
+    }
+}
+
+#[test]
+fn min_max() {
+    let a2 = Vec2 { x: 1.0, y: 5.0 };
+    let b2 = Vec2 { x: 3.0, y: -2.0 };
+    assert_eq!(vec2_min(&a2, &b2), Vec2 { x: 1.0, y: -2.0 });
+    assert_eq!(vec2_max(&a2, &b2), Vec2 { x: 3.0, y: 5.0 });
+
+    let a3 = Vec3 { x: 1.0, y: 5.0, z: 0.0 };
+    let b3 = Vec3 { x: 3.0, y: -2.0, z: 0.5 };
+    assert_eq!(vec3_min(&a3, &b3), Vec3 { x: 1.0, y: -2.0, z: 0.0 });
+    assert_eq!(vec3_max(&a3, &b3), Vec3 { x: 3.0, y: 5.0, z: 0.5 });
+
+    let a4 = Vec4 { x: 1.0, y: 5.0, z: 0.0, w: -1.0 };
+    let b4 = Vec4 { x: 3.0, y: -2.0, z: 0.5, w: -3.0 };
+    assert_eq!(vec4_min(&a4, &b4), Vec4 { x: 1.0, y: -2.0, z: 0.0, w: -3.0 });
+    assert_eq!(vec4_max(&a4, &b4), Vec4 { x: 3.0, y: 5.0, z: 0.5, w: -1.0 });
+}
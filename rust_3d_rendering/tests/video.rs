@@ -0,0 +1,138 @@
+use std::{fs, path::PathBuf};
+
+use rust_3d_rendering::video::{
+    VIDEO_FORMAT_NAMES, VideoFormat, avi_riff_size, frame_rate_fraction, video_format_from_name, video_format_from_path,
+    video_format_name, video_recorder_add_frame, video_recorder_finish, video_recorder_new,
+};
+
+fn out_path(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("video");
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+// a frame that's all one colour
+fn solid_frame(width: usize, height: usize, rgb: [u8; 3]) -> Vec<u8> {
+    (0..width * height).flat_map(|_| [rgb[0], rgb[1], rgb[2], 255]).collect()
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[test]
+fn names_round_trip() {
+    for (name, format) in VIDEO_FORMAT_NAMES {
+        assert_eq!(video_format_from_name(name), Some(format));
+        assert_eq!(video_format_name(format), name);
+    }
+    assert_eq!(video_format_from_path(&PathBuf::from("clip.Y4M")), Some(VideoFormat::Y4m));
+    assert_eq!(video_format_from_path(&PathBuf::from("clip.mp4")), None);
+    assert_eq!(video_format_from_path(&PathBuf::from("clip")), None);
+    assert!(video_recorder_new(&out_path("clip.mp4"), 4, 4, 30.0).is_err());
+}
+
+#[test]
+fn frame_rates_are_whole_fractions() {
+    assert_eq!(frame_rate_fraction(30.0), (30, 1));
+    assert_eq!(frame_rate_fraction(29.97), (2997, 100));
+    assert_eq!(frame_rate_fraction(12.5), (25, 2));
+}
+
+#[test]
+fn y4m_frames_are_studio_range_420() {
+    let path = out_path("solid.y4m");
+    let (width, height) = (5, 3);
+    let mut recorder = video_recorder_new(&path, width, height, 25.0).unwrap();
+    video_recorder_add_frame(&mut recorder, &solid_frame(width, height, [255, 255, 255]), 0.0).unwrap();
+    video_recorder_add_frame(&mut recorder, &solid_frame(width, height, [255, 0, 0]), 0.04).unwrap();
+    video_recorder_finish(recorder).unwrap();
+
+    let bytes = fs::read(&path).unwrap();
+    let header = b"YUV4MPEG2 W5 H3 F25:1 Ip A1:1 C420jpeg\n";
+    assert_eq!(&bytes[..header.len()], header);
+    // the chroma planes round up to 3x2 for an odd size
+    let (luma, chroma) = (width * height, 3 * 2);
+    let frame_size = b"FRAME\n".len() + luma + chroma * 2;
+    assert_eq!(bytes.len(), header.len() + frame_size * 2);
+
+    let white = &bytes[header.len() + 6..header.len() + frame_size];
+    assert!(white[..luma].iter().all(|&y| y == 235));
+    assert!(white[luma..].iter().all(|&c| c == 128));
+    let red = &bytes[header.len() + frame_size + 6..];
+    assert!(red[..luma].iter().all(|&y| y == 82));
+    assert!(red[luma..luma + chroma].iter().all(|&cb| cb == 90));
+    assert!(red[luma + chroma..].iter().all(|&cr| cr == 240));
+}
+
+#[test]
+fn frames_are_repeated_or_skipped_to_keep_time() {
+    let path = out_path("timing.y4m");
+    let frame = solid_frame(2, 2, [0, 0, 0]);
+    let mut recorder = video_recorder_new(&path, 2, 2, 10.0).unwrap();
+    video_recorder_add_frame(&mut recorder, &frame, 0.0).unwrap();
+    assert_eq!(recorder.frames_written, 1);
+    // a slow frame fills in the frames that went by
+    video_recorder_add_frame(&mut recorder, &frame, 0.3).unwrap();
+    assert_eq!(recorder.frames_written, 4);
+    // and fast ones that don't reach the next frame are left out
+    video_recorder_add_frame(&mut recorder, &frame, 0.32).unwrap();
+    video_recorder_add_frame(&mut recorder, &frame, 0.34).unwrap();
+    assert_eq!(recorder.frames_written, 4);
+    video_recorder_add_frame(&mut recorder, &frame, 0.4).unwrap();
+    assert_eq!(recorder.frames_written, 5);
+    assert!(video_recorder_add_frame(&mut recorder, &solid_frame(3, 2, [0, 0, 0]), 0.5).is_err());
+    video_recorder_finish(recorder).unwrap();
+}
+
+#[test]
+fn avi_frames_are_bottom_up_bgr_with_an_index() {
+    let path = out_path("frames.avi");
+    let (width, height) = (3, 2);
+    // red on the top row, blue on the bottom one
+    let mut rgba = solid_frame(width, 1, [255, 0, 0]);
+    rgba.extend(solid_frame(width, 1, [0, 0, 255]));
+    let mut recorder = video_recorder_new(&path, width, height, 24.0).unwrap();
+    for frame in 0..3 {
+        video_recorder_add_frame(&mut recorder, &rgba, frame as f32 / 24.0).unwrap();
+    }
+    video_recorder_finish(recorder).unwrap();
+
+    let bytes = fs::read(&path).unwrap();
+    assert_eq!(&bytes[0..4], b"RIFF");
+    assert_eq!(read_u32(&bytes, 4) as usize, bytes.len() - 8);
+    assert_eq!(read_u32(&bytes, 4) as u64, avi_riff_size(4 + 3 * (8 + 12 * 2), 3));
+    assert_eq!(&bytes[8..12], b"AVI ");
+    // total frames in the main header and the stream's length
+    assert_eq!(read_u32(&bytes, 48), 3);
+    assert_eq!(read_u32(&bytes, 140), 3);
+    assert_eq!((read_u32(&bytes, 128), read_u32(&bytes, 132)), (1, 24));
+
+    // rows of 9 bytes padded to 12
+    let row_size = 12;
+    let movi = bytes.windows(4).position(|w| w == b"movi").unwrap();
+    let chunk = movi + 4;
+    assert_eq!(&bytes[chunk..chunk + 4], b"00db");
+    assert_eq!(read_u32(&bytes, chunk + 4) as usize, row_size * height);
+    let pixels = &bytes[chunk + 8..chunk + 8 + row_size * height];
+    assert_eq!(&pixels[0..3], &[255, 0, 0]);
+    assert_eq!(&pixels[row_size..row_size + 3], &[0, 0, 255]);
+
+    let index = bytes.windows(4).rposition(|w| w == b"idx1").unwrap();
+    assert_eq!(read_u32(&bytes, index + 4), 3 * 16);
+    assert_eq!(index + 8 + 3 * 16, bytes.len());
+    for frame in 0..3 {
+        let entry = index + 8 + frame * 16;
+        assert_eq!(&bytes[entry..entry + 4], b"00db");
+        let offset = read_u32(&bytes, entry + 8) as usize;
+        assert_eq!(&bytes[movi + offset..movi + offset + 4], b"00db");
+    }
+}
+
+#[test]
+fn avi_size_leaves_room_for_the_index() {
+    // the headers, the movi list's type and an empty idx1
+    assert_eq!(avi_riff_size(4, 0), 224 - 8 + 8);
+    // each frame adds its chunk and a 16 byte index entry
+    assert_eq!(avi_riff_size(4 + 100, 1), avi_riff_size(4, 0) + 100 + 16);
+}